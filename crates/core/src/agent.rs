@@ -0,0 +1,653 @@
+use anyhow::{Context, Result, bail};
+use serde::Deserialize;
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+use crate::permissions::{PermissionDecision, PermissionPolicy, ToolKind};
+use crate::protocol::{Event, Usage};
+
+/// Model steps a single prompt may take before the turn is stopped; the
+/// default of the `limits.max_turns` setting.
+pub const DEFAULT_MAX_TURNS: usize = 50;
+
+/// A message in the conversation sent to the model.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Message {
+    User(String),
+    Assistant(String),
+    ToolCall {
+        id: String,
+        name: String,
+        input: String,
+    },
+    ToolResult {
+        id: String,
+        is_error: bool,
+        output: String,
+    },
+}
+
+/// Everything the model reads: the system prompt and the messages so far.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Conversation {
+    pub system_prompt: String,
+    pub messages: Vec<Message>,
+}
+
+impl Conversation {
+    /// The text of the newest user message.
+    pub fn last_user_message(&self) -> Option<&str> {
+        self.messages
+            .iter()
+            .rev()
+            .find_map(|message| match message {
+                Message::User(text) => Some(text.as_str()),
+                _ => None,
+            })
+    }
+}
+
+/// What the model does next.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ModelStep {
+    /// Answers the user, which ends the turn.
+    Answer { text: String, usage: Usage },
+    /// Asks for a tool to run; the model is called again with its result.
+    ToolCall { name: String, input: String },
+}
+
+/// Produces the next step of a turn from the conversation.
+pub trait Model: fmt::Debug {
+    fn next_step(&mut self, conversation: &Conversation) -> Result<ModelStep>;
+
+    /// Sends later steps to another deployment. Models that are not backed
+    /// by a deployment ignore it.
+    fn set_deployment(&mut self, _deployment: &str) {}
+}
+
+/// Stands in for a deployment until the chat client lands: repeats the
+/// prompt back without calling any tools.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EchoModel;
+
+impl Model for EchoModel {
+    fn next_step(&mut self, conversation: &Conversation) -> Result<ModelStep> {
+        let prompt = conversation.last_user_message().unwrap_or_default();
+        Ok(ModelStep::Answer {
+            text: format!("You said: {}", prompt.trim()),
+            usage: Usage::default(),
+        })
+    }
+}
+
+/// A tool the model may call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tool {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub kind: ToolKind,
+}
+
+/// Tools offered to the model. Input is a JSON object, e.g.
+/// `{"path": "src/lib.rs"}`.
+pub const TOOLS: &[Tool] = &[
+    Tool {
+        name: "read_file",
+        description: "Read a file in the workspace: {\"path\"}",
+        kind: ToolKind::Read,
+    },
+    Tool {
+        name: "write_file",
+        description: "Create or replace a file in the workspace: {\"path\", \"content\"}",
+        kind: ToolKind::Edit,
+    },
+];
+
+pub fn find_tool(name: &str) -> Option<&'static Tool> {
+    TOOLS.iter().find(|tool| tool.name == name)
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PathInput {
+    path: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct WriteInput {
+    path: String,
+    content: String,
+}
+
+/// A change to one file, checked against the disk before it is written.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileEdit {
+    /// Relative to the workspace root.
+    pub path: PathBuf,
+    /// Contents the edit was made against; `None` creates a new file.
+    pub old: Option<String>,
+    pub new: String,
+}
+
+impl FileEdit {
+    /// Writes the edit under `root`, refusing to if the file changed since
+    /// `old` was read or, for a new file, if one has appeared since.
+    pub fn apply(&self, root: &Path) -> Result<()> {
+        let path = resolve_in_workspace(root, &self.path)?;
+        let display = self.path.display();
+        match &self.old {
+            Some(old) => {
+                let current = fs::read_to_string(&path)
+                    .with_context(|| format!("Failed to read {display}"))?;
+                if current != *old {
+                    bail!("{display} changed on disk since the edit was proposed");
+                }
+                fs::write(&path, &self.new).with_context(|| format!("Failed to write {display}"))
+            }
+            None => {
+                let mut file = match OpenOptions::new().write(true).create_new(true).open(&path) {
+                    Err(err) if err.kind() == ErrorKind::AlreadyExists => {
+                        bail!("{display} was created on disk since the edit was proposed")
+                    }
+                    result => result.with_context(|| format!("Failed to create {display}"))?,
+                };
+                file.write_all(self.new.as_bytes())
+                    .with_context(|| format!("Failed to write {display}"))
+            }
+        }
+    }
+}
+
+/// Resolves `path` under `root`, following symlinks, and refuses anything
+/// that ends up outside it. The file need not exist, but its directory must.
+pub fn resolve_in_workspace(root: &Path, path: &Path) -> Result<PathBuf> {
+    let root = root
+        .canonicalize()
+        .context("Failed to resolve the workspace root")?;
+    let joined = root.join(path);
+    let resolved = match joined.canonicalize() {
+        Ok(resolved) => resolved,
+        Err(_) => {
+            let (Some(parent), Some(name)) = (joined.parent(), joined.file_name()) else {
+                bail!("{} is not a file path", path.display());
+            };
+            let parent = parent
+                .canonicalize()
+                .with_context(|| format!("No directory for {}", path.display()))?;
+            parent.join(name)
+        }
+    };
+    if !resolved.starts_with(&root) {
+        bail!("{} is outside the workspace", path.display());
+    }
+    Ok(resolved)
+}
+
+/// A tool call waiting for the user to allow or deny it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingTool {
+    pub id: String,
+    pub name: String,
+    pub input: String,
+    /// The change an edit tool would make, for showing it as a diff.
+    pub edit: Option<FileEdit>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum TurnState {
+    Idle,
+    Running { steps: usize },
+    Waiting { steps: usize, tool: PendingTool },
+}
+
+/// Runs turns against a model. Every tool call is checked against the
+/// permission policy before it runs, and everything that happens is
+/// reported as protocol events, whether the session is the console or a
+/// headless run.
+#[derive(Debug)]
+pub struct Agent {
+    model: Box<dyn Model>,
+    pub conversation: Conversation,
+    pub policy: PermissionPolicy,
+    /// Nobody is around to answer permission requests, so tools the policy
+    /// would ask about are denied instead.
+    pub unattended: bool,
+    pub max_turns: usize,
+    root: PathBuf,
+    state: TurnState,
+    events: VecDeque<Event>,
+    /// Edits written by tools, until taken with `take_edits`.
+    edits: Vec<FileEdit>,
+    next_id: usize,
+}
+
+impl Agent {
+    pub fn new(model: Box<dyn Model>, root: impl Into<PathBuf>, policy: PermissionPolicy) -> Self {
+        Self {
+            model,
+            conversation: Conversation::default(),
+            policy,
+            unattended: false,
+            max_turns: DEFAULT_MAX_TURNS,
+            root: root.into(),
+            state: TurnState::Idle,
+            events: VecDeque::new(),
+            edits: Vec::new(),
+            next_id: 1,
+        }
+    }
+
+    pub fn set_system_prompt(&mut self, prompt: String) {
+        self.conversation.system_prompt = prompt;
+    }
+
+    /// Switches the model to another deployment for the following steps.
+    pub fn set_model(&mut self, deployment: &str) {
+        self.model.set_deployment(deployment);
+    }
+
+    /// Whether a turn has started and not yet finished.
+    pub fn is_running(&self) -> bool {
+        self.state != TurnState::Idle || !self.events.is_empty()
+    }
+
+    /// The tool call waiting for approval, if any.
+    pub fn pending(&self) -> Option<&PendingTool> {
+        match &self.state {
+            TurnState::Waiting { tool, .. } => Some(tool),
+            _ => None,
+        }
+    }
+
+    /// Starts a turn with `text` from the user.
+    pub fn submit(&mut self, text: &str) -> Result<()> {
+        if self.state != TurnState::Idle {
+            bail!("A turn is already running");
+        }
+        self.conversation
+            .messages
+            .push(Message::User(text.to_string()));
+        self.state = TurnState::Running { steps: 0 };
+        Ok(())
+    }
+
+    /// The next event of the running turn. Returns `None` once the turn is
+    /// over, or while it waits for `respond` or `complete`.
+    pub fn step(&mut self) -> Option<Event> {
+        if let Some(event) = self.events.pop_front() {
+            return Some(event);
+        }
+        let TurnState::Running { steps } = self.state else {
+            return None;
+        };
+        if steps >= self.max_turns {
+            self.state = TurnState::Idle;
+            return Some(Event::Error {
+                message: format!("Stopped after {steps} model steps"),
+            });
+        }
+
+        self.state = TurnState::Running { steps: steps + 1 };
+        match self.model.next_step(&self.conversation) {
+            Ok(ModelStep::Answer { text, usage }) => {
+                self.conversation
+                    .messages
+                    .push(Message::Assistant(text.clone()));
+                self.events.push_back(Event::MessageDelta { text });
+                self.events.push_back(Event::Usage(usage));
+                self.state = TurnState::Idle;
+            }
+            Ok(ModelStep::ToolCall { name, input }) => self.call_tool(steps + 1, name, input),
+            Err(err) => {
+                self.events.push_back(Event::Error {
+                    message: format!("{err:#}"),
+                });
+                self.state = TurnState::Idle;
+            }
+        }
+        self.events.pop_front()
+    }
+
+    fn call_tool(&mut self, steps: usize, name: String, input: String) {
+        let id = format!("tool-{}", self.next_id);
+        self.next_id += 1;
+        self.conversation.messages.push(Message::ToolCall {
+            id: id.clone(),
+            name: name.clone(),
+            input: input.clone(),
+        });
+        self.events.push_back(Event::ToolUse {
+            id: id.clone(),
+            name: name.clone(),
+            input: input.clone(),
+        });
+
+        let Some(tool) = find_tool(&name) else {
+            self.finish_tool(&id, Err(format!("Unknown tool '{name}'")));
+            return;
+        };
+        match self.policy.decide(tool.kind) {
+            PermissionDecision::Allow => {
+                let result = self.run_tool(&name, &input);
+                self.finish_tool(&id, result);
+            }
+            PermissionDecision::Deny => self.finish_tool(
+                &id,
+                Err(format!("{} mode does not allow {name}", self.policy)),
+            ),
+            PermissionDecision::Ask if self.unattended => self.finish_tool(
+                &id,
+                Err(format!(
+                    "{name} needs approval in {} mode and nobody can give it",
+                    self.policy
+                )),
+            ),
+            PermissionDecision::Ask => match proposed_edit(&self.root, &name, &input) {
+                Ok(edit) => {
+                    self.events.push_back(Event::PermissionRequest {
+                        id: id.clone(),
+                        tool: name.clone(),
+                        input: input.clone(),
+                    });
+                    self.state = TurnState::Waiting {
+                        steps,
+                        tool: PendingTool {
+                            id,
+                            name,
+                            input,
+                            edit,
+                        },
+                    };
+                }
+                Err(err) => self.finish_tool(&id, Err(format!("{err:#}"))),
+            },
+        }
+    }
+
+    /// Records a tool's result for the model and reports it.
+    fn finish_tool(&mut self, id: &str, result: Result<String, String>) {
+        let (is_error, output) = match result {
+            Ok(output) => (false, output),
+            Err(error) => (true, error),
+        };
+        self.conversation.messages.push(Message::ToolResult {
+            id: id.to_string(),
+            is_error,
+            output: output.clone(),
+        });
+        self.events.push_back(Event::ToolResult {
+            id: id.to_string(),
+            is_error,
+            output,
+        });
+    }
+
+    /// Answers the pending permission request: an allowed tool runs, a
+    /// denied one reports the refusal to the model.
+    pub fn respond(&mut self, id: &str, allow: bool) -> Result<()> {
+        let tool = self.take_pending(id)?;
+        let result = if allow {
+            self.run_tool(&tool.name, &tool.input)
+        } else {
+            Err(format!("The user denied {}", tool.name))
+        };
+        self.finish_tool(&tool.id, result);
+        Ok(())
+    }
+
+    /// Finishes the pending tool call with a result produced elsewhere, e.g.
+    /// an edit the user applied only in part.
+    pub fn complete(&mut self, id: &str, is_error: bool, output: &str) -> Result<()> {
+        let tool = self.take_pending(id)?;
+        let result = if is_error {
+            Err(output.to_string())
+        } else {
+            Ok(output.to_string())
+        };
+        self.finish_tool(&tool.id, result);
+        Ok(())
+    }
+
+    fn take_pending(&mut self, id: &str) -> Result<PendingTool> {
+        match std::mem::replace(&mut self.state, TurnState::Idle) {
+            TurnState::Waiting { steps, tool } if tool.id == id => {
+                self.state = TurnState::Running { steps };
+                Ok(tool)
+            }
+            state => {
+                self.state = state;
+                bail!("No permission request '{id}' is pending")
+            }
+        }
+    }
+
+    /// Stops the running turn, returning whether there was one. The turn
+    /// ends with an error event saying so.
+    pub fn interrupt(&mut self) -> bool {
+        if self.state == TurnState::Idle {
+            return false;
+        }
+        if let TurnState::Waiting { tool, .. } = &self.state {
+            let id = tool.id.clone();
+            self.finish_tool(&id, Err("Interrupted".to_string()));
+        }
+        self.events.push_back(Event::Error {
+            message: "Interrupted".to_string(),
+        });
+        self.state = TurnState::Idle;
+        true
+    }
+
+    /// The edits tools have written since the last call, for recording them
+    /// as session changes.
+    pub fn take_edits(&mut self) -> Vec<FileEdit> {
+        std::mem::take(&mut self.edits)
+    }
+
+    /// Runs an allowed tool, returning its output or what went wrong.
+    fn run_tool(&mut self, name: &str, input: &str) -> Result<String, String> {
+        let root = &self.root;
+        let result = match name {
+            "read_file" => parse_input::<PathInput>(input).and_then(|input| {
+                let path = resolve_in_workspace(root, Path::new(&input.path))?;
+                fs::read_to_string(&path).with_context(|| format!("Failed to read {}", input.path))
+            }),
+            "write_file" => proposed_edit(root, name, input).and_then(|edit| {
+                let edit = edit.context("write_file proposed no edit")?;
+                edit.apply(root)?;
+                let output = format!("Wrote {}", edit.path.display());
+                self.edits.push(edit);
+                Ok(output)
+            }),
+            _ => Err(anyhow::anyhow!("Unknown tool '{name}'")),
+        };
+        result.map_err(|err| format!("{err:#}"))
+    }
+}
+
+/// The file change an edit tool would make, read against the disk now.
+fn proposed_edit(root: &Path, name: &str, input: &str) -> Result<Option<FileEdit>> {
+    if name != "write_file" {
+        return Ok(None);
+    }
+    let input = parse_input::<WriteInput>(input)?;
+    let path = resolve_in_workspace(root, Path::new(&input.path))?;
+    let old = match fs::read_to_string(&path) {
+        Ok(old) => Some(old),
+        Err(err) if err.kind() == ErrorKind::NotFound => None,
+        Err(err) => return Err(err).with_context(|| format!("Failed to read {}", input.path)),
+    };
+    Ok(Some(FileEdit {
+        path: PathBuf::from(input.path),
+        old,
+        new: input.content,
+    }))
+}
+
+fn parse_input<T: for<'de> Deserialize<'de>>(input: &str) -> Result<T> {
+    serde_json::from_str(input).context("Invalid tool input")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{ScriptedModel, temp_dir};
+
+    fn run_turn(agent: &mut Agent, text: &str) -> Vec<Event> {
+        agent.submit(text).unwrap();
+        std::iter::from_fn(|| agent.step()).collect()
+    }
+
+    fn write_step(path: &str, content: &str) -> ModelStep {
+        ModelStep::ToolCall {
+            name: "write_file".to_string(),
+            input: serde_json::json!({ "path": path, "content": content }).to_string(),
+        }
+    }
+
+    #[test]
+    fn test_echo_model_answers_the_prompt() {
+        // Test a turn with the stand-in model is one answer and its usage
+        let mut agent = Agent::new(Box::new(EchoModel), ".", PermissionPolicy::Default);
+        let events = run_turn(&mut agent, "hello");
+        assert_eq!(
+            events,
+            [
+                Event::MessageDelta {
+                    text: "You said: hello".to_string()
+                },
+                Event::Usage(Usage::default()),
+            ]
+        );
+        assert!(!agent.is_running());
+    }
+
+    #[test]
+    fn test_policy_is_checked_before_tools_run() {
+        // Test reads run, read-only denies edits and unattended runs never ask
+        let dir = temp_dir();
+        fs::write(dir.path().join("notes.txt"), "remember").unwrap();
+        let read = ModelStep::ToolCall {
+            name: "read_file".to_string(),
+            input: r#"{"path":"notes.txt"}"#.to_string(),
+        };
+        let model = ScriptedModel::new([read, write_step("new.txt", "x")]);
+        let mut agent = Agent::new(Box::new(model), dir.path(), PermissionPolicy::ReadOnly);
+        let results = run_turn(&mut agent, "go")
+            .into_iter()
+            .filter_map(|event| match event {
+                Event::ToolResult {
+                    is_error, output, ..
+                } => Some((is_error, output)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(results[0], (false, "remember".to_string()));
+        assert_eq!(
+            results[1],
+            (true, "read-only mode does not allow write_file".to_string())
+        );
+        assert!(!dir.path().join("new.txt").exists());
+
+        let model = ScriptedModel::new([write_step("new.txt", "x")]);
+        let mut agent = Agent::new(Box::new(model), dir.path(), PermissionPolicy::Default);
+        agent.unattended = true;
+        let events = run_turn(&mut agent, "go");
+        assert!(
+            !events
+                .iter()
+                .any(|event| matches!(event, Event::PermissionRequest { .. }))
+        );
+        assert!(!dir.path().join("new.txt").exists());
+    }
+
+    #[test]
+    fn test_permission_requests_wait_for_an_answer() {
+        // Test an edit waits for approval, then runs or reports the denial
+        let dir = temp_dir();
+        let model = ScriptedModel::new([write_step("a.txt", "one"), write_step("b.txt", "two")]);
+        let mut agent = Agent::new(Box::new(model), dir.path(), PermissionPolicy::Default);
+        let events = run_turn(&mut agent, "go");
+        assert!(matches!(
+            events.last(),
+            Some(Event::PermissionRequest { .. })
+        ));
+        let pending = agent.pending().unwrap().clone();
+        assert_eq!(pending.edit.unwrap().old, None);
+        assert!(agent.respond("tool-9", true).is_err());
+
+        agent.respond(&pending.id, true).unwrap();
+        std::iter::from_fn(|| agent.step()).for_each(drop);
+        assert_eq!(fs::read_to_string(dir.path().join("a.txt")).unwrap(), "one");
+        assert_eq!(agent.take_edits()[0].path, PathBuf::from("a.txt"));
+        assert!(agent.take_edits().is_empty());
+
+        let id = agent.pending().unwrap().id.clone();
+        agent.respond(&id, false).unwrap();
+        let events = std::iter::from_fn(|| agent.step()).collect::<Vec<_>>();
+        assert!(events.contains(&Event::ToolResult {
+            id,
+            is_error: true,
+            output: "The user denied write_file".to_string(),
+        }));
+        assert!(!dir.path().join("b.txt").exists());
+        assert!(!agent.is_running());
+    }
+
+    #[test]
+    fn test_interrupt_ends_a_waiting_turn() {
+        // Test interrupting answers the pending tool and ends the turn
+        let dir = temp_dir();
+        let model = ScriptedModel::new([write_step("a.txt", "one")]);
+        let mut agent = Agent::new(Box::new(model), dir.path(), PermissionPolicy::Default);
+        run_turn(&mut agent, "go");
+        assert!(agent.interrupt());
+        let events = std::iter::from_fn(|| agent.step()).collect::<Vec<_>>();
+        assert!(
+            matches!(events.last(), Some(Event::Error { message }) if message == "Interrupted")
+        );
+        assert!(!agent.is_running());
+        assert!(!agent.interrupt());
+    }
+
+    #[test]
+    fn test_edits_stay_inside_the_workspace_and_check_the_disk() {
+        // Test paths outside the root are refused and stale edits are not written
+        let dir = temp_dir();
+        let root = dir.path().join("project");
+        fs::create_dir_all(&root).unwrap();
+        assert!(resolve_in_workspace(&root, Path::new("../escape.txt")).is_err());
+        assert!(resolve_in_workspace(&root, Path::new("/etc/passwd")).is_err());
+        assert!(resolve_in_workspace(&root, Path::new("missing/dir/file.txt")).is_err());
+
+        let edit = FileEdit {
+            path: PathBuf::from("a.txt"),
+            old: None,
+            new: "new".to_string(),
+        };
+        edit.apply(&root).unwrap();
+        assert!(
+            edit.apply(&root)
+                .unwrap_err()
+                .to_string()
+                .contains("was created")
+        );
+
+        let stale = FileEdit {
+            old: Some("old".to_string()),
+            ..edit
+        };
+        assert!(
+            stale
+                .apply(&root)
+                .unwrap_err()
+                .to_string()
+                .contains("changed on disk")
+        );
+        assert_eq!(fs::read_to_string(root.join("a.txt")).unwrap(), "new");
+    }
+}
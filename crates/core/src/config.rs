@@ -0,0 +1,929 @@
+use anyhow::{Context, Result, bail};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use toml::{Table, Value};
+use toml_edit::DocumentMut;
+
+use crate::config_schema::{ConfigIssue, check_table, check_value, closest, line_column, locate};
+use crate::paths::{config_dir, find_project_root};
+/// Values of the `auth_method` setting.
+pub const AUTH_METHODS: &[&str] = &["browser", "device", "azure-cli", "api-key"];
+
+/// Values of the `permission_mode` setting, one per `PermissionPolicy`.
+pub const PERMISSION_MODES: &[&str] =
+    &["default", "accept-edits", "read-only", "bypass-permissions"];
+
+/// Used when `api_version` is not set.
+pub const DEFAULT_API_VERSION: &str = "2024-10-21";
+
+/// A setting Foundry reads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Setting {
+    /// Dotted key, e.g. `limits.max_turns`.
+    pub key: &'static str,
+    pub description: &'static str,
+    pub kind: SettingKind,
+    /// Built-in value as a TOML literal, if there is one.
+    pub default: Option<&'static str>,
+}
+
+/// The type of value a setting accepts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SettingKind {
+    String,
+    /// One of a fixed set of strings.
+    OneOf(&'static [&'static str]),
+    Integer,
+    /// An integer or a float.
+    Number,
+    Bool,
+    StringList,
+}
+
+impl Setting {
+    /// The environment variable that overrides the setting:
+    /// `limits.max_turns` is read from `FOUNDRY_LIMITS_MAX_TURNS`.
+    pub fn env_var(&self) -> String {
+        format!("FOUNDRY_{}", self.key.replace('.', "_").to_uppercase())
+    }
+}
+
+pub const SETTINGS: &[Setting] = &[
+    Setting {
+        key: "endpoint",
+        description: "Azure AI Foundry project endpoint URL",
+        kind: SettingKind::String,
+        default: None,
+    },
+    Setting {
+        key: "tenant",
+        description: "Microsoft Entra tenant to sign in to",
+        kind: SettingKind::String,
+        default: None,
+    },
+    Setting {
+        key: "deployment",
+        description: "Model deployment used for new sessions",
+        kind: SettingKind::String,
+        default: None,
+    },
+    Setting {
+        key: "deployments",
+        description: "Other deployments offered by /models",
+        kind: SettingKind::StringList,
+        default: None,
+    },
+    Setting {
+        key: "api_version",
+        description: "Azure OpenAI API version",
+        kind: SettingKind::String,
+        default: Some("\"2024-10-21\""),
+    },
+    Setting {
+        key: "auth_method",
+        description: "How `foundry login` signs in by default",
+        kind: SettingKind::OneOf(AUTH_METHODS),
+        default: Some("\"browser\""),
+    },
+    Setting {
+        key: "default_profile",
+        description: "Profile used when --profile is not given",
+        kind: SettingKind::String,
+        default: None,
+    },
+    Setting {
+        key: "theme",
+        description: "Color theme; detected from the terminal when unset",
+        kind: SettingKind::String,
+        default: None,
+    },
+    Setting {
+        key: "keymap",
+        description: "Key bindings file",
+        kind: SettingKind::String,
+        default: None,
+    },
+    Setting {
+        key: "inline",
+        description: "Keep the console in the terminal scrollback (--inline)",
+        kind: SettingKind::Bool,
+        default: Some("false"),
+    },
+    Setting {
+        key: "vim",
+        description: "Start the input box in vi mode",
+        kind: SettingKind::Bool,
+        default: Some("false"),
+    },
+    Setting {
+        key: "permission_mode",
+        description: "Tools allowed without approval",
+        kind: SettingKind::OneOf(PERMISSION_MODES),
+        default: Some("\"default\""),
+    },
+    Setting {
+        key: "limits.max_turns",
+        description: "Most model turns a single prompt may take",
+        kind: SettingKind::Integer,
+        default: Some("50"),
+    },
+    Setting {
+        key: "limits.max_output_tokens",
+        description: "Most tokens the model may produce per turn",
+        kind: SettingKind::Integer,
+        default: Some("8192"),
+    },
+    Setting {
+        key: "limits.max_cost_usd",
+        description: "Stop a session once its estimated cost reaches this",
+        kind: SettingKind::Number,
+        default: None,
+    },
+];
+
+/// Settings a `[profile.<name>]` section can set, switched together.
+pub const PROFILE_KEYS: &[&str] = &[
+    "endpoint",
+    "deployment",
+    "tenant",
+    "auth_method",
+    "permission_mode",
+];
+
+/// Marks a profile that must be confirmed before it is used.
+pub const PROTECTED: Setting = Setting {
+    key: "protected",
+    description: "Ask for confirmation before using this profile",
+    kind: SettingKind::Bool,
+    default: Some("false"),
+};
+
+/// A TOML config file, read and written by dotted keys such as
+/// `ui.theme`.
+#[derive(Debug, Clone, Default)]
+pub struct ConfigFile {
+    pub path: PathBuf,
+    table: Table,
+    /// The file as written, so saving keeps its comments and layout.
+    document: DocumentMut,
+}
+
+impl ConfigFile {
+    /// The user config file, `~/.config/foundry/config.toml`.
+    pub fn user() -> Result<Self> {
+        let Some(dir) = config_dir() else {
+            bail!("Failed to locate the config directory: no home directory");
+        };
+        Self::load(dir.join("config.toml"))
+    }
+
+    /// Loads `path`, treating a missing file as empty.
+    pub fn load(path: PathBuf) -> Result<Self> {
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(err) => {
+                return Err(err)
+                    .with_context(|| format!("Failed to read config file {}", path.display()));
+            }
+        };
+        let table = toml::from_str(&contents)
+            .with_context(|| format!("Failed to parse config file {}", path.display()))?;
+        let document = contents
+            .parse()
+            .with_context(|| format!("Failed to parse config file {}", path.display()))?;
+        Ok(Self {
+            path,
+            table,
+            document,
+        })
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        let mut parts = key.split('.');
+        let mut value = self.table.get(parts.next()?)?;
+        for part in parts {
+            value = value.as_table()?.get(part)?;
+        }
+        Some(value)
+    }
+
+    /// Sets `key`, reading `value` as a TOML value (`true`, `3`, `["a"]`)
+    /// and falling back to a plain string.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        self.insert(key, parse_value(value))
+    }
+
+    /// Sets `key` to `value` as is.
+    pub fn insert(&mut self, key: &str, value: Value) -> Result<()> {
+        let mut parts = key.split('.').collect::<Vec<_>>();
+        let Some(last) = parts.pop().filter(|last| !last.is_empty()) else {
+            bail!("Invalid config key '{key}'");
+        };
+
+        let mut document = self.document.as_table_mut() as &mut dyn toml_edit::TableLike;
+        for part in &parts {
+            let item = document.entry(part).or_insert_with(toml_edit::table);
+            let Some(child) = item.as_table_like_mut() else {
+                bail!("Cannot set '{key}': '{part}' is not a table");
+            };
+            document = child;
+        }
+        let mut item = document_item(&value)?;
+        match document.get_mut(last) {
+            // Replacing in place keeps the key's comments and spacing.
+            Some(existing) => {
+                if let (Some(old), Some(new)) = (existing.as_value(), item.as_value_mut()) {
+                    *new.decor_mut() = old.decor().clone();
+                }
+                *existing = item;
+            }
+            None => {
+                document.insert(last, item);
+            }
+        }
+
+        let mut table = &mut self.table;
+        for part in parts {
+            let entry = table
+                .entry(part)
+                .or_insert_with(|| Value::Table(Table::new()));
+            let Value::Table(child) = entry else {
+                bail!("Cannot set '{key}': '{part}' is not a table");
+            };
+            table = child;
+        }
+        table.insert(last.to_string(), value);
+        Ok(())
+    }
+
+    /// Leaves `key` out of the values read, keeping it in the file.
+    fn remove(&mut self, key: &str) {
+        let mut parts = key.split('.').collect::<Vec<_>>();
+        let Some(last) = parts.pop() else {
+            return;
+        };
+        let mut table = &mut self.table;
+        for part in parts {
+            match table.get_mut(part) {
+                Some(Value::Table(child)) => table = child,
+                _ => return,
+            }
+        }
+        table.remove(last);
+    }
+
+    /// Every value as a `(dotted key, TOML value)` pair, sorted by key.
+    pub fn entries(&self) -> Vec<(String, String)> {
+        let mut entries = Vec::new();
+        flatten(&self.table, "", &mut entries);
+        entries.sort();
+        entries
+    }
+
+    pub fn save(&self) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        fs::write(&self.path, self.document.to_string())
+            .with_context(|| format!("Failed to write config file {}", self.path.display()))
+    }
+}
+
+/// Where a setting's effective value came from, in order of precedence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ConfigSource {
+    Default,
+    User,
+    Project,
+    Local,
+    /// The active `[profile.<name>]`, merged across the config files.
+    Profile,
+    Env,
+    Cli,
+}
+
+impl ConfigSource {
+    /// Whether the layer is a file that `set` can write.
+    pub fn is_file(&self) -> bool {
+        matches!(self, Self::User | Self::Project | Self::Local)
+    }
+}
+
+impl fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Default => "default",
+            Self::User => "user",
+            Self::Project => "project",
+            Self::Local => "local",
+            Self::Profile => "profile",
+            Self::Env => "env",
+            Self::Cli => "cli",
+        })
+    }
+}
+
+/// A setting's effective value and where it came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigEntry {
+    pub key: String,
+    /// The value as TOML, e.g. `"gpt-4.1"` or `20`.
+    pub value: String,
+    pub source: ConfigSource,
+    /// The file or environment variable that set it.
+    pub origin: Option<String>,
+}
+
+/// The settings in effect for one project. Each layer overrides the ones
+/// before it:
+///
+/// 1. built-in defaults
+/// 2. the user file, `~/.config/foundry/config.toml`
+/// 3. the project file, `<repo>/.foundry/config.toml`
+/// 4. the local file, `<repo>/.foundry/config.local.toml`, kept out of git
+/// 5. the active profile, a `[profile.<name>]` section of those files
+/// 6. `FOUNDRY_*` environment variables, e.g. `FOUNDRY_LIMITS_MAX_TURNS`
+/// 7. command-line flags
+#[derive(Debug, Clone, Default)]
+pub struct LayeredConfig {
+    /// Lowest precedence first.
+    layers: Vec<(ConfigSource, ConfigFile)>,
+    profile: Option<String>,
+    /// Problems found while loading. Invalid values are left out, so the
+    /// setting falls back to a lower layer.
+    issues: Vec<ConfigIssue>,
+}
+
+impl LayeredConfig {
+    /// Loads the layers that apply in `cwd`, with `cli` holding the values
+    /// given as command-line flags.
+    pub fn load(cwd: &Path, cli: ConfigFile) -> Result<Self> {
+        let Some(dir) = config_dir() else {
+            bail!("Failed to locate the config directory: no home directory");
+        };
+        Self::load_from(
+            dir.join("config.toml"),
+            &find_project_root(cwd),
+            |name| std::env::var(name).ok(),
+            cli,
+        )
+    }
+
+    /// Loads the layers from explicit locations, reading variables through
+    /// `env`. Unreadable files and invalid settings are recorded as issues
+    /// rather than failing the load.
+    pub fn load_from(
+        user: PathBuf,
+        project_root: &Path,
+        env: impl Fn(&str) -> Option<String>,
+        mut cli: ConfigFile,
+    ) -> Result<Self> {
+        let project_dir = project_root.join(".foundry");
+        let mut issues = Vec::new();
+        let mut defaults = ConfigFile::default();
+        let mut from_env = ConfigFile::default();
+        for setting in SETTINGS {
+            if let Some(default) = setting.default {
+                defaults.set(setting.key, default)?;
+            }
+            let name = setting.env_var();
+            let Some(value) = env(&name) else {
+                continue;
+            };
+            let value = parse_value(&value);
+            match check_value(setting, &value) {
+                Ok(()) => from_env.insert(setting.key, value)?,
+                Err(message) => issues.push(ConfigIssue {
+                    origin: name,
+                    position: None,
+                    message,
+                }),
+            }
+        }
+        for problem in check_table(&cli.table) {
+            if problem.invalid_value {
+                cli.remove(&problem.key);
+            }
+            issues.push(ConfigIssue {
+                origin: "command line".to_string(),
+                position: None,
+                message: problem.message,
+            });
+        }
+
+        let user = load_layer(user, &mut issues);
+        let project = load_layer(project_dir.join("config.toml"), &mut issues);
+        let local = load_layer(project_dir.join("config.local.toml"), &mut issues);
+        let mut config = Self {
+            layers: vec![
+                (ConfigSource::Default, defaults),
+                (ConfigSource::User, user),
+                (ConfigSource::Project, project),
+                (ConfigSource::Local, local),
+                (ConfigSource::Profile, ConfigFile::default()),
+                (ConfigSource::Env, from_env),
+                (ConfigSource::Cli, cli),
+            ],
+            profile: None,
+            issues,
+        };
+        if let Some(name) = config.get_str("default_profile").map(str::to_string)
+            && let Err(err) = config.use_profile(Some(&name))
+        {
+            config.issues.push(ConfigIssue {
+                origin: "default_profile".to_string(),
+                position: None,
+                message: err.to_string(),
+            });
+        }
+        Ok(config)
+    }
+
+    /// Names of the profiles defined in any config file, sorted.
+    pub fn profiles(&self) -> Vec<String> {
+        let mut names = self
+            .file_layers()
+            .filter_map(|file| file.get("profile").and_then(Value::as_table))
+            .flat_map(|profiles| profiles.keys().cloned())
+            .collect::<Vec<_>>();
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    pub fn active_profile(&self) -> Option<&str> {
+        self.profile.as_deref()
+    }
+
+    /// Whether a profile is marked `protected` in the config files.
+    pub fn is_protected(&self, name: &str) -> bool {
+        self.file_layers()
+            .rev()
+            .find_map(|file| file.get(&format!("profile.{name}.protected")))
+            .and_then(Value::as_bool)
+            .unwrap_or(false)
+    }
+
+    /// Switches to the named profile, or back to no profile.
+    pub fn use_profile(&mut self, name: Option<&str>) -> Result<()> {
+        let mut layer = ConfigFile::default();
+        if let Some(name) = name {
+            let profiles = self.profiles();
+            if !profiles.iter().any(|profile| profile == name) {
+                match closest(name, profiles.iter().map(String::as_str)) {
+                    Some(suggestion) => {
+                        bail!("Unknown profile '{name}'; did you mean '{suggestion}'?")
+                    }
+                    None => bail!("Unknown profile '{name}'"),
+                }
+            }
+            // A profile may be spread over several files; later files win.
+            for file in self.file_layers() {
+                let Some(profile) = file
+                    .get(&format!("profile.{name}"))
+                    .and_then(Value::as_table)
+                else {
+                    continue;
+                };
+                for (key, value) in profile {
+                    if PROFILE_KEYS.contains(&key.as_str()) {
+                        layer.insert(key, value.clone())?;
+                    }
+                }
+            }
+        }
+        if let Some((_, profile)) = self
+            .layers
+            .iter_mut()
+            .find(|(source, _)| *source == ConfigSource::Profile)
+        {
+            *profile = layer;
+        }
+        self.profile = name.map(str::to_string);
+        Ok(())
+    }
+
+    fn file_layers(&self) -> impl DoubleEndedIterator<Item = &ConfigFile> {
+        self.layers
+            .iter()
+            .filter(|(source, _)| source.is_file())
+            .map(|(_, file)| file)
+    }
+
+    /// Problems found while loading, in layer order.
+    pub fn issues(&self) -> &[ConfigIssue] {
+        &self.issues
+    }
+
+    /// The effective value of `key` and the layer it came from.
+    pub fn get(&self, key: &str) -> Option<(&Value, ConfigSource)> {
+        self.layers
+            .iter()
+            .rev()
+            .find_map(|(source, file)| file.get(key).map(|value| (value, *source)))
+    }
+
+    pub fn get_str(&self, key: &str) -> Option<&str> {
+        self.get(key).and_then(|(value, _)| value.as_str())
+    }
+
+    pub fn get_bool(&self, key: &str) -> Option<bool> {
+        self.get(key).and_then(|(value, _)| value.as_bool())
+    }
+
+    pub fn layer(&self, source: ConfigSource) -> Option<&ConfigFile> {
+        self.layers
+            .iter()
+            .find(|(layer, _)| *layer == source)
+            .map(|(_, file)| file)
+    }
+
+    /// Sets `key` in one of the config files and saves it. Unknown keys and
+    /// wrongly typed values are refused, as is a file that does not parse,
+    /// so it is never overwritten.
+    pub fn set(&mut self, source: ConfigSource, key: &str, value: &str) -> Result<&ConfigFile> {
+        if !source.is_file() {
+            bail!("Cannot write {source} settings to a file");
+        }
+        let parsed = parse_value(value);
+        let mut probe = ConfigFile::default();
+        probe.insert(key, parsed.clone())?;
+        if let Some(problem) = check_table(&probe.table).into_iter().next() {
+            bail!("{}", problem.message);
+        }
+        let Some(index) = self.layers.iter().position(|(layer, _)| *layer == source) else {
+            bail!("No {source} config file is loaded");
+        };
+
+        let mut file = ConfigFile::load(self.layers[index].1.path.clone())?;
+        file.insert(key, parsed)?;
+        file.save()?;
+        let origin = file.path.display().to_string();
+        self.issues.retain(|issue| issue.origin != origin);
+        self.layers[index].1 = load_layer(file.path, &mut self.issues);
+        // The file may have changed the active profile's settings.
+        let profile = self.profile.clone();
+        self.use_profile(profile.as_deref())?;
+        Ok(&self.layers[index].1)
+    }
+
+    /// Every setting that has a value, sorted by key.
+    pub fn entries(&self) -> Vec<ConfigEntry> {
+        let mut entries = Vec::<ConfigEntry>::new();
+        for (source, file) in &self.layers {
+            for (key, value) in file.entries() {
+                let origin = match source {
+                    ConfigSource::Env => SETTINGS
+                        .iter()
+                        .find(|setting| setting.key == key)
+                        .map(Setting::env_var),
+                    ConfigSource::Profile => self
+                        .profile
+                        .as_ref()
+                        .map(|name| format!("[profile.{name}]")),
+                    source if source.is_file() => Some(file.path.display().to_string()),
+                    _ => None,
+                };
+                let entry = ConfigEntry {
+                    key,
+                    value,
+                    source: *source,
+                    origin,
+                };
+                match entries
+                    .iter_mut()
+                    .find(|existing| existing.key == entry.key)
+                {
+                    Some(existing) => *existing = entry,
+                    None => entries.push(entry),
+                }
+            }
+        }
+        entries.sort_by(|a, b| a.key.cmp(&b.key));
+        entries
+    }
+}
+
+/// Loads one config file, recording its problems in `issues` and leaving
+/// out values of the wrong type.
+fn load_layer(path: PathBuf, issues: &mut Vec<ConfigIssue>) -> ConfigFile {
+    let origin = path.display().to_string();
+    let mut file = ConfigFile {
+        path,
+        ..ConfigFile::default()
+    };
+    let contents = match fs::read_to_string(&file.path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return file,
+        Err(err) => {
+            issues.push(ConfigIssue {
+                origin,
+                position: None,
+                message: format!("Failed to read config file: {err}"),
+            });
+            return file;
+        }
+    };
+    file.table = match toml::from_str(&contents) {
+        Ok(table) => {
+            file.document = contents.parse().unwrap_or_default();
+            table
+        }
+        Err(err) => {
+            issues.push(ConfigIssue {
+                origin,
+                position: err.span().map(|span| line_column(&contents, span.start)),
+                message: err.message().trim_end().to_string(),
+            });
+            return file;
+        }
+    };
+    for problem in check_table(&file.table) {
+        if problem.invalid_value {
+            file.remove(&problem.key);
+        }
+        issues.push(ConfigIssue {
+            origin: origin.clone(),
+            position: locate(&contents, &problem.key, problem.invalid_value),
+            message: problem.message,
+        });
+    }
+    file
+}
+
+fn parse_value(value: &str) -> Value {
+    toml::from_str::<Table>(&format!("value = {value}"))
+        .ok()
+        .and_then(|mut table| table.remove("value"))
+        .unwrap_or_else(|| Value::String(value.to_string()))
+}
+
+/// `value` as an item of an editable document.
+fn document_item(value: &Value) -> Result<toml_edit::Item> {
+    Ok(match value {
+        Value::Table(table) => {
+            let document = toml::to_string(table)?.parse::<DocumentMut>()?;
+            toml_edit::Item::Table(document.as_table().clone())
+        }
+        value => toml_edit::Item::Value(value.to_string().parse()?),
+    })
+}
+
+fn flatten(table: &Table, prefix: &str, entries: &mut Vec<(String, String)>) {
+    for (key, value) in table {
+        let key = format!("{prefix}{key}");
+        match value {
+            Value::Table(child) => flatten(child, &format!("{key}."), entries),
+            value => entries.push((key, value.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::temp_dir;
+
+    #[test]
+    fn test_set_get_and_list_dotted_keys() {
+        // Test values are typed, nested by dotted keys and listed flat
+        let mut config = ConfigFile::default();
+        config.set("deployment", "gpt-4.1").unwrap();
+        config.set("ui.vim", "true").unwrap();
+        config.set("limits.max_turns", "20").unwrap();
+
+        assert_eq!(config.get("deployment").unwrap().as_str(), Some("gpt-4.1"));
+        assert_eq!(config.get("ui.vim").unwrap().as_bool(), Some(true));
+        assert!(config.get("ui.missing").is_none());
+        assert_eq!(
+            config.entries(),
+            vec![
+                ("deployment".to_string(), "\"gpt-4.1\"".to_string()),
+                ("limits.max_turns".to_string(), "20".to_string()),
+                ("ui.vim".to_string(), "true".to_string()),
+            ]
+        );
+        assert!(config.set("deployment.name", "x").is_err());
+    }
+
+    #[test]
+    fn test_config_file_round_trip() {
+        // Test a saved config file loads back with the same values
+        let dir = temp_dir();
+        let path = dir.path().join("config.toml");
+        let mut config = ConfigFile::load(path.clone()).unwrap();
+        config.set("ui.theme", "light").unwrap();
+        config.save().unwrap();
+
+        let reloaded = ConfigFile::load(path.clone()).unwrap();
+        assert_eq!(reloaded.get("ui.theme").unwrap().as_str(), Some("light"));
+    }
+
+    #[test]
+    fn test_save_keeps_comments_and_layout() {
+        // Test setting a value leaves the rest of the file as it was written
+        let dir = temp_dir();
+        let path = dir.path().join("config.toml");
+        let contents = "# Shared settings\ndeployment = \"gpt-4.1\"  # the default\n\n\
+                        [ui]\n# Pick a light theme on bright terminals\ntheme = \"dark\"\n";
+        fs::write(&path, contents).unwrap();
+
+        let mut config = ConfigFile::load(path.clone()).unwrap();
+        config.set("ui.theme", "light").unwrap();
+        config.set("limits.max_turns", "10").unwrap();
+        config.save().unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "# Shared settings\ndeployment = \"gpt-4.1\"  # the default\n\n\
+             [ui]\n# Pick a light theme on bright terminals\ntheme = \"light\"\n\n\
+             [limits]\nmax_turns = 10\n"
+        );
+    }
+
+    #[test]
+    fn test_layers_override_in_precedence_order() {
+        // Test project, local, env and CLI values win in turn and keep their source
+        let dir = temp_dir();
+        let root = dir.path();
+        fs::create_dir_all(root.join(".foundry")).unwrap();
+        fs::write(
+            root.join("user.toml"),
+            "deployment = \"user\"\ntheme = \"dark\"\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join(".foundry/config.toml"),
+            "deployment = \"project\"\nendpoint = \"https://dev\"\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join(".foundry/config.local.toml"),
+            "endpoint = \"https://local\"\n",
+        )
+        .unwrap();
+        let env = |name: &str| (name == "FOUNDRY_LIMITS_MAX_TURNS").then(|| "5".to_string());
+        let mut cli = ConfigFile::default();
+        cli.set("theme", "light").unwrap();
+
+        let config = LayeredConfig::load_from(root.join("user.toml"), root, env, cli).unwrap();
+        assert_eq!(config.get_str("deployment"), Some("project"));
+        assert_eq!(config.get_str("endpoint"), Some("https://local"));
+        assert_eq!(config.get_str("theme"), Some("light"));
+        assert_eq!(config.get_str("api_version"), Some(DEFAULT_API_VERSION));
+        let (turns, source) = config.get("limits.max_turns").unwrap();
+        assert_eq!((turns.as_integer(), source), (Some(5), ConfigSource::Env));
+
+        let entries = config.entries();
+        let endpoint = entries
+            .iter()
+            .find(|entry| entry.key == "endpoint")
+            .unwrap();
+        assert_eq!(endpoint.source, ConfigSource::Local);
+        assert!(
+            endpoint
+                .origin
+                .as_ref()
+                .unwrap()
+                .ends_with("config.local.toml")
+        );
+        let turns = entries
+            .iter()
+            .find(|entry| entry.key == "limits.max_turns")
+            .unwrap();
+        assert_eq!(turns.origin.as_deref(), Some("FOUNDRY_LIMITS_MAX_TURNS"));
+    }
+
+    #[test]
+    fn test_invalid_settings_are_reported_not_fatal() {
+        // Test typos, wrong types and syntax errors become located issues
+        let dir = temp_dir();
+        let root = dir.path();
+        fs::create_dir_all(root.join(".foundry")).unwrap();
+        fs::write(
+            root.join("user.toml"),
+            "deploymnet = \"o3\"\ntheme = \"dark\"\n\n[limits]\nmax_turns = \"ten\"\n",
+        )
+        .unwrap();
+        fs::write(root.join(".foundry/config.toml"), "endpoint = \n").unwrap();
+        let env = |name: &str| (name == "FOUNDRY_PERMISSION_MODE").then(|| "yolo".to_string());
+
+        let mut config =
+            LayeredConfig::load_from(root.join("user.toml"), root, env, ConfigFile::default())
+                .unwrap();
+        let issues = config
+            .issues()
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        assert_eq!(issues.len(), 4);
+        assert!(issues[0].starts_with("FOUNDRY_PERMISSION_MODE: 'permission_mode' must be one of"));
+        assert!(
+            issues[1].ends_with(
+                "user.toml:1:1: Unknown setting 'deploymnet'; did you mean 'deployment'?"
+            )
+        );
+        assert!(
+            issues[2]
+                .ends_with("user.toml:5:13: 'limits.max_turns' must be an integer, found string")
+        );
+        assert!(issues[3].contains("config.toml:1:"));
+        assert_eq!(config.get_str("theme"), Some("dark"));
+        assert_eq!(
+            config.get("limits.max_turns").unwrap().1,
+            ConfigSource::Default
+        );
+        assert_eq!(config.get_str("permission_mode"), Some("default"));
+
+        assert!(
+            config
+                .set(ConfigSource::Project, "deployment", "o3")
+                .is_err()
+        );
+        assert!(
+            config
+                .set(ConfigSource::User, "limits.max_turns", "ten")
+                .is_err()
+        );
+        let err = config
+            .set(ConfigSource::User, "deploymnet", "o3")
+            .unwrap_err();
+        assert!(err.to_string().contains("did you mean 'deployment'"));
+        config
+            .set(ConfigSource::User, "limits.max_turns", "10")
+            .unwrap();
+        assert_eq!(config.issues().len(), 3);
+    }
+
+    #[test]
+    fn test_profiles_switch_settings_together() {
+        // Test a profile overrides the files, merges across them and yields to env
+        let dir = temp_dir();
+        let root = dir.path();
+        fs::create_dir_all(root.join(".foundry")).unwrap();
+        fs::write(
+            root.join("user.toml"),
+            "deployment = \"gpt-4.1\"\ndefault_profile = \"dev\"\n\n\
+             [profile.dev]\nendpoint = \"https://dev\"\n\n\
+             [profile.prod]\nendpoint = \"https://prod\"\nprotected = true\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join(".foundry/config.toml"),
+            "[profile.prod]\ndeployment = \"o3\"\ntenant = \"contoso\"\n",
+        )
+        .unwrap();
+        let env = |name: &str| (name == "FOUNDRY_TENANT").then(|| "fabrikam".to_string());
+
+        let mut config =
+            LayeredConfig::load_from(root.join("user.toml"), root, env, ConfigFile::default())
+                .unwrap();
+        assert_eq!(config.profiles(), vec!["dev", "prod"]);
+        assert_eq!(config.active_profile(), Some("dev"));
+        assert_eq!(config.get_str("endpoint"), Some("https://dev"));
+        assert_eq!(config.get_str("deployment"), Some("gpt-4.1"));
+        assert!(!config.is_protected("dev"));
+        assert!(config.is_protected("prod"));
+
+        config.use_profile(Some("prod")).unwrap();
+        assert_eq!(config.get_str("endpoint"), Some("https://prod"));
+        assert_eq!(config.get("deployment").unwrap().1, ConfigSource::Profile);
+        assert_eq!(config.get_str("tenant"), Some("fabrikam"));
+        let entries = config.entries();
+        let deployment = entries
+            .iter()
+            .find(|entry| entry.key == "deployment")
+            .unwrap();
+        assert_eq!(deployment.origin.as_deref(), Some("[profile.prod]"));
+
+        let err = config.use_profile(Some("prd")).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Unknown profile 'prd'; did you mean 'prod'?"
+        );
+        config.use_profile(None).unwrap();
+        assert_eq!(config.get_str("endpoint"), None);
+    }
+
+    #[test]
+    fn test_set_writes_only_file_layers() {
+        // Test set saves to the chosen file and refuses env or CLI layers
+        let dir = temp_dir();
+        let root = dir.path();
+        let mut config = LayeredConfig::load_from(
+            root.join("user.toml"),
+            root,
+            |_| None,
+            ConfigFile::default(),
+        )
+        .unwrap();
+        config
+            .set(ConfigSource::Project, "deployment", "o3")
+            .unwrap();
+        assert_eq!(config.get("deployment").unwrap().1, ConfigSource::Project);
+        assert!(root.join(".foundry/config.toml").exists());
+        assert!(config.set(ConfigSource::Env, "deployment", "o3").is_err());
+    }
+}
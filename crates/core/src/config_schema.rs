@@ -0,0 +1,418 @@
+use serde_json::json;
+use std::fmt;
+use toml::de::{DeTable, DeValue};
+use toml::{Table, Value};
+
+use crate::config::{PROFILE_KEYS, PROTECTED, SETTINGS, Setting, SettingKind};
+
+/// A problem found in one config layer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigIssue {
+    /// The file or environment variable the problem is in.
+    pub origin: String,
+    /// 1-based line and column, for files.
+    pub position: Option<(usize, usize)>,
+    pub message: String,
+}
+
+impl fmt::Display for ConfigIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.position {
+            Some((line, column)) => write!(f, "{}:{line}:{column}: {}", self.origin, self.message),
+            None => write!(f, "{}: {}", self.origin, self.message),
+        }
+    }
+}
+
+/// A problem with one key, not yet located in its file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Problem {
+    /// Dotted key, e.g. `limits.max_turns`.
+    pub key: String,
+    /// Whether the value is wrong, rather than the key.
+    pub invalid_value: bool,
+    pub message: String,
+}
+
+pub fn find_setting(key: &str) -> Option<&'static Setting> {
+    SETTINGS.iter().find(|setting| setting.key == key)
+}
+
+/// Checks every key in `table` against the known settings.
+pub fn check_table(table: &Table) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    check_table_in(table, "", &mut problems);
+    problems
+}
+
+fn check_table_in(table: &Table, prefix: &str, problems: &mut Vec<Problem>) {
+    for (key, value) in table {
+        let key = format!("{prefix}{key}");
+        let section = format!("{key}.");
+        if let Some(setting) = find_setting(&key) {
+            if let Err(message) = check_value_as(setting, &key, value) {
+                problems.push(Problem {
+                    key,
+                    invalid_value: true,
+                    message,
+                });
+            }
+        } else if let Some(child) = value.as_table()
+            && SETTINGS
+                .iter()
+                .any(|setting| setting.key.starts_with(&section))
+        {
+            check_table_in(child, &section, problems);
+        } else if key == "profile" {
+            match value.as_table() {
+                Some(profiles) => check_profiles(profiles, problems),
+                None => problems.push(Problem {
+                    message: "'profile' must be a table of [profile.<name>] sections".to_string(),
+                    key,
+                    invalid_value: true,
+                }),
+            }
+        } else {
+            problems.push(Problem {
+                message: unknown_setting_message(&key),
+                key,
+                invalid_value: false,
+            });
+        }
+    }
+}
+
+fn check_profiles(profiles: &Table, problems: &mut Vec<Problem>) {
+    for (name, profile) in profiles {
+        let Some(profile) = profile.as_table() else {
+            problems.push(Problem {
+                key: format!("profile.{name}"),
+                invalid_value: true,
+                message: format!("'profile.{name}' must be a table"),
+            });
+            continue;
+        };
+        for (key, value) in profile {
+            let dotted = format!("profile.{name}.{key}");
+            let setting = if key == PROTECTED.key {
+                Some(&PROTECTED)
+            } else if PROFILE_KEYS.contains(&key.as_str()) {
+                find_setting(key)
+            } else {
+                None
+            };
+            let Some(setting) = setting else {
+                let candidates = PROFILE_KEYS.iter().copied().chain([PROTECTED.key]);
+                let mut message = format!("Unknown profile setting '{dotted}'");
+                if let Some(suggestion) = closest(key, candidates) {
+                    message.push_str(&format!("; did you mean '{suggestion}'?"));
+                }
+                problems.push(Problem {
+                    key: dotted,
+                    invalid_value: false,
+                    message,
+                });
+                continue;
+            };
+            if let Err(message) = check_value_as(setting, &dotted, value) {
+                problems.push(Problem {
+                    key: dotted,
+                    invalid_value: true,
+                    message,
+                });
+            }
+        }
+    }
+}
+
+/// Checks that `value` has the type `setting` expects.
+pub fn check_value(setting: &Setting, value: &Value) -> Result<(), String> {
+    check_value_as(setting, setting.key, value)
+}
+
+/// Like `check_value`, naming the setting `key` in messages, e.g. when it
+/// is set in a profile.
+fn check_value_as(setting: &Setting, key: &str, value: &Value) -> Result<(), String> {
+    let valid = match setting.kind {
+        SettingKind::String => value.is_str(),
+        SettingKind::OneOf(names) => match value.as_str() {
+            Some(name) if names.contains(&name) => true,
+            Some(name) => {
+                let mut message = format!(
+                    "'{key}' must be one of {}, found \"{name}\"",
+                    names.join(", ")
+                );
+                if let Some(suggestion) = closest(name, names.iter().copied()) {
+                    message.push_str(&format!("; did you mean \"{suggestion}\"?"));
+                }
+                return Err(message);
+            }
+            None => false,
+        },
+        SettingKind::Integer => value.is_integer(),
+        SettingKind::Number => value.is_integer() || value.is_float(),
+        SettingKind::Bool => value.is_bool(),
+        SettingKind::StringList => value
+            .as_array()
+            .is_some_and(|items| items.iter().all(Value::is_str)),
+    };
+    if valid {
+        Ok(())
+    } else {
+        Err(format!(
+            "'{key}' must be {}, found {}",
+            describe_kind(setting.kind),
+            value.type_str()
+        ))
+    }
+}
+
+/// `Unknown setting 'deploymnet'; did you mean 'deployment'?`
+pub fn unknown_setting_message(key: &str) -> String {
+    let candidates = SETTINGS.iter().map(|setting| setting.key);
+    match closest(key, candidates) {
+        Some(suggestion) => format!("Unknown setting '{key}'; did you mean '{suggestion}'?"),
+        None => format!("Unknown setting '{key}'"),
+    }
+}
+
+fn describe_kind(kind: SettingKind) -> String {
+    match kind {
+        SettingKind::String => "a string".to_string(),
+        SettingKind::OneOf(names) => format!("one of {}", names.join(", ")),
+        SettingKind::Integer => "an integer".to_string(),
+        SettingKind::Number => "a number".to_string(),
+        SettingKind::Bool => "true or false".to_string(),
+        SettingKind::StringList => "a list of strings".to_string(),
+    }
+}
+
+/// The candidate closest to `text`, if any is close enough to be a typo.
+/// A candidate whose last dotted segment matches exactly also counts, so
+/// `max_turns` suggests `limits.max_turns`.
+pub(crate) fn closest<'a>(
+    text: &str,
+    candidates: impl Iterator<Item = &'a str>,
+) -> Option<&'a str> {
+    let limit = (text.chars().count() / 3).max(1);
+    candidates
+        .map(|candidate| {
+            let last = candidate.rsplit('.').next().unwrap_or(candidate);
+            let distance =
+                edit_distance(text, candidate).min(if last == text { 0 } else { usize::MAX });
+            (distance, candidate)
+        })
+        .filter(|(distance, _)| *distance <= limit)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// Levenshtein distance, counting characters.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut previous = (0..=b.len()).collect::<Vec<_>>();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// Where dotted `key` is written in TOML `contents`: at its value when
+/// `at_value` is set, otherwise at the key.
+pub fn locate(contents: &str, key: &str, at_value: bool) -> Option<(usize, usize)> {
+    let document = DeTable::parse(contents).ok()?;
+    let mut table = document.get_ref();
+    let mut parts = key.split('.').peekable();
+    while let Some(part) = parts.next() {
+        let (name, value) = table.iter().find(|(name, _)| name.get_ref() == part)?;
+        if parts.peek().is_none() {
+            let span = if at_value { value.span() } else { name.span() };
+            return Some(line_column(contents, span.start));
+        }
+        let DeValue::Table(child) = value.get_ref() else {
+            return None;
+        };
+        table = child;
+    }
+    None
+}
+
+/// The 1-based line and column of a byte offset.
+pub fn line_column(contents: &str, offset: usize) -> (usize, usize) {
+    let before = &contents[..offset.min(contents.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before
+        .rsplit('\n')
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .count()
+        + 1;
+    (line, column)
+}
+
+/// A JSON Schema describing the config files, for editor completion and
+/// checking.
+pub fn json_schema() -> serde_json::Value {
+    let mut root = object_schema();
+    root["$schema"] = json!("https://json-schema.org/draft/2020-12/schema");
+    root["title"] = json!("Foundry configuration");
+    for setting in SETTINGS {
+        let mut parts = setting.key.split('.').collect::<Vec<_>>();
+        let last = parts.pop().unwrap_or(setting.key);
+        let mut node = &mut root;
+        for part in parts {
+            node = &mut node["properties"][part];
+            if node.is_null() {
+                *node = object_schema();
+            }
+        }
+        node["properties"][last] = setting_schema(setting);
+    }
+
+    let mut profile = object_schema();
+    for key in PROFILE_KEYS {
+        if let Some(setting) = find_setting(key) {
+            profile["properties"][key] = setting_schema(setting);
+        }
+    }
+    profile["properties"][PROTECTED.key] = setting_schema(&PROTECTED);
+    root["properties"]["profile"] = json!({
+        "type": "object",
+        "description": "Named sets of settings, chosen with --profile or /profile",
+        "additionalProperties": profile,
+    });
+    root
+}
+
+fn object_schema() -> serde_json::Value {
+    json!({
+        "type": "object",
+        "properties": {},
+        "additionalProperties": false,
+    })
+}
+
+fn setting_schema(setting: &Setting) -> serde_json::Value {
+    let mut schema = match setting.kind {
+        SettingKind::String => json!({ "type": "string" }),
+        SettingKind::OneOf(names) => json!({ "type": "string", "enum": names }),
+        SettingKind::Integer => json!({ "type": "integer" }),
+        SettingKind::Number => json!({ "type": "number" }),
+        SettingKind::Bool => json!({ "type": "boolean" }),
+        SettingKind::StringList => json!({ "type": "array", "items": { "type": "string" } }),
+    };
+    schema["description"] = json!(setting.description);
+    if let Some(default) = setting
+        .default
+        .and_then(|default| toml::from_str::<Table>(&format!("value = {default}")).ok())
+        .and_then(|mut table| table.remove("value"))
+    {
+        schema["default"] = json!(default);
+    }
+    schema
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unknown_keys_suggest_the_closest_setting() {
+        // Test typos, misplaced keys and wrong types are reported by key
+        let table = toml::from_str::<Table>(
+            "deploymnet = \"gpt-4.1\"\nmax_turns = 3\ncolour = \"red\"\n\n[limits]\nmax_turns = \"ten\"\n",
+        )
+        .unwrap();
+        let problems = check_table(&table);
+        let messages = problems
+            .iter()
+            .map(|problem| problem.message.as_str())
+            .collect::<Vec<_>>();
+        assert!(messages.contains(&"Unknown setting 'deploymnet'; did you mean 'deployment'?"));
+        assert!(
+            messages.contains(&"Unknown setting 'max_turns'; did you mean 'limits.max_turns'?")
+        );
+        assert!(messages.contains(&"Unknown setting 'colour'"));
+        assert!(messages.contains(&"'limits.max_turns' must be an integer, found string"));
+        assert_eq!(
+            problems
+                .iter()
+                .filter(|problem| problem.invalid_value)
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_profile_sections_are_checked() {
+        // Test profiles only take the keys they switch, plus protected
+        let table = toml::from_str::<Table>(
+            "[profile.prod]\nendpont = \"https://prod\"\nprotected = \"yes\"\ntheme = \"dark\"\n\n\
+             [profile.dev]\nauth_method = \"device\"\n",
+        )
+        .unwrap();
+        let messages = check_table(&table)
+            .into_iter()
+            .map(|problem| problem.message)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            messages,
+            vec![
+                "Unknown profile setting 'profile.prod.endpont'; did you mean 'endpoint'?",
+                "'profile.prod.protected' must be true or false, found string",
+                "Unknown profile setting 'profile.prod.theme'",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_enum_values_are_checked() {
+        // Test a misspelled choice is rejected with a suggestion
+        let setting = find_setting("permission_mode").unwrap();
+        assert!(check_value(setting, &Value::from("read-only")).is_ok());
+        assert_eq!(
+            check_value(setting, &Value::from("readonly")).unwrap_err(),
+            "'permission_mode' must be one of default, accept-edits, read-only, \
+             bypass-permissions, found \"readonly\"; did you mean \"read-only\"?"
+        );
+        assert!(check_value(setting, &Value::from(1)).is_err());
+    }
+
+    #[test]
+    fn test_locate_keys_and_values() {
+        // Test keys in tables and dotted keys are found at their line and column
+        let contents = "theme = \"dark\"\nlimits.max_cost_usd = 2\n";
+        assert_eq!(locate(contents, "theme", false), Some((1, 1)));
+        assert_eq!(locate(contents, "limits.max_cost_usd", true), Some((2, 23)));
+        assert_eq!(locate(contents, "missing", false), None);
+
+        let contents = "[limits]\n  max_turns = \"x\"\n";
+        assert_eq!(locate(contents, "limits.max_turns", false), Some((2, 3)));
+        assert_eq!(locate(contents, "limits.max_turns", true), Some((2, 15)));
+    }
+
+    #[test]
+    fn test_json_schema_nests_dotted_settings() {
+        // Test the schema has a property per setting, nested by dotted key
+        let schema = json_schema();
+        assert_eq!(schema["additionalProperties"], false);
+        assert_eq!(schema["properties"]["endpoint"]["type"], "string");
+        assert_eq!(schema["properties"]["api_version"]["default"], "2024-10-21");
+        let limits = &schema["properties"]["limits"];
+        assert_eq!(limits["properties"]["max_turns"]["type"], "integer");
+        assert_eq!(limits["properties"]["max_turns"]["default"], 50);
+        assert_eq!(
+            schema["properties"]["permission_mode"]["enum"][2],
+            "read-only"
+        );
+        let profile = &schema["properties"]["profile"]["additionalProperties"];
+        assert_eq!(profile["properties"]["protected"]["type"], "boolean");
+        assert!(profile["properties"]["theme"].is_null());
+    }
+}
@@ -0,0 +1,511 @@
+use serde_json::Value;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::paths::{config_dir, display_path, find_project_root, home_dir};
+
+pub const INSTRUCTIONS_FILE_NAME: &str = "FOUNDRY.md";
+
+/// How deep `@include`s may nest before the rest are skipped.
+const MAX_INCLUDE_DEPTH: usize = 5;
+
+const BASE_PROMPT: &str = "You are Foundry, a coding assistant working in the user's repository. \
+Read the code before changing it, keep changes focused on the request, and match the \
+conventions of the surrounding code.";
+
+/// Where an instruction file applies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InstructionScope {
+    /// `FOUNDRY.md` in the user config directory, for every project.
+    User,
+    /// `FOUNDRY.md` in the project root or a directory below it.
+    Project,
+}
+
+impl fmt::Display for InstructionScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            Self::User => "user",
+            Self::Project => "project",
+        })
+    }
+}
+
+/// A loaded `FOUNDRY.md` file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstructionFile {
+    pub path: PathBuf,
+    pub scope: InstructionScope,
+    /// The file's text with `@include` lines replaced by the included files.
+    pub contents: String,
+    /// Files pulled in by `@include`, in the order they were read.
+    pub includes: Vec<PathBuf>,
+    /// Includes that were skipped, and why.
+    pub problems: Vec<String>,
+}
+
+/// The instruction files that apply to a directory: the user's, then the
+/// project's from the root down to the directory itself, so the more
+/// specific ones come last.
+#[derive(Debug, Clone, Default)]
+pub struct Instructions {
+    user_dir: Option<PathBuf>,
+    root: PathBuf,
+    cwd: PathBuf,
+    pub files: Vec<InstructionFile>,
+}
+
+impl Instructions {
+    pub fn load(cwd: &Path) -> Self {
+        Self::load_from(config_dir(), find_project_root(cwd), cwd.to_path_buf())
+    }
+
+    pub fn load_from(user_dir: Option<PathBuf>, root: PathBuf, cwd: PathBuf) -> Self {
+        let mut instructions = Self {
+            user_dir,
+            root,
+            cwd,
+            files: Vec::new(),
+        };
+        instructions.reload();
+        instructions
+    }
+
+    /// Reads the files again, e.g. after one was edited.
+    pub fn reload(&mut self) {
+        let mut candidates = Vec::new();
+        if let Some(dir) = &self.user_dir {
+            candidates.push((dir.join(INSTRUCTIONS_FILE_NAME), InstructionScope::User));
+        }
+        let mut dir = self.root.clone();
+        candidates.push((dir.join(INSTRUCTIONS_FILE_NAME), InstructionScope::Project));
+        if let Ok(relative) = self.cwd.strip_prefix(&self.root) {
+            for component in relative.components() {
+                dir.push(component);
+                candidates.push((dir.join(INSTRUCTIONS_FILE_NAME), InstructionScope::Project));
+            }
+        }
+
+        // Project files come with the repository, so they may only include
+        // files inside it; the user's own file may include from their home.
+        let user_base = home_dir().or_else(|| self.user_dir.clone());
+        self.files = candidates
+            .into_iter()
+            .filter_map(|(path, scope)| {
+                let base = match scope {
+                    InstructionScope::User => user_base.as_deref()?,
+                    InstructionScope::Project => &self.root,
+                };
+                load_file(path, scope, base)
+            })
+            .collect();
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// The file `/memory edit` opens for `scope`: the user file, or the
+    /// project root's.
+    pub fn path_for(&self, scope: InstructionScope) -> Option<PathBuf> {
+        match scope {
+            InstructionScope::User => self.user_dir.as_ref(),
+            InstructionScope::Project => Some(&self.root),
+        }
+        .map(|dir| dir.join(INSTRUCTIONS_FILE_NAME))
+    }
+
+    /// The system prompt sent with every turn: the base prompt followed by
+    /// each non-empty instruction file.
+    pub fn system_prompt(&self) -> String {
+        let mut prompt = BASE_PROMPT.to_string();
+        let files = self
+            .files
+            .iter()
+            .filter(|file| !file.contents.trim().is_empty())
+            .collect::<Vec<_>>();
+        if files.is_empty() {
+            return prompt;
+        }
+
+        prompt.push_str(
+            "\n\n# Instructions\n\nFollow these instructions from FOUNDRY.md files. \
+             When they disagree, later files are more specific and take precedence.",
+        );
+        for file in files {
+            prompt.push_str(&format!(
+                "\n\n## {} ({} instructions)\n\n{}",
+                display_path(&file.path),
+                file.scope,
+                file.contents.trim()
+            ));
+        }
+        prompt
+    }
+}
+
+fn load_file(path: PathBuf, scope: InstructionScope, base: &Path) -> Option<InstructionFile> {
+    let text = fs::read_to_string(&path).ok()?;
+    let mut file = InstructionFile {
+        path,
+        scope,
+        contents: String::new(),
+        includes: Vec::new(),
+        problems: Vec::new(),
+    };
+    let mut stack = vec![canonical(&file.path)];
+    file.contents = expand_includes(
+        &text,
+        &file.path.clone(),
+        &canonical(base),
+        &mut stack,
+        &mut file,
+    );
+    Some(file)
+}
+
+/// Replaces each `@include <path>` line outside code fences with the
+/// contents of that file, resolved relative to the including file. Files
+/// outside `base` are skipped.
+fn expand_includes(
+    text: &str,
+    path: &Path,
+    base: &Path,
+    stack: &mut Vec<PathBuf>,
+    file: &mut InstructionFile,
+) -> String {
+    let dir = path.parent().unwrap_or(Path::new(""));
+    let mut in_fence = false;
+    let mut lines = Vec::new();
+    for line in text.lines() {
+        if line.trim_start().starts_with("```") {
+            in_fence = !in_fence;
+        }
+        let target = match line.trim().strip_prefix("@include ") {
+            Some(target) if !in_fence => dir.join(target.trim()),
+            _ => {
+                lines.push(line.to_string());
+                continue;
+            }
+        };
+
+        let canonical_target = canonical(&target);
+        if target.exists() && !canonical_target.starts_with(base) {
+            file.problems.push(format!(
+                "Skipped {}: it is outside {}",
+                display_path(&target),
+                display_path(base)
+            ));
+        } else if stack.contains(&canonical_target) {
+            file.problems.push(format!(
+                "Skipped {}: it includes itself",
+                display_path(&target)
+            ));
+        } else if stack.len() > MAX_INCLUDE_DEPTH {
+            file.problems.push(format!(
+                "Skipped {}: includes nest more than {MAX_INCLUDE_DEPTH} deep",
+                display_path(&target)
+            ));
+        } else {
+            match fs::read_to_string(&target) {
+                Ok(included) => {
+                    file.includes.push(target.clone());
+                    stack.push(canonical_target);
+                    let expanded = expand_includes(&included, &target, base, stack, file);
+                    stack.pop();
+                    lines.push(expanded.trim_end().to_string());
+                }
+                Err(err) => file.problems.push(format!(
+                    "Failed to include {}: {err}",
+                    display_path(&target)
+                )),
+            }
+        }
+    }
+    lines.join("\n")
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// A starter `FOUNDRY.md` for the project at `root`, drafted from what the
+/// repository contains: its manifests, layout and existing guidance.
+pub fn starter_instructions(root: &Path) -> String {
+    let name = root
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "Project".to_string());
+    let mut lines = vec![
+        format!("# {name}"),
+        String::new(),
+        "Guidance for Foundry when working in this repository.".to_string(),
+    ];
+
+    if let Some(summary) = readme_summary(root) {
+        lines.extend([String::new(), "## Overview".to_string(), String::new()]);
+        lines.push(summary);
+    }
+
+    let commands = project_commands(root);
+    if !commands.is_empty() {
+        lines.extend([String::new(), "## Commands".to_string(), String::new()]);
+        lines.extend(
+            commands
+                .into_iter()
+                .map(|(command, purpose)| format!("- `{command}`: {purpose}")),
+        );
+    }
+
+    let directories = top_level_directories(root);
+    if !directories.is_empty() {
+        lines.extend([String::new(), "## Layout".to_string(), String::new()]);
+        lines.extend(directories.into_iter().map(|dir| format!("- `{dir}/`")));
+    }
+
+    lines.extend([
+        String::new(),
+        "## Conventions".to_string(),
+        String::new(),
+        "- Describe the code style, naming and test layout reviewers expect here.".to_string(),
+    ]);
+    for guidance in [".github/copilot-instructions.md", "CONTRIBUTING.md"] {
+        if root.join(guidance).is_file() {
+            lines.push(format!("\n@include {guidance}"));
+        }
+    }
+    lines.push(String::new());
+    lines.join("\n")
+}
+
+/// The first paragraph of the README that is not a heading or badge.
+fn readme_summary(root: &Path) -> Option<String> {
+    let readme = fs::read_to_string(root.join("README.md")).ok()?;
+    readme
+        .split("\n\n")
+        .map(str::trim)
+        .find(|paragraph| {
+            !paragraph.is_empty()
+                && !paragraph.starts_with('#')
+                && !paragraph.starts_with('[')
+                && !paragraph.starts_with('<')
+                && !paragraph.starts_with('!')
+        })
+        .map(|paragraph| {
+            paragraph
+                .lines()
+                .map(str::trim)
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+}
+
+/// Build, test and lint commands for the manifests found in `root`.
+fn project_commands(root: &Path) -> Vec<(String, &'static str)> {
+    let mut commands = Vec::new();
+    if root.join("Cargo.toml").is_file() {
+        commands.extend([
+            ("cargo build --workspace".to_string(), "build"),
+            ("cargo test --workspace".to_string(), "run the tests"),
+            (
+                "cargo clippy --workspace --all-targets -- -D warnings".to_string(),
+                "lint",
+            ),
+            ("cargo fmt --all".to_string(), "format"),
+        ]);
+    }
+    if let Ok(manifest) = fs::read_to_string(root.join("package.json")) {
+        commands.push(("npm install".to_string(), "install dependencies"));
+        let manifest = serde_json::from_str::<Value>(&manifest).unwrap_or_default();
+        if let Some(scripts) = manifest["scripts"].as_object() {
+            commands.extend(
+                scripts
+                    .keys()
+                    .map(|script| (format!("npm run {script}"), "package script")),
+            );
+        }
+    }
+    if root.join("pyproject.toml").is_file() {
+        commands.extend([
+            ("pip install -e .".to_string(), "install for development"),
+            ("pytest".to_string(), "run the tests"),
+        ]);
+    }
+    if root.join("go.mod").is_file() {
+        commands.extend([
+            ("go build ./...".to_string(), "build"),
+            ("go test ./...".to_string(), "run the tests"),
+        ]);
+    }
+    if root.join("Makefile").is_file() {
+        commands.push(("make".to_string(), "default make target"));
+    }
+    commands
+}
+
+fn top_level_directories(root: &Path) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(root) else {
+        return Vec::new();
+    };
+    let mut directories = entries
+        .filter_map(Result::ok)
+        .filter(|entry| entry.path().is_dir())
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .filter(|name| !name.starts_with('.') && !["target", "node_modules"].contains(&&**name))
+        .collect::<Vec<_>>();
+    directories.sort();
+    directories
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::temp_dir;
+
+    #[test]
+    fn test_discovers_files_from_root_to_cwd() {
+        // Test the user file comes first, then the project's from the root down
+        let dir = temp_dir();
+        let base = dir.path();
+        let user = base.join("config");
+        let root = base.join("repo");
+        let cwd = root.join("crates").join("core");
+        fs::create_dir_all(&user).unwrap();
+        fs::create_dir_all(&cwd).unwrap();
+        fs::write(user.join(INSTRUCTIONS_FILE_NAME), "Be brief.").unwrap();
+        fs::write(root.join(INSTRUCTIONS_FILE_NAME), "Run the tests.").unwrap();
+        fs::write(cwd.join(INSTRUCTIONS_FILE_NAME), "No unsafe here.").unwrap();
+        fs::write(root.join("crates").join(INSTRUCTIONS_FILE_NAME), "").unwrap();
+
+        let instructions = Instructions::load_from(Some(user.clone()), root.clone(), cwd.clone());
+        let scopes = instructions
+            .files
+            .iter()
+            .map(|file| (file.path.clone(), file.scope))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            scopes,
+            [
+                (user.join(INSTRUCTIONS_FILE_NAME), InstructionScope::User),
+                (root.join(INSTRUCTIONS_FILE_NAME), InstructionScope::Project),
+                (
+                    root.join("crates").join(INSTRUCTIONS_FILE_NAME),
+                    InstructionScope::Project
+                ),
+                (cwd.join(INSTRUCTIONS_FILE_NAME), InstructionScope::Project),
+            ]
+        );
+
+        let prompt = instructions.system_prompt();
+        assert!(prompt.starts_with(BASE_PROMPT));
+        let brief = prompt.find("Be brief.").unwrap();
+        let tests = prompt.find("Run the tests.").unwrap();
+        let unsafe_code = prompt.find("No unsafe here.").unwrap();
+        assert!(brief < tests && tests < unsafe_code);
+        assert!(prompt.contains("(user instructions)"));
+        assert_eq!(prompt.matches("## ").count(), 3);
+
+        fs::remove_file(cwd.join(INSTRUCTIONS_FILE_NAME)).unwrap();
+        let mut instructions = instructions;
+        instructions.reload();
+        assert_eq!(instructions.files.len(), 3);
+        assert_eq!(
+            Instructions::load_from(None, root.clone(), root.clone()).system_prompt(),
+            format!(
+                "{BASE_PROMPT}\n\n# Instructions\n\nFollow these instructions from FOUNDRY.md \
+                 files. When they disagree, later files are more specific and take \
+                 precedence.\n\n## {} (project instructions)\n\nRun the tests.",
+                display_path(&root.join(INSTRUCTIONS_FILE_NAME))
+            )
+        );
+    }
+
+    #[test]
+    fn test_includes_are_expanded() {
+        // Test @include lines are replaced, relative to the including file,
+        // except in code fences, and cycles, missing files and files outside
+        // the project are reported
+        let dir = temp_dir();
+        let root = dir.path().join("repo");
+        fs::create_dir_all(root.join("docs")).unwrap();
+        fs::write(
+            root.join(INSTRUCTIONS_FILE_NAME),
+            "Intro\n@include docs/style.md\n```\n@include docs/style.md\n```\n@include missing.md",
+        )
+        .unwrap();
+        fs::write(
+            root.join("docs/style.md"),
+            "Use tabs.\n@include ../FOUNDRY.md\n@include extra.md\n",
+        )
+        .unwrap();
+        fs::write(root.join("docs/extra.md"), "Nested.\n").unwrap();
+
+        let instructions = Instructions::load_from(None, root.to_path_buf(), root.to_path_buf());
+        let file = &instructions.files[0];
+        assert_eq!(
+            file.contents,
+            "Intro\nUse tabs.\nNested.\n```\n@include docs/style.md\n```"
+        );
+        assert_eq!(
+            file.includes,
+            [root.join("docs/style.md"), root.join("docs/extra.md")]
+        );
+        assert_eq!(file.problems.len(), 2);
+        assert!(file.problems[0].contains("it includes itself"));
+        assert!(file.problems[1].starts_with("Failed to include"));
+
+        fs::write(dir.path().join("secret.md"), "Secret.").unwrap();
+        fs::write(
+            root.join("docs/extra.md"),
+            "@include ../../secret.md\n@include ./../../repo/docs/../../secret.md\n",
+        )
+        .unwrap();
+        let instructions = Instructions::load_from(None, root.to_path_buf(), root.to_path_buf());
+        let file = &instructions.files[0];
+        assert!(!file.contents.contains("Secret."));
+        assert_eq!(
+            file.problems
+                .iter()
+                .filter(|problem| problem.contains("is outside"))
+                .count(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_starter_instructions_describe_the_repository() {
+        // Test the starter file lists commands, layout and existing guidance
+        let dir = temp_dir();
+        let root = dir.path();
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::create_dir_all(root.join(".github")).unwrap();
+        fs::create_dir_all(root.join("target")).unwrap();
+        fs::write(root.join("Cargo.toml"), "[package]").unwrap();
+        fs::write(
+            root.join("package.json"),
+            r#"{"scripts": {"lint": "eslint ."}}"#,
+        )
+        .unwrap();
+        fs::write(
+            root.join("README.md"),
+            "# Demo\n\n[![ci](badge)](link)\n\nA demo\nproject.\n\nMore.",
+        )
+        .unwrap();
+        fs::write(root.join(".github/copilot-instructions.md"), "Be nice.").unwrap();
+
+        let starter = starter_instructions(root);
+        let name = root.file_name().unwrap().to_string_lossy();
+        assert!(starter.starts_with(&format!("# {name}\n")));
+        assert!(starter.contains("## Overview\n\nA demo project.\n"));
+        assert!(starter.contains("- `cargo test --workspace`: run the tests"));
+        assert!(starter.contains("- `npm run lint`: package script"));
+        assert!(starter.contains("## Layout\n\n- `src/`\n"));
+        assert!(!starter.contains("`target/`"));
+        assert!(starter.ends_with("@include .github/copilot-instructions.md\n"));
+
+        fs::write(root.join(INSTRUCTIONS_FILE_NAME), &starter).unwrap();
+        let instructions = Instructions::load_from(None, root.to_path_buf(), root.to_path_buf());
+        assert!(instructions.files[0].contents.ends_with("Be nice."));
+    }
+}
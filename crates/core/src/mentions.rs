@@ -0,0 +1,242 @@
+use anyhow::{Context, Result, bail};
+use std::fs;
+use std::path::Path;
+
+/// Upper bound on files offered by the picker, to keep huge trees responsive.
+pub const MAX_WORKSPACE_FILES: usize = 20_000;
+
+/// A `@path` or `@path:start-end` reference found in a message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileReference {
+    pub path: String,
+    /// Inclusive, 1-based line range.
+    pub lines: Option<(usize, usize)>,
+}
+
+impl FileReference {
+    /// Parses the text after `@`, e.g. `src/lib.rs` or `src/lib.rs:10-40`.
+    /// A single line may be given as `src/lib.rs:10`.
+    pub fn parse(token: &str) -> Option<Self> {
+        if token.is_empty() {
+            return None;
+        }
+
+        let (path, lines) = match token.rsplit_once(':') {
+            Some((path, range)) if !path.is_empty() => match parse_line_range(range) {
+                Some(lines) => (path, Some(lines)),
+                None => (token, None),
+            },
+            _ => (token, None),
+        };
+
+        Some(Self {
+            path: path.to_string(),
+            lines,
+        })
+    }
+}
+
+fn parse_line_range(range: &str) -> Option<(usize, usize)> {
+    let (start, end) = range.split_once('-').unwrap_or((range, range));
+    let start = start.parse::<usize>().ok()?;
+    let end = end.parse::<usize>().ok()?;
+    (start >= 1 && start <= end).then_some((start, end))
+}
+
+/// Lists files under `root` relative to it, honouring `.gitignore`, global
+/// git excludes and `.ignore` files. Hidden files are included, apart from
+/// the `.git` directory itself.
+pub fn list_workspace_files(root: &Path) -> Vec<String> {
+    let walker = ignore::WalkBuilder::new(root)
+        .hidden(false)
+        .require_git(false)
+        .filter_entry(|entry| entry.file_name() != ".git")
+        .build();
+
+    let mut files = walker
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_some_and(|kind| kind.is_file()))
+        .filter_map(|entry| {
+            let relative = entry.path().strip_prefix(root).ok()?;
+            Some(relative.to_string_lossy().replace('\\', "/"))
+        })
+        .take(MAX_WORKSPACE_FILES)
+        .collect::<Vec<_>>();
+    files.sort();
+    files
+}
+
+/// Finds every `@reference` in `message`. An `@` only starts a reference at
+/// the beginning of the message or after whitespace, so e-mail addresses are
+/// left alone.
+pub fn find_references(message: &str) -> Vec<FileReference> {
+    message
+        .split_whitespace()
+        .filter_map(|word| word.strip_prefix('@'))
+        .filter_map(FileReference::parse)
+        .collect()
+}
+
+/// A message with its `@` mentions resolved.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AttachedMessage {
+    /// The message text followed by a context block for each attached file.
+    pub text: String,
+    /// Mentions left as plain text, each with the reason it was not attached.
+    pub unresolved: Vec<String>,
+}
+
+impl AttachedMessage {
+    /// A one-line summary of the mentions that were not attached.
+    pub fn warning(&self) -> Option<String> {
+        (!self.unresolved.is_empty())
+            .then(|| format!("Not attached: {}", self.unresolved.join("; ")))
+    }
+}
+
+/// Appends the contents of every file referenced in `message` as tagged
+/// context blocks after the message text. Only existing files inside `root`
+/// are attached; any other mention stays in the text as written and is
+/// reported in [`AttachedMessage::unresolved`].
+pub fn attach_file_references(message: &str, root: &Path) -> AttachedMessage {
+    let mut attached = AttachedMessage {
+        text: message.to_string(),
+        unresolved: Vec::new(),
+    };
+    for reference in find_references(message) {
+        match context_block(&reference, root) {
+            Ok(block) => attached.text.push_str(&block),
+            Err(err) => attached.unresolved.push(format!("{err:#}")),
+        }
+    }
+    attached
+}
+
+fn context_block(reference: &FileReference, root: &Path) -> Result<String> {
+    let contents = read_workspace_file(&reference.path, root)?;
+    let (body, range) = match reference.lines {
+        Some((start, end)) => {
+            let line_count = contents.lines().count();
+            if start > line_count {
+                bail!(
+                    "@{}:{start}-{end} is past the end of the file ({line_count} lines)",
+                    reference.path
+                );
+            }
+            let selected = contents
+                .lines()
+                .skip(start - 1)
+                .take(end - start + 1)
+                .collect::<Vec<_>>()
+                .join("\n");
+            (
+                selected,
+                format!(" lines=\"{start}-{}\"", end.min(line_count)),
+            )
+        }
+        None => (contents.trim_end().to_string(), String::new()),
+    };
+    Ok(format!(
+        "\n\n<file path=\"{}\"{range}>\n{body}\n</file>",
+        reference.path
+    ))
+}
+
+/// Reads `path` relative to `root`, refusing anything that resolves outside
+/// it, whether through `..`, an absolute path or a symlink.
+fn read_workspace_file(path: &str, root: &Path) -> Result<String> {
+    let root = root
+        .canonicalize()
+        .context("Failed to resolve the workspace root")?;
+    let resolved = match root.join(path).canonicalize() {
+        Ok(resolved) if resolved.is_file() => resolved,
+        _ => bail!("@{path} is not a file in the workspace"),
+    };
+    if !resolved.starts_with(&root) {
+        bail!("@{path} is outside the workspace");
+    }
+    fs::read_to_string(&resolved).with_context(|| format!("Failed to read @{path}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_file_reference() {
+        // Test plain paths, ranges and single lines are recognised
+        assert_eq!(
+            FileReference::parse("src/lib.rs:10-40"),
+            Some(FileReference {
+                path: "src/lib.rs".to_string(),
+                lines: Some((10, 40)),
+            })
+        );
+        assert_eq!(
+            FileReference::parse("src/lib.rs:7").unwrap().lines,
+            Some((7, 7))
+        );
+        assert_eq!(FileReference::parse("src/lib.rs").unwrap().lines, None);
+        assert_eq!(FileReference::parse("a:b").unwrap().path, "a:b");
+        assert_eq!(FileReference::parse(""), None);
+    }
+
+    #[test]
+    fn test_find_references_skips_email_addresses() {
+        // Test only whitespace-delimited @tokens count as references
+        let references = find_references("see @README.md and mail me@example.com");
+        assert_eq!(references.len(), 1);
+        assert_eq!(references[0].path, "README.md");
+    }
+
+    #[test]
+    fn test_workspace_files_and_attachments() {
+        // Test ignored files are skipped and ranges attach the selected lines
+        let dir = crate::testing::temp_dir();
+        let root = dir.path();
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::create_dir_all(root.join("target")).unwrap();
+        fs::create_dir_all(root.join(".git")).unwrap();
+        fs::write(root.join(".gitignore"), "target/\n").unwrap();
+        fs::write(root.join("src/lib.rs"), "one\ntwo\nthree\nfour\n").unwrap();
+        fs::write(root.join("target/out.txt"), "built").unwrap();
+
+        assert_eq!(list_workspace_files(root), [".gitignore", "src/lib.rs"]);
+
+        let message = attach_file_references("explain @src/lib.rs:2-3", root);
+        assert_eq!(
+            message.text,
+            "explain @src/lib.rs:2-3\n\n<file path=\"src/lib.rs\" lines=\"2-3\">\ntwo\nthree\n</file>"
+        );
+        assert_eq!(message.warning(), None);
+    }
+
+    #[test]
+    fn test_unresolved_mentions_stay_as_text() {
+        // Test missing, out-of-range and outside paths are reported, not attached
+        let dir = crate::testing::temp_dir();
+        let root = dir.path().join("project");
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("lib.rs"), "one\n").unwrap();
+        fs::write(dir.path().join("secret"), "hidden").unwrap();
+
+        let message = "ping @alice about @lib.rs:9-10 or @../secret and @/etc/passwd";
+        let attached = attach_file_references(message, &root);
+        assert_eq!(attached.text, message);
+        assert_eq!(
+            attached.unresolved,
+            [
+                "@alice is not a file in the workspace",
+                "@lib.rs:9-10 is past the end of the file (1 lines)",
+                "@../secret is outside the workspace",
+                "@/etc/passwd is outside the workspace",
+            ]
+        );
+        assert!(
+            attached
+                .warning()
+                .unwrap()
+                .starts_with("Not attached: @alice")
+        );
+    }
+}
@@ -0,0 +1,141 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const APP_DIR_NAME: &str = "foundry";
+
+/// Walks up from `start` to the nearest directory containing `.git`,
+/// falling back to `start` itself when no repository is found.
+pub fn find_project_root(start: &Path) -> PathBuf {
+    start
+        .ancestors()
+        .find(|dir| dir.join(".git").exists())
+        .unwrap_or(start)
+        .to_path_buf()
+}
+
+/// Returns the per-user state directory (`$XDG_STATE_HOME/foundry`, or
+/// `~/.local/state/foundry`), if a home directory can be determined.
+pub fn state_dir() -> Option<PathBuf> {
+    xdg_dir("XDG_STATE_HOME", ".local/state")
+}
+
+/// Returns the per-user config directory (`$XDG_CONFIG_HOME/foundry`, or
+/// `~/.config/foundry`), if a home directory can be determined.
+pub fn config_dir() -> Option<PathBuf> {
+    xdg_dir("XDG_CONFIG_HOME", ".config")
+}
+
+/// Turns an absolute project path into a single file-name-safe component,
+/// e.g. `/home/me/repo` becomes `-home-me-repo`.
+pub fn project_key(project_root: &Path) -> String {
+    project_root
+        .to_string_lossy()
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '.' || c == '_' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect()
+}
+
+/// Returns the checked-out branch of the repository at `root`, or the
+/// short commit id when `HEAD` is detached.
+pub fn git_branch(root: &Path) -> Option<String> {
+    let dot_git = root.join(".git");
+    // Worktrees and submodules use a `.git` file pointing at the real directory.
+    let git_dir = if dot_git.is_file() {
+        let contents = fs::read_to_string(&dot_git).ok()?;
+        root.join(contents.strip_prefix("gitdir:")?.trim())
+    } else {
+        dot_git
+    };
+
+    let head = fs::read_to_string(git_dir.join("HEAD")).ok()?;
+    let head = head.trim();
+    match head.strip_prefix("ref: ") {
+        Some(reference) => Some(
+            reference
+                .strip_prefix("refs/heads/")
+                .unwrap_or(reference)
+                .to_string(),
+        ),
+        None => Some(head.chars().take(7).collect()),
+    }
+}
+
+/// Shortens paths under the home directory to start with `~`.
+pub fn display_path(path: &Path) -> String {
+    match home_dir().and_then(|home| path.strip_prefix(home).ok().map(Path::to_path_buf)) {
+        Some(relative) if relative.as_os_str().is_empty() => "~".to_string(),
+        Some(relative) => format!("~/{}", relative.display()),
+        None => path.display().to_string(),
+    }
+}
+
+/// The first `program` executable found on `PATH`.
+pub fn find_on_path(program: &str) -> Option<PathBuf> {
+    let paths = env::var_os("PATH")?;
+    env::split_paths(&paths)
+        .map(|dir| dir.join(program))
+        .find(|path| path.is_file())
+}
+
+fn xdg_dir(variable: &str, home_fallback: &str) -> Option<PathBuf> {
+    let base = match env::var_os(variable) {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => home_dir()?.join(home_fallback),
+    };
+    Some(base.join(APP_DIR_NAME))
+}
+
+/// The user's home directory.
+pub fn home_dir() -> Option<PathBuf> {
+    env::var_os("HOME")
+        .or_else(|| env::var_os("USERPROFILE"))
+        .filter(|home| !home.is_empty())
+        .map(PathBuf::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::temp_dir;
+
+    #[test]
+    fn test_find_project_root_uses_git_directory() {
+        // Test the nearest ancestor containing .git is treated as the root
+        let dir = temp_dir();
+        let root = dir.path();
+        let nested = root.join("src").join("bin");
+        std::fs::create_dir_all(&nested).unwrap();
+        std::fs::create_dir_all(root.join(".git")).unwrap();
+
+        assert_eq!(find_project_root(&nested), root);
+    }
+
+    #[test]
+    fn test_project_key_is_single_component() {
+        // Test path separators are replaced so the key is a valid file name
+        let key = project_key(Path::new("/home/me/my repo"));
+        assert_eq!(key, "-home-me-my-repo");
+    }
+
+    #[test]
+    fn test_git_branch_reads_head() {
+        // Test branch names and detached heads are read from .git/HEAD
+        let dir = temp_dir();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join(".git")).unwrap();
+        assert_eq!(git_branch(root), None);
+
+        std::fs::write(root.join(".git/HEAD"), "ref: refs/heads/feature/x\n").unwrap();
+        assert_eq!(git_branch(root).as_deref(), Some("feature/x"));
+
+        std::fs::write(root.join(".git/HEAD"), "0123456789abcdef\n").unwrap();
+        assert_eq!(git_branch(root).as_deref(), Some("0123456"));
+    }
+}
@@ -0,0 +1,102 @@
+use clap::ValueEnum;
+use std::fmt;
+
+/// What a tool does, which decides whether it needs approval.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToolKind {
+    /// Reads files or searches the workspace.
+    Read,
+    /// Writes or deletes files.
+    Edit,
+    /// Runs commands or reaches the network.
+    Execute,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PermissionDecision {
+    Allow,
+    Ask,
+    Deny,
+}
+
+/// How much a turn may do without asking the user first.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum PermissionPolicy {
+    /// Reads are allowed; edits and commands need approval.
+    #[default]
+    Default,
+    /// Reads and edits are allowed; commands need approval.
+    AcceptEdits,
+    /// Only reads are allowed; everything else is denied.
+    ReadOnly,
+    /// Everything is allowed without asking.
+    BypassPermissions,
+}
+
+impl PermissionPolicy {
+    pub fn decide(&self, kind: ToolKind) -> PermissionDecision {
+        match (self, kind) {
+            (_, ToolKind::Read) | (Self::BypassPermissions, _) => PermissionDecision::Allow,
+            (Self::AcceptEdits, ToolKind::Edit) => PermissionDecision::Allow,
+            (Self::ReadOnly, _) => PermissionDecision::Deny,
+            (Self::Default | Self::AcceptEdits, _) => PermissionDecision::Ask,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Default => "default",
+            Self::AcceptEdits => "accept-edits",
+            Self::ReadOnly => "read-only",
+            Self::BypassPermissions => "bypass-permissions",
+        }
+    }
+}
+
+impl fmt::Display for PermissionPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::PERMISSION_MODES;
+
+    #[test]
+    fn test_policy_decisions() {
+        // Test each policy allows, asks about or denies each kind of tool
+        use PermissionDecision::*;
+        let decisions = |policy: PermissionPolicy| {
+            [ToolKind::Read, ToolKind::Edit, ToolKind::Execute].map(|kind| policy.decide(kind))
+        };
+        assert_eq!(decisions(PermissionPolicy::Default), [Allow, Ask, Ask]);
+        assert_eq!(
+            decisions(PermissionPolicy::AcceptEdits),
+            [Allow, Allow, Ask]
+        );
+        assert_eq!(decisions(PermissionPolicy::ReadOnly), [Allow, Deny, Deny]);
+        assert_eq!(
+            decisions(PermissionPolicy::BypassPermissions),
+            [Allow, Allow, Allow]
+        );
+    }
+
+    #[test]
+    fn test_policy_names_round_trip() {
+        // Test every policy parses back from its name, as listed for the config
+        let names = PermissionPolicy::value_variants()
+            .iter()
+            .map(PermissionPolicy::name)
+            .collect::<Vec<_>>();
+        assert_eq!(names, PERMISSION_MODES);
+        for policy in PermissionPolicy::value_variants() {
+            assert_eq!(
+                PermissionPolicy::from_str(policy.name(), false),
+                Ok(*policy)
+            );
+        }
+        assert!(PermissionPolicy::from_str("yolo", false).is_err());
+    }
+}
@@ -0,0 +1,259 @@
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Version of the event schema. Bump it whenever an event changes in a way
+/// that could break consumers; adding optional fields does not count.
+pub const SCHEMA_VERSION: u32 = 2;
+
+/// Something that happened during a session, as streamed by
+/// `--output-format stream-json`, one JSON object per line.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    /// Sent when a session starts and whenever its settings change.
+    System {
+        schema_version: u32,
+        session_id: String,
+        cwd: String,
+        permission_mode: String,
+        model: Option<String>,
+        /// The `FOUNDRY.md` files in the system prompt.
+        #[serde(default)]
+        instructions: Vec<String>,
+    },
+    /// The prompt that starts a turn.
+    UserMessage {
+        text: String,
+    },
+    /// A piece of the assistant's answer.
+    MessageDelta {
+        text: String,
+    },
+    ToolUse {
+        id: String,
+        name: String,
+        input: String,
+    },
+    ToolResult {
+        id: String,
+        is_error: bool,
+        output: String,
+    },
+    /// A tool needs approval, answered with `InputMessage::PermissionResponse`.
+    PermissionRequest {
+        id: String,
+        tool: String,
+        input: String,
+    },
+    Usage(Usage),
+    Error {
+        message: String,
+    },
+    /// Always the last event of a turn.
+    Result(TurnResult),
+}
+
+/// A message from the program driving a session, read by
+/// `--input-format stream-json`, one JSON object per line.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum InputMessage {
+    /// Starts a turn.
+    UserMessage { text: String },
+    /// Answers the `Event::PermissionRequest` with the same id.
+    PermissionResponse { id: String, allow: bool },
+    /// Stops the running turn.
+    Interrupt,
+    /// Switches to one of the configured deployments.
+    SetModel { model: String },
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Usage {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    /// Estimated cost in US dollars.
+    pub cost_usd: f64,
+}
+
+impl Usage {
+    pub fn add(&mut self, other: &Usage) {
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.cost_usd += other.cost_usd;
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolCallRecord {
+    pub id: String,
+    pub name: String,
+    pub input: String,
+    /// `None` while the tool has not returned.
+    pub output: Option<String>,
+    pub is_error: bool,
+}
+
+/// Everything a turn produced, as printed by `--output-format json`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TurnResult {
+    pub schema_version: u32,
+    pub session_id: String,
+    pub is_error: bool,
+    pub answer: String,
+    pub tool_calls: Vec<ToolCallRecord>,
+    pub usage: Usage,
+    pub errors: Vec<String>,
+    pub duration_ms: u64,
+}
+
+impl TurnResult {
+    pub fn new(session_id: impl Into<String>) -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            session_id: session_id.into(),
+            is_error: false,
+            answer: String::new(),
+            tool_calls: Vec::new(),
+            usage: Usage::default(),
+            errors: Vec::new(),
+            duration_ms: 0,
+        }
+    }
+
+    /// Folds a streamed event into the result.
+    pub fn record(&mut self, event: &Event) {
+        match event {
+            Event::MessageDelta { text } => self.answer.push_str(text),
+            Event::ToolUse { id, name, input } => self.tool_calls.push(ToolCallRecord {
+                id: id.clone(),
+                name: name.clone(),
+                input: input.clone(),
+                output: None,
+                is_error: false,
+            }),
+            Event::ToolResult {
+                id,
+                is_error,
+                output,
+            } => {
+                if let Some(call) = self.tool_calls.iter_mut().find(|call| call.id == *id) {
+                    call.output = Some(output.clone());
+                    call.is_error = *is_error;
+                }
+            }
+            Event::Usage(usage) => self.usage.add(usage),
+            Event::Error { message } => {
+                self.errors.push(message.clone());
+                self.is_error = true;
+            }
+            Event::System { .. }
+            | Event::UserMessage { .. }
+            | Event::PermissionRequest { .. }
+            | Event::Result(_) => {}
+        }
+    }
+}
+
+/// A new session id, unique per process start.
+pub fn new_session_id() -> String {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis());
+    format!("{millis:x}-{:x}", std::process::id())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_events_are_tagged_by_type() {
+        // Test events serialize with a snake_case type tag and round-trip
+        let event = Event::ToolUse {
+            id: "t1".to_string(),
+            name: "grep".to_string(),
+            input: "foo".to_string(),
+        };
+        let json = serde_json::to_string(&event).unwrap();
+        assert_eq!(
+            json,
+            r#"{"type":"tool_use","id":"t1","name":"grep","input":"foo"}"#
+        );
+        assert_eq!(serde_json::from_str::<Event>(&json).unwrap(), event);
+
+        let usage = serde_json::to_value(Event::Usage(Usage::default())).unwrap();
+        assert_eq!(usage["type"], "usage");
+        assert_eq!(usage["input_tokens"], 0);
+    }
+
+    #[test]
+    fn test_result_records_events() {
+        // Test deltas, tool calls, usage and errors are folded into the result
+        let mut result = TurnResult::new("s1");
+        let events = [
+            Event::MessageDelta {
+                text: "Hel".to_string(),
+            },
+            Event::ToolUse {
+                id: "t1".to_string(),
+                name: "read".to_string(),
+                input: "a.rs".to_string(),
+            },
+            Event::ToolResult {
+                id: "t1".to_string(),
+                is_error: true,
+                output: "missing".to_string(),
+            },
+            Event::MessageDelta {
+                text: "lo".to_string(),
+            },
+            Event::Usage(Usage {
+                input_tokens: 10,
+                output_tokens: 2,
+                cost_usd: 0.5,
+            }),
+            Event::Usage(Usage {
+                input_tokens: 5,
+                output_tokens: 1,
+                cost_usd: 0.25,
+            }),
+        ];
+        for event in &events {
+            result.record(event);
+        }
+        assert_eq!(result.answer, "Hello");
+        assert_eq!(result.tool_calls[0].output.as_deref(), Some("missing"));
+        assert!(result.tool_calls[0].is_error);
+        assert_eq!(result.usage.input_tokens, 15);
+        assert_eq!(result.usage.cost_usd, 0.75);
+        assert!(!result.is_error);
+
+        result.record(&Event::Error {
+            message: "boom".to_string(),
+        });
+        assert!(result.is_error);
+        assert_eq!(result.errors, vec!["boom"]);
+    }
+
+    #[test]
+    fn test_parse_input_messages() {
+        // Test input messages are read by their type tag
+        let message = serde_json::from_str::<InputMessage>(
+            r#"{"type":"permission_response","id":"p1","allow":true}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            message,
+            InputMessage::PermissionResponse {
+                id: "p1".to_string(),
+                allow: true
+            }
+        );
+        assert_eq!(
+            serde_json::from_str::<InputMessage>(r#"{"type":"interrupt"}"#).unwrap(),
+            InputMessage::Interrupt
+        );
+        assert!(serde_json::from_str::<InputMessage>(r#"{"type":"shutdown"}"#).is_err());
+    }
+}
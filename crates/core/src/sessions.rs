@@ -0,0 +1,215 @@
+use anyhow::{Context, Result, bail};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::paths::{find_project_root, project_key, state_dir};
+use crate::protocol::Event;
+
+/// A recorded session, as listed by `foundry sessions list`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionSummary {
+    pub id: String,
+    pub modified: SystemTime,
+    /// The prompt of the first turn.
+    pub title: Option<String>,
+    pub turns: usize,
+}
+
+/// The sessions `SessionStore::list` could read, and why others were not.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SessionList {
+    pub sessions: Vec<SessionSummary>,
+    pub skipped: Vec<String>,
+}
+
+/// Transcripts of the sessions run in one project, one file of
+/// newline-delimited events per session.
+#[derive(Debug, Clone)]
+pub struct SessionStore {
+    dir: PathBuf,
+}
+
+impl SessionStore {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    /// The store for the project containing `cwd`, if a home directory can
+    /// be determined.
+    pub fn for_project(cwd: &Path) -> Option<Self> {
+        let project_root = find_project_root(cwd);
+        state_dir().map(|dir| Self::new(dir.join("sessions").join(project_key(&project_root))))
+    }
+
+    fn path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{id}.jsonl"))
+    }
+
+    pub fn append(&self, id: &str, event: &Event) -> Result<()> {
+        fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create {}", self.dir.display()))?;
+        let path = self.path(id);
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("Failed to open session {}", path.display()))?;
+        writeln!(file, "{}", serde_json::to_string(event)?)
+            .with_context(|| format!("Failed to write session {}", path.display()))
+    }
+
+    pub fn load(&self, id: &str) -> Result<Vec<Event>> {
+        let path = self.path(id);
+        let contents = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read session {}", path.display()))?;
+        contents
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
+                serde_json::from_str(line)
+                    .with_context(|| format!("Failed to parse session {}", path.display()))
+            })
+            .collect()
+    }
+
+    /// Ids of the recorded sessions with when each was last written.
+    fn ids(&self) -> Result<Vec<(String, SystemTime)>> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => {
+                return Err(err)
+                    .with_context(|| format!("Failed to list sessions in {}", self.dir.display()));
+            }
+        };
+
+        let mut ids = Vec::new();
+        for entry in entries.filter_map(Result::ok) {
+            let path = entry.path();
+            let Some(id) = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.strip_suffix(".jsonl"))
+            else {
+                continue;
+            };
+            let modified = entry
+                .metadata()
+                .and_then(|metadata| metadata.modified())
+                .unwrap_or(SystemTime::UNIX_EPOCH);
+            ids.push((id.to_string(), modified));
+        }
+        Ok(ids)
+    }
+
+    /// Recorded sessions, most recently used first. Sessions that cannot be
+    /// read are left out and described in `skipped`.
+    pub fn list(&self) -> Result<SessionList> {
+        let mut list = SessionList::default();
+        for (id, modified) in self.ids()? {
+            let events = match self.load(&id) {
+                Ok(events) => events,
+                Err(err) => {
+                    list.skipped.push(format!("{err:#}"));
+                    continue;
+                }
+            };
+            let prompts = events.iter().filter_map(|event| match event {
+                Event::UserMessage { text } => Some(text),
+                _ => None,
+            });
+            list.sessions.push(SessionSummary {
+                title: prompts.clone().next().cloned(),
+                turns: prompts.count(),
+                id,
+                modified,
+            });
+        }
+        list.sessions
+            .sort_by_key(|session| std::cmp::Reverse(session.modified));
+        Ok(list)
+    }
+
+    /// Finds the one session whose id starts with `prefix`.
+    pub fn resolve(&self, prefix: &str) -> Result<String> {
+        let matches = self
+            .ids()?
+            .into_iter()
+            .map(|(id, _)| id)
+            .filter(|id| id.starts_with(prefix))
+            .collect::<Vec<_>>();
+        match matches.as_slice() {
+            [id] => Ok(id.clone()),
+            [] => bail!("No session matches '{prefix}'"),
+            _ => bail!("'{prefix}' matches {} sessions", matches.len()),
+        }
+    }
+
+    pub fn remove(&self, id: &str) -> Result<()> {
+        let path = self.path(id);
+        fs::remove_file(&path)
+            .with_context(|| format!("Failed to remove session {}", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::temp_dir;
+
+    #[test]
+    fn test_session_store_records_and_lists() {
+        // Test sessions are appended, summarized, resolved by prefix and removed
+        let dir = temp_dir();
+        let store = SessionStore::new(dir.path().to_path_buf());
+        assert!(store.list().unwrap().sessions.is_empty());
+
+        for text in ["first prompt", "second prompt"] {
+            let event = Event::UserMessage {
+                text: text.to_string(),
+            };
+            store.append("abc123", &event).unwrap();
+        }
+        store
+            .append(
+                "abd456",
+                &Event::Error {
+                    message: "boom".to_string(),
+                },
+            )
+            .unwrap();
+
+        let sessions = store.list().unwrap().sessions;
+        let session = sessions.iter().find(|s| s.id == "abc123").unwrap();
+        assert_eq!(session.title.as_deref(), Some("first prompt"));
+        assert_eq!(session.turns, 2);
+
+        assert_eq!(store.resolve("abc").unwrap(), "abc123");
+        assert!(store.resolve("ab").is_err());
+        assert!(store.resolve("zzz").is_err());
+
+        store.remove("abc123").unwrap();
+        assert_eq!(store.list().unwrap().sessions.len(), 1);
+    }
+
+    #[test]
+    fn test_corrupt_sessions_are_skipped() {
+        // Test an unreadable transcript is reported but does not hide the others
+        let dir = temp_dir();
+        let store = SessionStore::new(dir.path().to_path_buf());
+        let event = Event::UserMessage {
+            text: "hello".to_string(),
+        };
+        store.append("good", &event).unwrap();
+        fs::write(dir.path().join("bad.jsonl"), "{not json\n").unwrap();
+
+        let list = store.list().unwrap();
+        assert_eq!(list.sessions.len(), 1);
+        assert_eq!(list.sessions[0].id, "good");
+        assert_eq!(list.skipped.len(), 1);
+        assert!(list.skipped[0].contains("bad.jsonl"));
+        assert_eq!(store.resolve("ba").unwrap(), "bad");
+    }
+}
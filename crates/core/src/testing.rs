@@ -0,0 +1,38 @@
+use anyhow::Result;
+use std::collections::VecDeque;
+use tempfile::TempDir;
+
+use crate::agent::{Conversation, EchoModel, Model, ModelStep};
+
+/// A fresh directory for one test. It is removed when dropped, even if the
+/// test panics, and never shared with tests running in parallel.
+pub fn temp_dir() -> TempDir {
+    tempfile::Builder::new()
+        .prefix("foundry-test-")
+        .tempdir()
+        .expect("Failed to create a temporary directory")
+}
+
+/// A model that takes the given steps in order, then echoes like
+/// [`EchoModel`] once they run out.
+#[derive(Debug, Default)]
+pub struct ScriptedModel {
+    steps: VecDeque<ModelStep>,
+}
+
+impl ScriptedModel {
+    pub fn new(steps: impl IntoIterator<Item = ModelStep>) -> Self {
+        Self {
+            steps: steps.into_iter().collect(),
+        }
+    }
+}
+
+impl Model for ScriptedModel {
+    fn next_step(&mut self, conversation: &Conversation) -> Result<ModelStep> {
+        match self.steps.pop_front() {
+            Some(step) => Ok(step),
+            None => EchoModel.next_step(conversation),
+        }
+    }
+}
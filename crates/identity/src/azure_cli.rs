@@ -0,0 +1,87 @@
+use anyhow::{Context, Result, bail};
+use serde::Deserialize;
+use std::process::Command;
+
+use crate::credentials::Credential;
+
+/// Resource that Azure AI Foundry tokens are issued for.
+pub const COGNITIVE_SERVICES_RESOURCE: &str = "https://cognitiveservices.azure.com";
+
+/// Signs in to the Azure CLI interactively, in the browser or with a device
+/// code, optionally to a specific tenant. The CLI talks to the user on the
+/// inherited terminal.
+pub fn login(device_code: bool, tenant: Option<&str>) -> Result<()> {
+    let mut command = Command::new("az");
+    command.arg("login");
+    if device_code {
+        command.arg("--use-device-code");
+    }
+    if let Some(tenant) = tenant {
+        command.args(["--tenant", tenant]);
+    }
+    let status = command
+        .status()
+        .context("Failed to run the Azure CLI (az); install it or sign in with --key")?;
+    if !status.success() {
+        bail!("az login failed with {status}");
+    }
+    Ok(())
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AccessTokenResponse {
+    access_token: String,
+    /// Unix time in seconds, reported by Azure CLI 2.54 and later.
+    #[serde(rename = "expires_on")]
+    expires_on: Option<u64>,
+}
+
+/// Gets an access token for Azure AI Foundry from the current Azure CLI
+/// sign-in, for `tenant` if given.
+pub fn access_token(tenant: Option<&str>) -> Result<Credential> {
+    let mut command = Command::new("az");
+    command
+        .args(["account", "get-access-token", "--output", "json"])
+        .args(["--resource", COGNITIVE_SERVICES_RESOURCE]);
+    if let Some(tenant) = tenant {
+        command.args(["--tenant", tenant]);
+    }
+    let output = command
+        .output()
+        .context("Failed to run the Azure CLI (az); install it or sign in with --key")?;
+    if !output.status.success() {
+        bail!(
+            "Azure CLI could not get a token: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    parse_access_token(&output.stdout)
+}
+
+fn parse_access_token(json: &[u8]) -> Result<Credential> {
+    let response: AccessTokenResponse =
+        serde_json::from_slice(json).context("Failed to parse the Azure CLI token")?;
+    Ok(Credential::AccessToken {
+        token: response.access_token,
+        expires_on: response.expires_on,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_access_token() {
+        // Test the Azure CLI token output is turned into a credential
+        let json = br#"{"accessToken":"abc","expiresOn":"2025-01-01 10:00:00.000000","expires_on":1735725600,"tenant":"t","tokenType":"Bearer"}"#;
+        assert_eq!(
+            parse_access_token(json).unwrap(),
+            Credential::AccessToken {
+                token: "abc".to_string(),
+                expires_on: Some(1_735_725_600)
+            }
+        );
+    }
+}
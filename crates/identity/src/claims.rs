@@ -0,0 +1,97 @@
+use anyhow::{Context, Result, bail};
+use serde::Deserialize;
+
+/// The claims of an Entra ID access token that are worth showing.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct TokenClaims {
+    pub name: Option<String>,
+    pub upn: Option<String>,
+    pub preferred_username: Option<String>,
+    pub unique_name: Option<String>,
+    /// Object id of the signed-in user or service principal.
+    pub oid: Option<String>,
+    /// Tenant id.
+    pub tid: Option<String>,
+    pub aud: Option<String>,
+    /// Expiry as Unix time in seconds.
+    pub exp: Option<u64>,
+}
+
+impl TokenClaims {
+    /// The best available account name.
+    pub fn account(&self) -> Option<&str> {
+        self.upn
+            .as_deref()
+            .or(self.preferred_username.as_deref())
+            .or(self.unique_name.as_deref())
+    }
+}
+
+/// Decodes the payload of a JWT without verifying its signature, which is
+/// enough for showing who a token belongs to.
+pub fn decode_claims(token: &str) -> Result<TokenClaims> {
+    let mut parts = token.split('.');
+    let (Some(_header), Some(payload), Some(_signature)) =
+        (parts.next(), parts.next(), parts.next())
+    else {
+        bail!("Access token is not a JWT");
+    };
+    let payload = decode_base64url(payload.trim_end_matches('='))
+        .context("Failed to decode access token claims")?;
+    serde_json::from_slice(&payload).context("Failed to parse access token claims")
+}
+
+/// Decodes unpadded base64url, the encoding JWT segments use.
+fn decode_base64url(text: &str) -> Result<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(text.len() * 3 / 4);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for c in text.bytes() {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'-' => 62,
+            b'_' => 63,
+            _ => bail!("Invalid base64url character '{}'", c as char),
+        };
+        buffer = buffer << 6 | u32::from(value);
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+        }
+    }
+    if bits >= 6 {
+        bail!("Truncated base64url input");
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_claims() {
+        // Test the payload of a token is decoded into its claims
+        // {"upn":"ada@contoso.com","tid":"tenant-1","exp":1700000000,"scp":"user"}
+        let payload = "eyJ1cG4iOiJhZGFAY29udG9zby5jb20iLCJ0aWQiOiJ0ZW5hbnQtMSIsImV4cCI6MTcwMDAwMDAwMCwic2NwIjoidXNlciJ9";
+        let claims = decode_claims(&format!("header.{payload}.signature")).unwrap();
+        assert_eq!(claims.account(), Some("ada@contoso.com"));
+        assert_eq!(claims.tid.as_deref(), Some("tenant-1"));
+        assert_eq!(claims.exp, Some(1_700_000_000));
+
+        assert!(decode_claims("not-a-token").is_err());
+    }
+
+    #[test]
+    fn test_decode_base64url() {
+        // Test the URL-safe alphabet is decoded and malformed input rejected
+        assert_eq!(decode_base64url("YT8-").unwrap(), b"a?>");
+        assert_eq!(decode_base64url("YWI").unwrap(), b"ab");
+        assert_eq!(decode_base64url("").unwrap(), b"");
+        assert!(decode_base64url("YT8+").is_err());
+        assert!(decode_base64url("YWJjZ").is_err());
+    }
+}
@@ -0,0 +1,303 @@
+use anyhow::{Context, Result};
+use foundry_core::paths::state_dir;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// How to sign in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LoginMethod {
+    /// `az login --use-device-code`, for machines without a browser.
+    Device,
+    /// `az login` in the browser.
+    Browser,
+    /// Reuse an existing Azure CLI sign-in without prompting.
+    AzureCli,
+    ApiKey(String),
+}
+
+/// A saved credential.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Credential {
+    ApiKey {
+        key: String,
+    },
+    /// Signed in through the Azure CLI. Only the choice is saved; tokens are
+    /// fetched from the CLI whenever one is needed, so they never go stale
+    /// on disk.
+    AzureCli {
+        tenant: Option<String>,
+    },
+    AccessToken {
+        token: String,
+        /// Unix time in seconds.
+        expires_on: Option<u64>,
+    },
+}
+
+impl Credential {
+    /// The last few characters of an API key, e.g. `••••3f9a`.
+    pub fn masked_key(key: &str) -> String {
+        let tail = key
+            .chars()
+            .rev()
+            .take(4)
+            .collect::<Vec<_>>()
+            .into_iter()
+            .rev()
+            .collect::<String>();
+        format!("••••{tail}")
+    }
+
+    /// Whether an access token has expired; API keys never do.
+    pub fn is_expired(&self) -> bool {
+        match self {
+            Self::ApiKey { .. } | Self::AzureCli { .. } => false,
+            Self::AccessToken { expires_on, .. } => {
+                expires_on.is_some_and(|expires_on| expires_on <= unix_now())
+            }
+        }
+    }
+}
+
+/// Environment variable holding an API key, which takes precedence over
+/// saved credentials.
+pub const API_KEY_ENV: &str = "FOUNDRY_API_KEY";
+
+/// Where a resolved credential came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CredentialSource {
+    Environment,
+    Saved,
+    AzureCli,
+}
+
+impl CredentialSource {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Environment => API_KEY_ENV,
+            Self::Saved => "saved credentials",
+            Self::AzureCli => "Azure CLI",
+        }
+    }
+}
+
+/// Finds a credential by trying, in order: `FOUNDRY_API_KEY`, the saved
+/// credential and the current Azure CLI sign-in. Azure CLI tokens are for
+/// `tenant`, the configured one, or else the tenant the sign-in was saved
+/// with.
+pub fn resolve_credential(
+    store: Option<&CredentialStore>,
+    tenant: Option<&str>,
+) -> Result<(Credential, CredentialSource)> {
+    if let Some(key) = std::env::var(API_KEY_ENV)
+        .ok()
+        .filter(|key| !key.trim().is_empty())
+    {
+        return Ok((Credential::ApiKey { key }, CredentialSource::Environment));
+    }
+    match store.map(CredentialStore::load).transpose()?.flatten() {
+        Some(Credential::AzureCli { tenant: saved }) => {
+            let credential = crate::azure_cli::access_token(tenant.or(saved.as_deref()))?;
+            return Ok((credential, CredentialSource::AzureCli));
+        }
+        Some(credential) => return Ok((credential, CredentialSource::Saved)),
+        None => {}
+    }
+    let credential = crate::azure_cli::access_token(tenant)
+        .context("No credentials found: set FOUNDRY_API_KEY or run `foundry login`")?;
+    Ok((credential, CredentialSource::AzureCli))
+}
+
+/// Seconds since the Unix epoch.
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+/// The saved credential, kept in a file only the current user can read.
+/// A profile's store falls back to the shared credential until the profile
+/// signs in itself.
+#[derive(Debug, Clone)]
+pub struct CredentialStore {
+    path: PathBuf,
+    fallback: Option<PathBuf>,
+}
+
+impl CredentialStore {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            fallback: None,
+        }
+    }
+
+    /// A store that reads `fallback` while `path` holds no credential.
+    pub fn with_fallback(path: PathBuf, fallback: PathBuf) -> Self {
+        Self {
+            path,
+            fallback: Some(fallback),
+        }
+    }
+
+    /// `$XDG_STATE_HOME/foundry/credentials.json`, if a home directory can
+    /// be determined.
+    pub fn default_location() -> Option<Self> {
+        state_dir().map(|dir| Self::new(dir.join("credentials.json")))
+    }
+
+    /// `credentials.<profile>.json` beside the shared credentials, which it
+    /// falls back to, or the shared store without a profile.
+    pub fn for_profile(profile: Option<&str>) -> Option<Self> {
+        let Some(profile) = profile else {
+            return Self::default_location();
+        };
+        state_dir().map(|dir| {
+            Self::with_fallback(
+                dir.join(format!("credentials.{profile}.json")),
+                dir.join("credentials.json"),
+            )
+        })
+    }
+
+    pub fn load(&self) -> Result<Option<Credential>> {
+        match read_credential(&self.path)? {
+            Some(credential) => Ok(Some(credential)),
+            None => match &self.fallback {
+                Some(fallback) => read_credential(fallback),
+                None => Ok(None),
+            },
+        }
+    }
+
+    /// Writes the credential to a new file that only the current user can
+    /// read, then moves it into place, so the secret is never readable by
+    /// others, even briefly.
+    pub fn save(&self, credential: &Credential) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        let contents = serde_json::to_string_pretty(credential)?;
+        let temp = self.path.with_extension("json.tmp");
+        match fs::remove_file(&temp) {
+            Err(err) if err.kind() != ErrorKind::NotFound => {
+                return Err(err).with_context(|| format!("Failed to remove {}", temp.display()));
+            }
+            _ => {}
+        }
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        options
+            .open(&temp)
+            .and_then(|mut file| file.write_all(contents.as_bytes()))
+            .with_context(|| format!("Failed to write credentials {}", temp.display()))?;
+        fs::rename(&temp, &self.path)
+            .with_context(|| format!("Failed to write credentials {}", self.path.display()))
+    }
+
+    /// Deletes the saved credential, returning whether there was one. A
+    /// profile's store leaves the shared credential alone.
+    pub fn clear(&self) -> Result<bool> {
+        match fs::remove_file(&self.path) {
+            Ok(()) => Ok(true),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(false),
+            Err(err) => Err(err)
+                .with_context(|| format!("Failed to remove credentials {}", self.path.display())),
+        }
+    }
+}
+
+fn read_credential(path: &Path) -> Result<Option<Credential>> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(err) => {
+            return Err(err)
+                .with_context(|| format!("Failed to read credentials {}", path.display()));
+        }
+    };
+    let credential = serde_json::from_str(&contents)
+        .with_context(|| format!("Failed to parse credentials {}", path.display()))?;
+    Ok(Some(credential))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use foundry_core::testing::temp_dir;
+
+    #[test]
+    fn test_credential_store_round_trip() {
+        // Test credentials are saved privately, reloaded and cleared
+        let dir = temp_dir();
+        let path = dir.path().join("state/credentials.json");
+        let store = CredentialStore::new(path.clone());
+        assert_eq!(store.load().unwrap(), None);
+
+        let credential = Credential::ApiKey {
+            key: "secret-3f9a".to_string(),
+        };
+        store.save(&credential).unwrap();
+        assert_eq!(store.load().unwrap(), Some(credential));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        let credential = Credential::AzureCli { tenant: None };
+        store.save(&credential).unwrap();
+        assert_eq!(store.load().unwrap(), Some(credential));
+        assert!(store.clear().unwrap());
+        assert!(!store.clear().unwrap());
+    }
+
+    #[test]
+    fn test_profile_store_falls_back_to_shared() {
+        // Test a profile reads the shared credential until it saves its own
+        let dir = temp_dir();
+        let shared = CredentialStore::new(dir.path().join("credentials.json"));
+        let prod = CredentialStore::with_fallback(
+            dir.path().join("credentials.prod.json"),
+            dir.path().join("credentials.json"),
+        );
+        let key = |key: &str| Credential::ApiKey {
+            key: key.to_string(),
+        };
+        shared.save(&key("shared")).unwrap();
+        assert_eq!(prod.load().unwrap(), Some(key("shared")));
+
+        prod.save(&key("prod")).unwrap();
+        assert_eq!(prod.load().unwrap(), Some(key("prod")));
+        assert_eq!(shared.load().unwrap(), Some(key("shared")));
+        assert!(prod.clear().unwrap());
+        assert_eq!(prod.load().unwrap(), Some(key("shared")));
+    }
+
+    #[test]
+    fn test_masked_key_and_expiry() {
+        // Test keys are masked and only past token expiries count as expired
+        assert_eq!(Credential::masked_key("secret-3f9a"), "••••3f9a");
+        let expired = Credential::AccessToken {
+            token: String::new(),
+            expires_on: Some(1),
+        };
+        assert!(expired.is_expired());
+        let valid = Credential::AccessToken {
+            token: String::new(),
+            expires_on: Some(unix_now() + 3600),
+        };
+        assert!(!valid.is_expired());
+    }
+}
@@ -1 +1,11 @@
+// Identity
+// Credentials for Azure AI Foundry: how they are obtained, stored and inspected
 
+pub mod azure_cli;
+pub mod claims;
+pub mod credentials;
+
+pub use claims::{TokenClaims, decode_claims};
+pub use credentials::{
+    Credential, CredentialSource, CredentialStore, LoginMethod, resolve_credential,
+};
@@ -1,16 +1,116 @@
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 pub struct AppState {
     pub input: String,
     pub cursor_position: usize,
     pub output_history: Vec<String>,
     pub status_text: String,
     pub should_quit: bool,
+    pub pasted_blocks: Vec<PastedBlock>,
+    pub history: History,
+    pub reverse_search: Option<ReverseSearch>,
+    pub completion: Option<CompletionPopup>,
+    pub workspace_root: PathBuf,
+    pub workspace_files: Vec<String>,
+    pub theme: Theme,
+    pub keymap: Keymap,
+    pub pending_keys: Vec<KeyEvent>,
+    /// Console rows scrolled up from the newest output.
+    pub scroll_back: usize,
+    /// Width the console text was last drawn at, for counting wrapped rows;
+    /// zero before the first draw.
+    pub console_width: u16,
+    /// Vi-style modal editing, when enabled.
+    pub vim: Option<Vim>,
+    /// Set when the input should be opened in the external editor; the run
+    /// loop owns the terminal and performs the hand-off.
+    pub editor_requested: bool,
+    /// Instruction file `/memory edit` asked to open in the external editor.
+    pub instructions_edit_requested: Option<PathBuf>,
+    /// Set when Ctrl+Z asks for the process to be suspended.
+    pub suspend_requested: bool,
+    /// Console message or code block focused for copying.
+    pub focus: Option<SelectionTarget>,
+    /// Text waiting for the run loop to copy to the clipboard.
+    pub pending_copy: Option<String>,
+    /// A message a command asked to send to the model once its output is
+    /// shown.
+    pub pending_turn: Option<String>,
+    /// The open diff viewer, for reviewing proposed edits or `/diff`.
+    pub diff_view: Option<DiffView>,
+    /// Every file changed in this session, from its original content to the
+    /// latest.
+    pub session_changes: Vec<FileDiff>,
+    /// Connection, model, activity and usage shown in the status bar.
+    pub status_bar: StatusBar,
+    /// Tool invocations shown as blocks in the console.
+    pub tool_calls: Vec<ToolCall>,
+    /// Shows tool output in full instead of the compact preview.
+    pub verbose_transcript: bool,
+    /// Search over the console output, while open.
+    pub console_search: Option<ConsoleSearch>,
+    /// Settings from every config layer, shown and changed by `/config`.
+    pub config: LayeredConfig,
+    /// `FOUNDRY.md` files assembled into the system prompt.
+    pub instructions: Instructions,
+    /// Runs the conversation's turns.
+    pub agent: Agent,
+    /// Console tool call blocks by the agent's tool call id.
+    pub agent_tool_calls: HashMap<String, usize>,
+    /// The agent's tool call whose edit is open in the diff viewer.
+    pub pending_review: Option<String>,
 }
 
-use crate::commands::get_help_message;
+/// A large paste that is shown as a placeholder chip in the input box but
+/// expanded back to its full content when the input is submitted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PastedBlock {
+    pub placeholder: String,
+    pub content: String,
+    /// Character offset of the placeholder in the input.
+    pub start: usize,
+}
+
+impl PastedBlock {
+    fn end(&self) -> usize {
+        self.start + self.placeholder.chars().count()
+    }
+}
+
+use anyhow::{Context, Result, bail};
+use clap::ValueEnum;
+use crossterm::event::KeyEvent;
+use foundry_core::agent::{Agent, EchoModel, FileEdit};
+use foundry_core::config::LayeredConfig;
+use foundry_core::instructions::Instructions;
+use foundry_core::permissions::PermissionPolicy;
+use foundry_core::protocol::Event;
+use ratatui::widgets::{Paragraph, Wrap};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::commands::{connection_state, credential_store, get_help_message};
+use crate::completion::CompletionPopup;
+use crate::diff::{DiffPurpose, DiffView, FileDiff};
+use crate::history::{History, ReverseSearch};
+use crate::keymap::{Keymap, default_keymap_path};
+use crate::search::ConsoleSearch;
+use crate::selection::{SelectionTarget, selection_targets};
+use crate::status::{Activity, StatusBar};
+use crate::theme::{Theme, detect_theme, load_theme};
+use crate::tool_call::{ToolCall, ToolStatus};
+use crate::vim::Vim;
 
 const DEFAULT_STATUS_MESSAGE: &str = "🚀 Welcome to Azure AI Foundry Code!";
 
+/// Lines of context kept below a search match when scrolling to it.
+const SEARCH_CONTEXT_LINES: usize = 3;
+
+/// Pastes with more lines than this are collapsed into a placeholder chip.
+pub const LARGE_PASTE_LINE_THRESHOLD: usize = 10;
+/// Pastes with more characters than this are collapsed into a placeholder chip.
+pub const LARGE_PASTE_CHAR_THRESHOLD: usize = 1000;
+
 impl Default for AppState {
     fn default() -> Self {
         let mut output_history = get_help_message()
@@ -26,6 +126,35 @@ impl Default for AppState {
             output_history,
             status_text: DEFAULT_STATUS_MESSAGE.to_string(),
             should_quit: false,
+            pasted_blocks: Vec::new(),
+            history: History::default(),
+            reverse_search: None,
+            completion: None,
+            workspace_root: PathBuf::from("."),
+            workspace_files: Vec::new(),
+            theme: Theme::default(),
+            keymap: Keymap::default(),
+            pending_keys: Vec::new(),
+            scroll_back: 0,
+            console_width: 0,
+            vim: None,
+            editor_requested: false,
+            instructions_edit_requested: None,
+            suspend_requested: false,
+            focus: None,
+            pending_copy: None,
+            pending_turn: None,
+            diff_view: None,
+            session_changes: Vec::new(),
+            status_bar: StatusBar::default(),
+            tool_calls: Vec::new(),
+            verbose_transcript: false,
+            console_search: None,
+            config: LayeredConfig::default(),
+            instructions: Instructions::default(),
+            agent: Agent::new(Box::new(EchoModel), ".", PermissionPolicy::default()),
+            agent_tool_calls: HashMap::new(),
+            pending_review: None,
         }
     }
 }
@@ -36,17 +165,159 @@ impl AppState {
     }
 
     pub fn add_char(&mut self, c: char) {
-        self.input.insert(self.cursor_position, c);
+        let index = self.byte_index();
+        self.input.insert(index, c);
+        self.shift_pasted_blocks(self.cursor_position, 0, 1);
         self.cursor_position += 1;
     }
 
+    pub fn insert_str(&mut self, text: &str) {
+        let index = self.byte_index();
+        let length = text.chars().count();
+        self.input.insert_str(index, text);
+        self.shift_pasted_blocks(self.cursor_position, 0, length);
+        self.cursor_position += length;
+    }
+
     pub fn remove_char(&mut self) {
         if self.cursor_position > 0 {
             self.cursor_position -= 1;
-            self.input.remove(self.cursor_position);
+            let index = self.byte_index();
+            self.input.remove(index);
+            self.shift_pasted_blocks(self.cursor_position, 1, 0);
+        }
+    }
+
+    /// Inserts pasted text at the cursor as a single edit.
+    ///
+    /// Line endings are normalised to `\n`. Pastes above the large-paste
+    /// thresholds are replaced by a `[Pasted N lines]` chip, and the full text
+    /// is kept in `pasted_blocks` until the input is submitted.
+    pub fn paste(&mut self, text: &str) {
+        let text = text.replace("\r\n", "\n").replace('\r', "\n");
+        let line_count = text.lines().count();
+
+        if line_count > LARGE_PASTE_LINE_THRESHOLD
+            || text.chars().count() > LARGE_PASTE_CHAR_THRESHOLD
+        {
+            let placeholder = self.next_paste_placeholder(line_count);
+            let start = self.cursor_position;
+            self.insert_str(&placeholder);
+            self.pasted_blocks.push(PastedBlock {
+                placeholder,
+                content: text,
+                start,
+            });
+        } else {
+            self.insert_str(&text);
+        }
+    }
+
+    /// Returns the input with every paste placeholder replaced by its content.
+    pub fn expanded_input(&self) -> String {
+        let chars = self.input.chars().collect::<Vec<_>>();
+        let mut blocks = self.pasted_blocks.iter().collect::<Vec<_>>();
+        blocks.sort_by_key(|block| block.start);
+
+        let mut expanded = String::new();
+        let mut position = 0;
+        for block in blocks {
+            let Some(text) = chars.get(block.start..block.end()) else {
+                continue;
+            };
+            if text.iter().copied().ne(block.placeholder.chars()) {
+                continue;
+            }
+            expanded.extend(&chars[position..block.start]);
+            expanded.push_str(&block.content);
+            position = block.end();
+        }
+        expanded.extend(&chars[position..]);
+        expanded
+    }
+
+    fn shift_pasted_blocks(&mut self, start: usize, removed: usize, inserted: usize) {
+        shift_pasted_blocks(&mut self.pasted_blocks, start, removed, inserted);
+    }
+
+    /// Moves paste chips along with an edit made directly to `input`, e.g. by
+    /// vi mode, given the input as it was before. A repeated character makes
+    /// the edited span ambiguous, so both extremes are tried and the one that
+    /// keeps the most chips wins.
+    pub fn track_input_edit(&mut self, old: &str) {
+        if self.pasted_blocks.is_empty() || old == self.input {
+            return;
+        }
+        let old = old.chars().collect::<Vec<_>>();
+        let new = self.input.chars().collect::<Vec<_>>();
+        let shortest = old.len().min(new.len());
+        let prefix = common_length(old.iter(), new.iter()).min(shortest);
+        let suffix = common_length(old.iter().rev(), new.iter().rev()).min(shortest);
+
+        let alignments = [
+            (prefix, suffix.min(shortest - prefix)),
+            (prefix.min(shortest - suffix), suffix),
+        ];
+        self.pasted_blocks = alignments
+            .map(|(prefix, suffix)| {
+                let mut blocks = self.pasted_blocks.clone();
+                shift_pasted_blocks(
+                    &mut blocks,
+                    prefix,
+                    old.len() - prefix - suffix,
+                    new.len() - prefix - suffix,
+                );
+                blocks
+            })
+            .into_iter()
+            .max_by_key(Vec::len)
+            .unwrap_or_default();
+    }
+
+    fn next_paste_placeholder(&self, line_count: usize) -> String {
+        let duplicates = self
+            .pasted_blocks
+            .iter()
+            .filter(|block| block.content.lines().count() == line_count)
+            .count();
+        let noun = if line_count == 1 { "line" } else { "lines" };
+
+        if duplicates == 0 {
+            format!("[Pasted {line_count} {noun}]")
+        } else {
+            format!("[Pasted {line_count} {noun} #{}]", duplicates + 1)
+        }
+    }
+
+    fn cursor_line_and_column(&self) -> (usize, usize) {
+        let before_cursor = &self.input[..self.byte_index()];
+        let line = before_cursor.matches('\n').count();
+        let column = before_cursor
+            .rsplit('\n')
+            .next()
+            .map_or(0, |text| text.chars().count());
+        (line, column)
+    }
+
+    fn move_cursor_to_line(&mut self, target_line: usize, column: usize) {
+        let mut position = 0;
+        for (index, line) in self.input.split('\n').enumerate() {
+            let length = line.chars().count();
+            if index == target_line {
+                self.cursor_position = position + column.min(length);
+                return;
+            }
+            position += length + 1;
         }
     }
 
+    fn byte_index(&self) -> usize {
+        self.input
+            .char_indices()
+            .nth(self.cursor_position)
+            .map_or(self.input.len(), |(index, _)| index)
+    }
+
     pub fn move_cursor_left(&mut self) {
         if self.cursor_position > 0 {
             self.cursor_position -= 1;
@@ -54,20 +325,568 @@ impl AppState {
     }
 
     pub fn move_cursor_right(&mut self) {
-        if self.cursor_position < self.input.len() {
+        if self.cursor_position < self.input.chars().count() {
             self.cursor_position += 1;
         }
     }
 
+    /// Replaces the whole input, leaving the cursor at the end.
+    pub fn set_input(&mut self, text: &str) {
+        self.input = text.to_string();
+        self.cursor_position = self.input.chars().count();
+        self.pasted_blocks.clear();
+    }
+
+    pub fn is_cursor_on_first_line(&self) -> bool {
+        !self.input[..self.byte_index()].contains('\n')
+    }
+
+    pub fn is_cursor_on_last_line(&self) -> bool {
+        !self.input[self.byte_index()..].contains('\n')
+    }
+
+    pub fn move_cursor_up(&mut self) {
+        let (line, column) = self.cursor_line_and_column();
+        if line > 0 {
+            self.move_cursor_to_line(line - 1, column);
+        }
+    }
+
+    pub fn move_cursor_down(&mut self) {
+        let (line, column) = self.cursor_line_and_column();
+        if line + 1 < self.input.split('\n').count() {
+            self.move_cursor_to_line(line + 1, column);
+        }
+    }
+
+    /// Recalls the previous history entry into the input.
+    pub fn history_previous(&mut self) {
+        if let Some(entry) = self.history.older(&self.input).map(str::to_string) {
+            self.set_input(&entry);
+        }
+    }
+
+    /// Recalls the next history entry, or the draft once past the newest.
+    pub fn history_next(&mut self) {
+        if let Some(entry) = self.history.newer() {
+            self.set_input(&entry);
+        }
+    }
+
+    pub fn start_reverse_search(&mut self) {
+        self.reverse_search = Some(ReverseSearch {
+            original_input: self.input.clone(),
+            ..ReverseSearch::default()
+        });
+    }
+
+    /// Replaces the search query and re-runs it from the newest entry.
+    pub fn update_reverse_search(&mut self, query: String) {
+        if let Some(search) = &mut self.reverse_search {
+            search.match_index = self.history.search(&query, None);
+            search.query = query;
+        }
+    }
+
+    /// Moves the search to the next older match (Ctrl+R while searching).
+    pub fn reverse_search_older(&mut self) {
+        if let Some(search) = &mut self.reverse_search {
+            let before = search.match_index.or(Some(self.history.entries().len()));
+            if let Some(index) = self.history.search(&search.query, before) {
+                search.match_index = Some(index);
+            }
+        }
+    }
+
+    /// Ends the search, loading the matched entry into the input.
+    pub fn accept_reverse_search(&mut self) {
+        if let Some(search) = self.reverse_search.take() {
+            let entry = search
+                .match_index
+                .and_then(|index| self.history.get(index))
+                .map(str::to_string)
+                .unwrap_or(search.original_input);
+            self.set_input(&entry);
+        }
+    }
+
+    /// Ends the search, restoring the input as it was before searching.
+    pub fn cancel_reverse_search(&mut self) {
+        if let Some(search) = self.reverse_search.take() {
+            self.set_input(&search.original_input);
+        }
+    }
+
+    /// Recomputes the completion popup for the current input.
+    pub fn refresh_completion(&mut self) {
+        self.completion =
+            CompletionPopup::for_input(&self.input, self.cursor_position, &self.workspace_files);
+    }
+
+    /// Applies the highlighted completion and refreshes the popup, which may
+    /// now offer argument completions for the chosen command.
+    pub fn accept_completion(&mut self) {
+        if let Some(popup) = self.completion.take() {
+            let (input, cursor_position) = popup.apply();
+            let old = std::mem::replace(&mut self.input, input);
+            self.track_input_edit(&old);
+            self.cursor_position = cursor_position;
+            self.refresh_completion();
+        }
+    }
+
     pub fn clear_input(&mut self) {
         self.input.clear();
         self.cursor_position = 0;
+        self.pasted_blocks.clear();
+        self.completion = None;
+        if let Some(vim) = &mut self.vim {
+            vim.reset();
+        }
     }
 
     pub fn add_output(&mut self, output: String) {
         self.output_history.push(output);
     }
 
+    pub fn scroll_up(&mut self, lines: usize) {
+        let total_rows = self
+            .output_history
+            .iter()
+            .flat_map(|entry| entry.split('\n'))
+            .map(|line| self.rows(line))
+            .sum::<usize>();
+        self.scroll_back = (self.scroll_back + lines).min(total_rows);
+    }
+
+    pub fn scroll_down(&mut self, lines: usize) {
+        self.scroll_back = self.scroll_back.saturating_sub(lines);
+    }
+
+    /// Focuses the newest message or code block, or moves the focus up if
+    /// something is already focused.
+    pub fn focus_output(&mut self) {
+        if self.focus.is_some() {
+            self.move_focus(false);
+            return;
+        }
+        self.focus = selection_targets(&self.output_history).last().copied();
+        self.scroll_to_focus();
+    }
+
+    /// Moves the focus to the next (newer) or previous (older) target,
+    /// stopping at either end.
+    pub fn move_focus(&mut self, newer: bool) {
+        let targets = selection_targets(&self.output_history);
+        let Some(index) = self
+            .focus
+            .and_then(|focus| targets.iter().position(|target| *target == focus))
+        else {
+            return;
+        };
+        let index = if newer {
+            (index + 1).min(targets.len() - 1)
+        } else {
+            index.saturating_sub(1)
+        };
+        self.focus = Some(targets[index]);
+        self.scroll_to_focus();
+    }
+
+    /// Queues the focused text for copying and leaves focus mode.
+    pub fn yank_focus(&mut self) {
+        if let Some(focus) = self.focus.take() {
+            self.pending_copy = focus.text(&self.output_history);
+        }
+    }
+
+    /// Scrolls the console so the end of the focused target is at the bottom.
+    fn scroll_to_focus(&mut self) {
+        let Some(focus) = self.focus else {
+            return;
+        };
+        let last_line = focus.line_range(&self.output_history).end.saturating_sub(1);
+        self.scroll_back = self.lines_below(focus.message(), last_line);
+    }
+
+    /// Console rows below `line` of `entry`, as counted by `scroll_back`.
+    fn lines_below(&self, entry: usize, line: usize) -> usize {
+        let rest_of_entry = self.output_history[entry].split('\n').skip(line + 1);
+        let later_entries = self.output_history[entry + 1..]
+            .iter()
+            .flat_map(|entry| entry.split('\n'));
+        rest_of_entry
+            .chain(later_entries)
+            .map(|line| self.rows(line))
+            .sum()
+    }
+
+    /// Rows a console line takes once wrapped the way the console draws it.
+    fn rows(&self, line: &str) -> usize {
+        if self.console_width == 0 {
+            return 1;
+        }
+        Paragraph::new(line)
+            .wrap(Wrap { trim: true })
+            .line_count(self.console_width)
+    }
+
+    /// Opens the console search, or goes back to editing an open one.
+    pub fn start_console_search(&mut self) {
+        match &mut self.console_search {
+            Some(search) => search.editing = true,
+            None => self.console_search = Some(ConsoleSearch::new()),
+        }
+    }
+
+    pub fn update_console_search(&mut self, query: String) {
+        if let Some(search) = &mut self.console_search {
+            search.query = query;
+            search.update(&self.output_history);
+        }
+        self.scroll_to_search_match();
+    }
+
+    pub fn toggle_search_regex(&mut self) {
+        if let Some(search) = &mut self.console_search {
+            search.regex = !search.regex;
+            search.update(&self.output_history);
+        }
+        self.scroll_to_search_match();
+    }
+
+    /// Jumps to the next match towards older (`true`) or newer output.
+    pub fn jump_to_search_match(&mut self, older: bool) {
+        if let Some(search) = &mut self.console_search {
+            if older {
+                search.older();
+            } else {
+                search.newer();
+            }
+        }
+        self.scroll_to_search_match();
+    }
+
+    pub fn close_console_search(&mut self) {
+        self.console_search = None;
+        self.scroll_back = 0;
+    }
+
+    /// Scrolls so the current match sits a few lines above the bottom.
+    fn scroll_to_search_match(&mut self) {
+        let Some(found) = self
+            .console_search
+            .as_ref()
+            .and_then(ConsoleSearch::current_match)
+        else {
+            return;
+        };
+        self.scroll_back = self
+            .lines_below(found.entry, found.line)
+            .saturating_sub(SEARCH_CONTEXT_LINES);
+    }
+
+    /// Opens proposed edits for hunk-by-hunk approval.
+    pub fn propose_edits(&mut self, files: Vec<FileDiff>) {
+        let view = DiffView::new(files, DiffPurpose::Review);
+        if !view.files.is_empty() {
+            self.diff_view = Some(view);
+        }
+    }
+
+    /// Writes the accepted hunks of the edits under review and records them
+    /// as session changes, returning the number of files written. Each file
+    /// is checked against the disk first; files that changed since the edit
+    /// was proposed, or could not be written, stay open for review.
+    pub fn apply_review(&mut self) -> Result<usize> {
+        let Some(view) = self.diff_view.take() else {
+            return Ok(0);
+        };
+        let mut written = 0;
+        let mut unapplied = Vec::new();
+        let mut problems = Vec::new();
+        for file in view.files {
+            let content = file.apply_accepted();
+            if content == file.old {
+                continue;
+            }
+            match self.write_reviewed(&file, &content) {
+                Ok(()) => {
+                    self.record_change(file.path, &file.old, content);
+                    written += 1;
+                }
+                Err(err) => {
+                    problems.push(format!("{err:#}"));
+                    unapplied.push(file);
+                }
+            }
+        }
+        if !unapplied.is_empty() {
+            self.diff_view = Some(DiffView::new(unapplied, DiffPurpose::Review));
+            bail!("{}", problems.join("; "));
+        }
+        Ok(written)
+    }
+
+    /// Writes `content` over the file under review, unless the file no
+    /// longer holds the text the review was made against.
+    fn write_reviewed(&self, file: &FileDiff, content: &str) -> Result<()> {
+        let old = match fs::read_to_string(self.workspace_root.join(&file.path)) {
+            Ok(current) => Some(current),
+            // A new file is proposed against empty contents.
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => None,
+            Err(err) => {
+                return Err(err).with_context(|| format!("Failed to read {}", file.path.display()));
+            }
+        };
+        if old.as_deref().unwrap_or_default() != file.old {
+            bail!(
+                "{} changed on disk since the edit was proposed",
+                file.path.display()
+            );
+        }
+        FileEdit {
+            path: file.path.clone(),
+            old,
+            new: content.to_string(),
+        }
+        .apply(&self.workspace_root)
+    }
+
+    /// Applies the reviewed edit and hands the outcome back to the agent's
+    /// turn, returning the number of files written.
+    pub fn submit_review(&mut self) -> Result<usize> {
+        let written = self.apply_review()?;
+        if let Some(id) = self.pending_review.take() {
+            let result = if written == 0 {
+                self.agent.complete(&id, true, "The user rejected the edit")
+            } else {
+                self.agent
+                    .complete(&id, false, "The user applied the accepted hunks")
+            };
+            if let Err(err) = result {
+                self.set_status(format!("⚠️ {err:#}"));
+            }
+            self.run_agent();
+        }
+        Ok(written)
+    }
+
+    /// Closes the diff viewer, denying the agent's edit if it was under review.
+    pub fn discard_review(&mut self) {
+        self.diff_view = None;
+        if let Some(id) = self.pending_review.take() {
+            if let Err(err) = self.agent.respond(&id, false) {
+                self.set_status(format!("⚠️ {err:#}"));
+            }
+            self.run_agent();
+        }
+    }
+
+    /// Adds a change to the session, merging it with earlier changes to the
+    /// same file.
+    pub fn record_change(&mut self, path: PathBuf, old: &str, new: String) {
+        match self
+            .session_changes
+            .iter_mut()
+            .find(|change| change.path == path)
+        {
+            Some(change) => *change = FileDiff::new(path, change.old.clone(), new),
+            None => self.session_changes.push(FileDiff::new(path, old, new)),
+        }
+        self.session_changes.retain(|change| !change.is_empty());
+    }
+
+    /// Adds a running tool call block to the console, returning its id.
+    pub fn start_tool_call(&mut self, name: &str, args: &str) -> usize {
+        let call = ToolCall::new(name, args, self.output_history.len());
+        self.add_output(call.render(self.verbose_transcript));
+        self.tool_calls.push(call);
+        self.status_bar
+            .start_activity(Activity::RunningTool(name.to_string()));
+        self.tool_calls.len() - 1
+    }
+
+    pub fn finish_tool_call(&mut self, id: usize, succeeded: bool, output: &str) {
+        if let Some(call) = self.tool_calls.get_mut(id) {
+            call.finish(succeeded, output);
+            self.refresh_tool_call(id);
+        }
+        if self
+            .tool_calls
+            .iter()
+            .all(|call| call.status != ToolStatus::Running)
+        {
+            self.status_bar.finish_activity();
+        }
+    }
+
+    /// Sends a message to the agent, with the instruction files as they are
+    /// now, and shows the turn it starts.
+    pub fn start_turn(&mut self, message: &str) -> Result<()> {
+        self.instructions.reload();
+        self.agent
+            .set_system_prompt(self.instructions.system_prompt());
+        self.agent.submit(message)?;
+        self.status_bar.start_activity(Activity::Thinking);
+        self.run_agent();
+        Ok(())
+    }
+
+    /// Shows the agent's events until its turn ends or waits for approval.
+    pub fn run_agent(&mut self) {
+        while let Some(event) = self.agent.step() {
+            self.show_event(event);
+            for edit in self.agent.take_edits() {
+                let old = edit.old.unwrap_or_default();
+                self.record_change(edit.path, &old, edit.new);
+            }
+        }
+        // An interrupted turn takes its edit under review with it.
+        if self.pending_review.is_some() && self.agent.pending().is_none() {
+            self.pending_review = None;
+            self.diff_view = None;
+        }
+        self.status_bar.finish_activity();
+    }
+
+    fn show_event(&mut self, event: Event) {
+        match event {
+            Event::MessageDelta { text } => self.add_output(text),
+            Event::ToolUse { id, name, input } => {
+                let index = self.start_tool_call(&name, &input);
+                self.agent_tool_calls.insert(id, index);
+            }
+            Event::ToolResult {
+                id,
+                is_error,
+                output,
+            } => {
+                if let Some(index) = self.agent_tool_calls.remove(&id) {
+                    self.finish_tool_call(index, !is_error, &output);
+                }
+            }
+            Event::PermissionRequest { id, tool, .. } => {
+                let edit = self
+                    .agent
+                    .pending()
+                    .and_then(|pending| pending.edit.clone());
+                match edit {
+                    Some(edit) => {
+                        let old = edit.old.unwrap_or_default();
+                        self.propose_edits(vec![FileDiff::new(edit.path, old, edit.new)]);
+                        // An edit that changes nothing opens no viewer and
+                        // needs no review.
+                        if self.diff_view.is_some() {
+                            self.pending_review = Some(id);
+                            self.set_status(format!("🔒 Review the edit {tool} proposes"));
+                        } else if let Err(err) = self.agent.respond(&id, true) {
+                            self.set_status(format!("⚠️ {err:#}"));
+                        }
+                    }
+                    None => self.set_status(format!(
+                        "🔒 {tool} is waiting for approval; press Esc to interrupt the turn"
+                    )),
+                }
+            }
+            Event::Usage(usage) => {
+                self.status_bar
+                    .add_usage(usage.input_tokens, usage.output_tokens, usage.cost_usd);
+            }
+            Event::Error { message } => {
+                self.add_output(format!("⚠️ {message}"));
+                self.set_status(format!("⚠️ {message}"));
+            }
+            Event::System { .. } | Event::UserMessage { .. } | Event::Result(_) => {}
+        }
+    }
+
+    /// Expands or collapses the tool call shown in console entry `entry`,
+    /// returning `false` if the entry is not a tool call.
+    pub fn toggle_tool_call(&mut self, entry: usize) -> bool {
+        let Some(id) = self.tool_calls.iter().position(|call| call.entry == entry) else {
+            return false;
+        };
+        self.tool_calls[id].expanded = !self.tool_calls[id].expanded;
+        self.refresh_tool_call(id);
+        true
+    }
+
+    /// Switches between compact and verbose transcripts.
+    pub fn toggle_transcript_mode(&mut self) {
+        self.verbose_transcript = !self.verbose_transcript;
+        for id in 0..self.tool_calls.len() {
+            self.refresh_tool_call(id);
+        }
+    }
+
+    /// Number of leading console entries that will no longer change, i.e.
+    /// those before the first running tool call.
+    pub fn settled_output_len(&self) -> usize {
+        self.tool_calls
+            .iter()
+            .filter(|call| call.status == ToolStatus::Running)
+            .map(|call| call.entry)
+            .min()
+            .unwrap_or(self.output_history.len())
+    }
+
+    fn refresh_tool_call(&mut self, id: usize) {
+        let call = &self.tool_calls[id];
+        if let Some(entry) = self.output_history.get_mut(call.entry) {
+            *entry = call.render(self.verbose_transcript);
+        }
+    }
+
+    /// Called at a fixed rate by the run loop to animate the status bar.
+    pub fn on_tick(&mut self) {
+        self.status_bar.on_tick(&self.workspace_root);
+    }
+
+    /// Applies `config` to the running session: the deployment, profile and
+    /// sign-in state shown in the status bar, the theme and keymap, whether vi mode is on
+    /// and the permission mode tools run under. A theme or keymap that fails
+    /// to load is reported in the status line.
+    pub fn apply_config(&mut self) {
+        let profile = self.config.active_profile().map(str::to_string);
+        self.status_bar.profile_protected = profile
+            .as_deref()
+            .is_some_and(|name| self.config.is_protected(name));
+        self.status_bar.profile = profile;
+        self.status_bar.model = self.config.get_str("deployment").map(str::to_string);
+        self.status_bar.connection = connection_state(credential_store(&self.config).ok().as_ref());
+        self.theme = match self.config.get_str("theme").map(load_theme) {
+            Some(Ok(theme)) => theme,
+            Some(Err(err)) => {
+                self.set_status(format!("⚠️ {err:#}"));
+                detect_theme()
+            }
+            None => detect_theme(),
+        };
+        let keymap_path = match self.config.get_str("keymap") {
+            Some(path) => Some(PathBuf::from(path)),
+            None => default_keymap_path(),
+        };
+        self.keymap = match keymap_path.map(|path| Keymap::load(&path)) {
+            Some(Ok(keymap)) => keymap,
+            Some(Err(err)) => {
+                self.set_status(format!("⚠️ {err:#}"));
+                Keymap::default()
+            }
+            None => Keymap::default(),
+        };
+        let vim = self.config.get_bool("vim").unwrap_or(false);
+        if vim != self.vim.is_some() {
+            self.vim = vim.then(Vim::default);
+        }
+        // Invalid values are reported as config issues and left out.
+        self.agent.policy = self
+            .config
+            .get_str("permission_mode")
+            .and_then(|name| PermissionPolicy::from_str(name, false).ok())
+            .unwrap_or_default();
+    }
+
     pub fn set_status(&mut self, status: String) {
         self.status_text = status;
     }
@@ -77,9 +896,43 @@ impl AppState {
     }
 }
 
+/// Keeps paste chips on their placeholders after `removed` characters at
+/// `start` were replaced by `inserted` ones. A chip the edit touches is
+/// broken and stays behind as plain text.
+fn shift_pasted_blocks(
+    blocks: &mut Vec<PastedBlock>,
+    start: usize,
+    removed: usize,
+    inserted: usize,
+) {
+    let end = start + removed;
+    blocks.retain_mut(|block| {
+        if block.end() <= start {
+            true
+        } else if block.start >= end {
+            block.start = block.start - removed + inserted;
+            true
+        } else {
+            false
+        }
+    });
+}
+
+/// Number of leading items two sequences have in common.
+fn common_length<'a>(
+    first: impl Iterator<Item = &'a char>,
+    second: impl Iterator<Item = &'a char>,
+) -> usize {
+    first.zip(second).take_while(|(a, b)| a == b).count()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::keymap::Action;
+    use foundry_core::agent::ModelStep;
+    use foundry_core::config::ConfigFile;
+    use foundry_core::testing::{ScriptedModel, temp_dir};
 
     #[test]
     fn test_app_state_default() {
@@ -87,7 +940,7 @@ mod tests {
         let state = AppState::default();
         assert_eq!(state.input, "");
         assert_eq!(state.cursor_position, 0);
-        assert!(state.output_history.len() > 0);
+        assert!(!state.output_history.is_empty());
         assert!(state.output_history[0].contains("Available System Commands"));
         assert_eq!(state.status_text, "🚀 Welcome to Azure AI Foundry Code!");
         assert!(!state.should_quit);
@@ -123,9 +976,11 @@ mod tests {
     #[test]
     fn test_app_state_cursor_movement() {
         // Test cursor movement with boundary checks
-        let mut state = AppState::default();
-        state.input = "hello".to_string();
-        state.cursor_position = 2;
+        let mut state = AppState {
+            input: "hello".to_string(),
+            cursor_position: 2,
+            ..AppState::default()
+        };
 
         state.move_cursor_left();
         assert_eq!(state.cursor_position, 1);
@@ -146,9 +1001,11 @@ mod tests {
     #[test]
     fn test_app_state_clear_input() {
         // Test input clearing resets both text and cursor
-        let mut state = AppState::default();
-        state.input = "test".to_string();
-        state.cursor_position = 2;
+        let mut state = AppState {
+            input: "test".to_string(),
+            cursor_position: 2,
+            ..AppState::default()
+        };
         state.clear_input();
         assert_eq!(state.input, "");
         assert_eq!(state.cursor_position, 0);
@@ -172,4 +1029,484 @@ mod tests {
         state.quit();
         assert!(state.should_quit);
     }
+
+    #[test]
+    fn test_app_state_paste_small() {
+        // Test small pastes are inserted verbatim at the cursor
+        let mut state = AppState::default();
+        state.add_char('a');
+        state.add_char('b');
+        state.cursor_position = 1;
+        state.paste("line1\r\nline2");
+        assert_eq!(state.input, "aline1\nline2b");
+        assert_eq!(state.cursor_position, 12);
+        assert!(state.pasted_blocks.is_empty());
+    }
+
+    #[test]
+    fn test_app_state_paste_large() {
+        // Test large pastes collapse into a placeholder but expand on submit
+        let mut state = AppState::default();
+        let trace = (1..=240)
+            .map(|i| format!("frame {i}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        state.insert_str("see ");
+        state.paste(&trace);
+        assert_eq!(state.input, "see [Pasted 240 lines]");
+        assert_eq!(state.expanded_input(), format!("see {trace}"));
+
+        state.paste(&trace);
+        assert!(state.input.ends_with("[Pasted 240 lines #2]"));
+        assert_eq!(state.expanded_input(), format!("see {trace}{trace}"));
+
+        state.clear_input();
+        assert!(state.pasted_blocks.is_empty());
+    }
+
+    #[test]
+    fn test_paste_chips_follow_their_span() {
+        // Test chips expand by position, so typed look-alikes and broken chips stay text
+        let mut state = AppState::default();
+        let long_line = "x".repeat(LARGE_PASTE_CHAR_THRESHOLD + 1);
+        state.paste(&long_line);
+        assert_eq!(state.input, "[Pasted 1 line]");
+
+        state.cursor_position = 0;
+        state.insert_str("[Pasted 1 line] ");
+        assert_eq!(
+            state.expanded_input(),
+            format!("[Pasted 1 line] {long_line}")
+        );
+
+        state.cursor_position = state.input.chars().count();
+        state.remove_char();
+        assert_eq!(state.input, "[Pasted 1 line] [Pasted 1 line");
+        assert_eq!(state.expanded_input(), state.input);
+        assert!(state.pasted_blocks.is_empty());
+    }
+
+    #[test]
+    fn test_paste_chips_survive_direct_edits() {
+        // Test chips move with edits made straight to the input, as vi mode does
+        let mut state = AppState::default();
+        let long_line = "x".repeat(LARGE_PASTE_CHAR_THRESHOLD + 1);
+        state.insert_str("a ");
+        state.paste(&long_line);
+
+        let old = state.input.clone();
+        state.input.insert(2, '[');
+        state.track_input_edit(&old);
+        assert_eq!(state.expanded_input(), format!("a [{long_line}"));
+
+        let old = state.input.clone();
+        state.input = format!("{old} tail");
+        state.track_input_edit(&old);
+        assert_eq!(state.expanded_input(), format!("a [{long_line} tail"));
+
+        let old = state.input.clone();
+        state.input = state.input.replacen("line]", "", 1);
+        state.track_input_edit(&old);
+        assert!(state.pasted_blocks.is_empty());
+    }
+
+    #[test]
+    fn test_apply_config_toggles_vim() {
+        // Test the vim setting turns vi mode on and off
+        let root = foundry_core::testing::temp_dir();
+        let mut cli = ConfigFile::default();
+        cli.set("vim", "true").unwrap();
+        let mut app = AppState {
+            config: LayeredConfig::load_from(
+                root.path().join("user.toml"),
+                root.path(),
+                |_| None,
+                cli,
+            )
+            .unwrap(),
+            ..AppState::default()
+        };
+        app.apply_config();
+        assert!(app.vim.is_some());
+
+        app.config = LayeredConfig::default();
+        app.apply_config();
+        assert!(app.vim.is_none());
+    }
+
+    #[test]
+    fn test_apply_config_reloads_theme_and_keymap() {
+        // Test a changed theme or keymap setting takes effect straight away
+        let root = temp_dir();
+        let keymap = root.path().join("keymap.toml");
+        fs::write(
+            &keymap,
+            "[bindings]\n\"ctrl+c\" = \"none\"\n\"ctrl+q\" = \"quit\"",
+        )
+        .unwrap();
+        let mut cli = ConfigFile::default();
+        cli.set("theme", "light").unwrap();
+        cli.set("keymap", &keymap.display().to_string()).unwrap();
+        let mut app = AppState {
+            config: LayeredConfig::load_from(
+                root.path().join("user.toml"),
+                root.path(),
+                |_| None,
+                cli,
+            )
+            .unwrap(),
+            ..AppState::default()
+        };
+        app.apply_config();
+        assert_eq!(app.theme.name, "light");
+        assert_eq!(
+            app.keymap.primary_key(Action::Quit),
+            Some("Ctrl+Q".to_string())
+        );
+
+        fs::write(&keymap, "[bindings]\n\"ctrl+q\" = \"explode\"").unwrap();
+        app.apply_config();
+        assert!(app.status_text.contains("Invalid keymap"));
+        assert_eq!(
+            app.keymap.primary_key(Action::Quit),
+            Keymap::default().primary_key(Action::Quit)
+        );
+    }
+
+    #[test]
+    fn test_app_state_multibyte_cursor() {
+        // Test cursor positions are counted in characters, not bytes
+        let mut state = AppState::default();
+        state.insert_str("héllo");
+        assert_eq!(state.cursor_position, 5);
+        state.move_cursor_left();
+        state.remove_char();
+        assert_eq!(state.input, "hélo");
+        state.move_cursor_right();
+        state.move_cursor_right();
+        assert_eq!(state.cursor_position, 4);
+    }
+
+    #[test]
+    fn test_app_state_multiline_cursor() {
+        // Test vertical movement keeps the column and detects edge lines
+        let mut state = AppState::default();
+        state.set_input("first\nab\nthird");
+        assert!(state.is_cursor_on_last_line());
+        assert!(!state.is_cursor_on_first_line());
+
+        state.move_cursor_up();
+        assert_eq!(state.cursor_position, 8);
+        state.move_cursor_up();
+        assert_eq!(state.cursor_position, 2);
+        assert!(state.is_cursor_on_first_line());
+
+        state.move_cursor_down();
+        assert_eq!(state.cursor_position, 8);
+    }
+
+    #[test]
+    fn test_app_state_reverse_search() {
+        // Test Ctrl+R search selects matches and restores input on cancel
+        let mut state = AppState::default();
+        for entry in ["cargo build", "git status", "cargo test"] {
+            state.history.push(entry).unwrap();
+        }
+        state.set_input("draft");
+
+        state.start_reverse_search();
+        state.update_reverse_search("cargo".to_string());
+        state.reverse_search_older();
+        state.accept_reverse_search();
+        assert_eq!(state.input, "cargo build");
+        assert!(state.reverse_search.is_none());
+
+        state.start_reverse_search();
+        state.update_reverse_search("git".to_string());
+        state.cancel_reverse_search();
+        assert_eq!(state.input, "cargo build");
+    }
+
+    #[test]
+    fn test_focus_scrolling_counts_wrapped_rows() {
+        // Test lines wider than the console count once per wrapped row
+        let mut state = AppState {
+            output_history: vec![
+                "> hi".to_string(),
+                "Reply".to_string(),
+                "one two three four five six".to_string(),
+                String::new(),
+            ],
+            console_width: 10,
+            ..AppState::default()
+        };
+        state.focus_output();
+        state.move_focus(false);
+        assert_eq!(state.focus, Some(SelectionTarget::Message(1)));
+        assert_eq!(state.scroll_back, 4);
+
+        state.focus = None;
+        state.scroll_up(100);
+        assert_eq!(state.scroll_back, 6);
+    }
+
+    #[test]
+    fn test_focus_navigation_and_yank() {
+        // Test focus starts at the newest target, walks up and yanks its text
+        let mut state = AppState {
+            output_history: vec![
+                "> hi".to_string(),
+                "Reply\n```\ncode\n```".to_string(),
+                String::new(),
+            ],
+            ..AppState::default()
+        };
+
+        state.focus_output();
+        assert_eq!(
+            state.focus,
+            Some(SelectionTarget::CodeBlock {
+                message: 1,
+                block: 0
+            })
+        );
+        assert_eq!(state.scroll_back, 1);
+
+        state.focus_output();
+        assert_eq!(state.focus, Some(SelectionTarget::Message(1)));
+        state.move_focus(false);
+        state.move_focus(false);
+        assert_eq!(state.focus, Some(SelectionTarget::Message(0)));
+        assert_eq!(state.scroll_back, 5);
+
+        state.move_focus(true);
+        state.yank_focus();
+        assert_eq!(state.focus, None);
+        assert_eq!(state.pending_copy.as_deref(), Some("Reply\n```\ncode\n```"));
+    }
+
+    #[test]
+    fn test_apply_review_writes_accepted_hunks() {
+        // Test approved edits are written and merged into the session changes
+        let dir = temp_dir();
+        let root = dir.path().to_path_buf();
+        fs::write(root.join("notes.txt"), "one\n").unwrap();
+        let mut state = AppState {
+            workspace_root: root.clone(),
+            ..AppState::default()
+        };
+
+        state.propose_edits(vec![FileDiff::new("notes.txt", "one\n", "two\n")]);
+        state
+            .diff_view
+            .as_mut()
+            .unwrap()
+            .decide_all(crate::diff::HunkDecision::Accepted);
+        assert_eq!(state.apply_review().unwrap(), 1);
+        assert_eq!(fs::read_to_string(root.join("notes.txt")).unwrap(), "two\n");
+
+        state.propose_edits(vec![FileDiff::new("notes.txt", "two\n", "three\n")]);
+        assert_eq!(state.apply_review().unwrap(), 0);
+        state.record_change(PathBuf::from("notes.txt"), "two\n", "three\n".to_string());
+        assert_eq!(state.session_changes.len(), 1);
+        assert_eq!(state.session_changes[0].old, "one\n");
+        assert_eq!(state.session_changes[0].new, "three\n");
+
+        state.record_change(PathBuf::from("notes.txt"), "three\n", "one\n".to_string());
+        assert!(state.session_changes.is_empty());
+    }
+
+    #[test]
+    fn test_apply_review_keeps_files_changed_on_disk() {
+        // Test a file edited since the proposal is not overwritten and stays under review
+        let dir = temp_dir();
+        fs::write(dir.path().join("a.txt"), "a\n").unwrap();
+        fs::write(dir.path().join("b.txt"), "edited elsewhere\n").unwrap();
+        let mut state = AppState {
+            workspace_root: dir.path().to_path_buf(),
+            ..AppState::default()
+        };
+
+        state.propose_edits(vec![
+            FileDiff::new("a.txt", "a\n", "A\n"),
+            FileDiff::new("b.txt", "b\n", "B\n"),
+            FileDiff::new("c.txt", "", "new\n"),
+        ]);
+        state
+            .diff_view
+            .as_mut()
+            .unwrap()
+            .decide_all(crate::diff::HunkDecision::Accepted);
+        let err = state.apply_review().unwrap_err();
+        assert!(err.to_string().contains("b.txt changed on disk"));
+        assert_eq!(fs::read_to_string(dir.path().join("a.txt")).unwrap(), "A\n");
+        assert_eq!(
+            fs::read_to_string(dir.path().join("c.txt")).unwrap(),
+            "new\n"
+        );
+        assert_eq!(
+            fs::read_to_string(dir.path().join("b.txt")).unwrap(),
+            "edited elsewhere\n"
+        );
+
+        let view = state.diff_view.as_ref().unwrap();
+        assert_eq!(view.files.len(), 1);
+        assert_eq!(view.files[0].path, PathBuf::from("b.txt"));
+        assert_eq!(
+            view.files[0].decisions,
+            [crate::diff::HunkDecision::Accepted]
+        );
+        assert_eq!(state.session_changes.len(), 2);
+    }
+
+    #[test]
+    fn test_agent_edits_are_reviewed() {
+        // Test an edit waiting for approval opens for review and reports back to the turn
+        let dir = temp_dir();
+        fs::write(dir.path().join("notes.txt"), "one\n").unwrap();
+        let write = |content: &str| ModelStep::ToolCall {
+            name: "write_file".to_string(),
+            input: serde_json::json!({ "path": "notes.txt", "content": content }).to_string(),
+        };
+        let model = ScriptedModel::new([write("two\n"), write("three\n")]);
+        let mut state = AppState {
+            workspace_root: dir.path().to_path_buf(),
+            agent: Agent::new(Box::new(model), dir.path(), PermissionPolicy::Default),
+            ..AppState::default()
+        };
+
+        state.start_turn("edit").unwrap();
+        assert!(state.pending_review.is_some());
+        state
+            .diff_view
+            .as_mut()
+            .unwrap()
+            .decide_all(crate::diff::HunkDecision::Accepted);
+        assert_eq!(state.submit_review().unwrap(), 1);
+        assert_eq!(
+            fs::read_to_string(dir.path().join("notes.txt")).unwrap(),
+            "two\n"
+        );
+        assert_eq!(state.tool_calls[0].status, ToolStatus::Succeeded);
+
+        // The turn went on to propose a second edit, which is discarded.
+        assert!(state.pending_review.is_some());
+        state.discard_review();
+        assert_eq!(state.tool_calls[1].status, ToolStatus::Failed);
+        assert_eq!(
+            fs::read_to_string(dir.path().join("notes.txt")).unwrap(),
+            "two\n"
+        );
+        assert!(!state.agent.is_running());
+        assert_eq!(state.session_changes.len(), 1);
+    }
+
+    #[test]
+    fn test_allowed_agent_edits_are_recorded() {
+        // Test edits the policy allows without review still become session changes
+        let dir = temp_dir();
+        let write = ModelStep::ToolCall {
+            name: "write_file".to_string(),
+            input: r#"{"path":"new.txt","content":"hello\n"}"#.to_string(),
+        };
+        let mut state = AppState {
+            workspace_root: dir.path().to_path_buf(),
+            agent: Agent::new(
+                Box::new(ScriptedModel::new([write])),
+                dir.path(),
+                PermissionPolicy::AcceptEdits,
+            ),
+            ..AppState::default()
+        };
+        state.start_turn("write").unwrap();
+        assert!(state.diff_view.is_none());
+        assert_eq!(state.session_changes.len(), 1);
+        assert_eq!(state.session_changes[0].new, "hello\n");
+    }
+
+    #[test]
+    fn test_agent_events_are_shown() {
+        // Test tool calls become console blocks and a waiting turn can be interrupted
+        let root = temp_dir();
+        fs::write(root.path().join("notes.txt"), "remember\n").unwrap();
+        let tool_call = |name: &str, input: &str| ModelStep::ToolCall {
+            name: name.to_string(),
+            input: input.to_string(),
+        };
+        let model = ScriptedModel::new([
+            tool_call("read_file", r#"{"path":"notes.txt"}"#),
+            tool_call("write_file", r#"{"path":"notes.txt","content":"forget"}"#),
+        ]);
+        let mut state = AppState {
+            agent: Agent::new(Box::new(model), root.path(), PermissionPolicy::Default),
+            ..AppState::default()
+        };
+
+        state.start_turn("tidy up").unwrap();
+        assert_eq!(state.tool_calls.len(), 2);
+        assert_eq!(state.tool_calls[0].status, ToolStatus::Succeeded);
+        assert_eq!(state.tool_calls[1].status, ToolStatus::Running);
+        assert!(
+            state
+                .status_text
+                .contains("Review the edit write_file proposes")
+        );
+        assert!(state.start_turn("again").is_err());
+
+        assert!(state.agent.interrupt());
+        state.run_agent();
+        assert_eq!(state.tool_calls[1].status, ToolStatus::Failed);
+        assert!(state.diff_view.is_none());
+        assert_eq!(state.output_history.last().unwrap(), "⚠️ Interrupted");
+        assert_eq!(state.status_bar.activity, Activity::Idle);
+        assert!(!state.agent.is_running());
+    }
+
+    #[test]
+    fn test_tool_call_blocks() {
+        // Test tool calls render into the console and follow the transcript mode
+        let mut state = AppState::default();
+        let id = state.start_tool_call("read_file", "src/main.rs");
+        let entry = state.output_history.len() - 1;
+        assert!(state.output_history[entry].ends_with("running…"));
+        assert_eq!(state.settled_output_len(), entry);
+        assert!(matches!(
+            state.status_bar.activity,
+            Activity::RunningTool(_)
+        ));
+
+        let output = (1..=10).map(|i| format!("line {i}\n")).collect::<String>();
+        state.finish_tool_call(id, true, &output);
+        assert_eq!(state.status_bar.activity, Activity::Idle);
+        assert_eq!(state.settled_output_len(), state.output_history.len());
+        assert!(!state.output_history[entry].contains("line 10"));
+
+        state.toggle_transcript_mode();
+        assert!(state.output_history[entry].contains("line 10"));
+        state.toggle_transcript_mode();
+        assert!(state.toggle_tool_call(entry));
+        assert!(state.output_history[entry].contains("line 10"));
+        assert!(!state.toggle_tool_call(0));
+    }
+
+    #[test]
+    fn test_console_search_scrolls_to_match() {
+        // Test searching scrolls to the newest match and n/N move between matches
+        let mut state = AppState {
+            output_history: (1..=40).map(|i| format!("line {i}")).collect(),
+            ..AppState::default()
+        };
+        state.start_console_search();
+        state.update_console_search("line 1".to_string());
+        let search = state.console_search.as_ref().unwrap();
+        assert_eq!(search.matches.len(), 11);
+        assert_eq!(search.current_match().unwrap().entry, 18);
+        assert_eq!(state.scroll_back, 21 - SEARCH_CONTEXT_LINES);
+
+        state.jump_to_search_match(true);
+        assert_eq!(state.console_search.as_ref().unwrap().current, Some(9));
+        state.close_console_search();
+        assert!(state.console_search.is_none());
+        assert_eq!(state.scroll_back, 0);
+    }
 }
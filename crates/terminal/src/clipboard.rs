@@ -0,0 +1,77 @@
+use anyhow::{Context, Result, bail};
+use crossterm::{clipboard::CopyToClipboard, execute};
+use foundry_core::paths::find_on_path;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Native clipboard tools, tried in order when present on `PATH`.
+const NATIVE_TOOLS: &[(&str, &[&str])] = &[
+    ("wl-copy", &[]),
+    ("xclip", &["-selection", "clipboard"]),
+    ("xsel", &["--clipboard", "--input"]),
+    ("pbcopy", &[]),
+];
+
+/// Copies `text` with an OSC 52 escape sequence written to `out`, which
+/// reaches the local clipboard even over SSH when the terminal supports it,
+/// and with the first native clipboard tool found. Returns how it was copied.
+///
+/// A failing native tool is not an error, since the terminal may still have
+/// picked up the OSC 52 sequence.
+pub fn copy_to_clipboard(out: &mut impl Write, text: &str) -> Result<String> {
+    execute!(out, CopyToClipboard::to_clipboard_from(text))
+        .context("Failed to write OSC 52 sequence")?;
+
+    let native = native_tool().and_then(|(program, args)| copy_with(program, args, text).ok());
+    Ok(match native {
+        Some(program) => format!("OSC 52 and {program}"),
+        None => "OSC 52".to_string(),
+    })
+}
+
+fn native_tool() -> Option<(&'static str, &'static [&'static str])> {
+    NATIVE_TOOLS
+        .iter()
+        .copied()
+        .find(|(program, _)| is_on_path(program))
+}
+
+fn copy_with(program: &'static str, args: &[&str], text: &str) -> Result<&'static str> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .with_context(|| format!("Failed to run {program}"))?;
+    child
+        .stdin
+        .take()
+        .context("Failed to open clipboard tool input")?
+        .write_all(text.as_bytes())
+        .with_context(|| format!("Failed to write to {program}"))?;
+    let status = child
+        .wait()
+        .with_context(|| format!("Failed to wait for {program}"))?;
+    if !status.success() {
+        bail!("{program} exited with {status}");
+    }
+    Ok(program)
+}
+
+fn is_on_path(program: &str) -> bool {
+    find_on_path(program).is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_osc52_sequence() {
+        // Test the OSC 52 sequence carries the base64-encoded text
+        let mut out = Vec::new();
+        execute!(out, CopyToClipboard::to_clipboard_from("hello")).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "\x1b]52;c;aGVsbG8=\x1b\\");
+    }
+}
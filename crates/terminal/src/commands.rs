@@ -1,19 +1,825 @@
-pub fn get_help_message() -> Vec<&'static str> {
-    vec![
-        "Available System Commands (prefix with /):",
-        "  /help     - Show this help message",
-        "  /clear    - Clear the screen",
-        "  /exit     - Exit the application",
-        "  /login    - Login to system (coming soon)",
-        "  /logout   - Logout from system (coming soon)",
-        "",
-        "💡 Tips:",
-        "  • Use arrow keys (←→) to move cursor",
-        "  • Press Enter to execute commands",
-        "  • Press Esc to exit anytime",
-        "",
-        "Type any message or use system commands above to get started!",
-    ]
+use anyhow::{Context, Result, bail};
+use foundry_core::config::{ConfigEntry, ConfigFile, ConfigSource, LayeredConfig, PROFILE_KEYS};
+use foundry_core::instructions::{
+    INSTRUCTIONS_FILE_NAME, InstructionScope, Instructions, starter_instructions,
+};
+use foundry_core::paths::display_path;
+use foundry_core::protocol::Event;
+use foundry_core::sessions::SessionStore;
+use identity::credentials::{API_KEY_ENV, unix_now};
+use identity::{Credential, CredentialStore, LoginMethod, azure_cli, decode_claims};
+use std::fs;
+use std::io::{self, Write};
+use std::time::{Duration, SystemTime};
+
+use crate::app::AppState;
+use crate::completion::{Completion, complete_from};
+use crate::diff::{DiffPurpose, DiffView};
+use crate::editor::editor_command;
+use crate::selection::last_reply;
+use crate::status::{ConnectionState, format_elapsed};
+use crate::theme::{available_themes, load_theme};
+use crate::vim::Vim;
+
+/// A slash command known to the terminal, used for help and completion.
+pub struct SystemCommand {
+    pub name: &'static str,
+    pub description: &'static str,
+    /// Completes the argument being typed after the command name, if the
+    /// command takes one.
+    pub complete_argument: Option<fn(&str) -> Vec<Completion>>,
+}
+
+pub const SYSTEM_COMMANDS: &[SystemCommand] = &[
+    SystemCommand {
+        name: "help",
+        description: "Show this help message",
+        complete_argument: None,
+    },
+    SystemCommand {
+        name: "clear",
+        description: "Clear the screen",
+        complete_argument: None,
+    },
+    SystemCommand {
+        name: "exit",
+        description: "Exit the application",
+        complete_argument: None,
+    },
+    SystemCommand {
+        name: "copy",
+        description: "Copy the last reply to the clipboard",
+        complete_argument: None,
+    },
+    SystemCommand {
+        name: "diff",
+        description: "Review the files changed in this session",
+        complete_argument: None,
+    },
+    SystemCommand {
+        name: "theme",
+        description: "Switch color theme",
+        complete_argument: Some(complete_theme_name),
+    },
+    SystemCommand {
+        name: "keys",
+        description: "List active key bindings",
+        complete_argument: None,
+    },
+    SystemCommand {
+        name: "vim",
+        description: "Toggle vi-style editing",
+        complete_argument: None,
+    },
+    SystemCommand {
+        name: "login",
+        description: "Sign in with your Azure CLI account",
+        complete_argument: None,
+    },
+    SystemCommand {
+        name: "logout",
+        description: "Sign out and forget saved credentials",
+        complete_argument: None,
+    },
+    SystemCommand {
+        name: "whoami",
+        description: "Show the signed-in account",
+        complete_argument: None,
+    },
+    SystemCommand {
+        name: "config",
+        description: "Show effective settings and their sources, or get/set one",
+        complete_argument: Some(complete_config_action),
+    },
+    SystemCommand {
+        name: "models",
+        description: "List model deployments",
+        complete_argument: None,
+    },
+    SystemCommand {
+        name: "profile",
+        description: "List config profiles, or switch to one",
+        complete_argument: Some(complete_profile_name),
+    },
+    SystemCommand {
+        name: "memory",
+        description: "Show or edit the FOUNDRY.md instruction files",
+        complete_argument: Some(complete_memory_action),
+    },
+    SystemCommand {
+        name: "init",
+        description: "Draft a FOUNDRY.md and have the model refine it from the repository",
+        complete_argument: None,
+    },
+    SystemCommand {
+        name: "sessions",
+        description: "List, show or remove recorded sessions",
+        complete_argument: Some(complete_sessions_action),
+    },
+];
+
+pub fn find_system_command(name: &str) -> Option<&'static SystemCommand> {
+    SYSTEM_COMMANDS.iter().find(|command| command.name == name)
+}
+
+pub fn get_help_message() -> Vec<String> {
+    let mut lines = vec!["Available System Commands (prefix with /):".to_string()];
+    lines.extend(
+        SYSTEM_COMMANDS
+            .iter()
+            .map(|command| format!("  /{:<8} - {}", command.name, command.description)),
+    );
+    lines.extend(
+        [
+            "",
+            "💡 Tips:",
+            "  • Use arrow keys (←→) to move cursor",
+            "  • Use ↑↓ to recall previous prompts, Ctrl+R to search them",
+            "  • Press Enter to execute commands",
+            "  • Press Esc to close popups, Ctrl+C to exit",
+            "  • Use /keys to list key bindings, /vim for vi-style editing",
+            "  • Press Ctrl+G to edit long prompts in $EDITOR",
+            "  • Press Ctrl+F to search the conversation, then n/N for older/newer matches",
+            "  • Press Alt+↑ to select a message or code block, then y to copy it",
+            "  • Press e on a selected tool call to expand it, Ctrl+O for verbose output",
+            "",
+            "Type any message or use system commands above to get started!",
+        ]
+        .map(String::from),
+    );
+    lines
+}
+
+/// Runs system commands that read or change application state, returning
+/// `None` for input that `execute_command` should handle instead.
+pub fn execute_app_command(app: &mut AppState, input: &str) -> Option<String> {
+    let cmd = input.trim().strip_prefix('/')?;
+    let (name, argument) = cmd.split_once(' ').unwrap_or((cmd, ""));
+    let argument = argument.trim();
+
+    match name {
+        "theme" => Some(execute_theme_command(app, argument)),
+        "vim" => {
+            if app.vim.take().is_some() {
+                Some("Vi mode disabled.".to_string())
+            } else {
+                app.vim = Some(Vim::default());
+                Some("Vi mode enabled. Press Esc for normal mode.".to_string())
+            }
+        }
+        "copy" => Some(match last_reply(&app.output_history) {
+            Some(reply) => {
+                let lines = reply.lines().count();
+                app.pending_copy = Some(reply);
+                format!("Copying the last reply ({lines} lines) to the clipboard.")
+            }
+            None => "Nothing to copy yet.".to_string(),
+        }),
+        "diff" => Some(if app.session_changes.is_empty() {
+            "No files changed in this session.".to_string()
+        } else {
+            let view = DiffView::new(app.session_changes.clone(), DiffPurpose::Session);
+            let count = view.files.len();
+            app.diff_view = Some(view);
+            format!("Showing changes to {count} file(s).")
+        }),
+        "keys" => {
+            let mut lines = vec!["Key bindings:".to_string()];
+            lines.extend(app.keymap.describe());
+            Some(lines.join("\n"))
+        }
+        "login" => {
+            let result = report(credential_store(&app.config).and_then(|store| {
+                login(&store, LoginMethod::AzureCli, app.config.get_str("tenant"))
+                    .context("Run `foundry login` in a shell to sign in interactively")
+            }));
+            app.status_bar.connection =
+                connection_state(credential_store(&app.config).ok().as_ref());
+            Some(result)
+        }
+        "logout" => {
+            let result = report(credential_store(&app.config).and_then(|store| logout(&store)));
+            app.status_bar.connection =
+                connection_state(credential_store(&app.config).ok().as_ref());
+            Some(result)
+        }
+        "whoami" => {
+            Some(report(credential_store(&app.config).and_then(|store| {
+                whoami(&store, app.config.get_str("tenant"))
+            })))
+        }
+        "config" => Some(report(execute_config_command(app, argument))),
+        "models" => Some(models_list(&app.config)),
+        "profile" => Some(report(execute_profile_command(app, argument))),
+        "memory" => Some(report(execute_memory_command(app, argument))),
+        "init" => Some(report(init_instructions(app))),
+        "sessions" => Some(report(execute_sessions_command(app, argument))),
+        _ => None,
+    }
+}
+
+/// Shows a command's output, or its error in the console.
+fn report(result: Result<String>) -> String {
+    result.unwrap_or_else(|err| format!("⚠️ {err:#}"))
+}
+
+fn execute_config_command(app: &mut AppState, argument: &str) -> Result<String> {
+    let (action, rest) = argument.split_once(' ').unwrap_or((argument, ""));
+    let rest = rest.trim_start();
+    match (action, rest) {
+        ("" | "list", "") => {
+            let mut lines = vec![config_list(&app.config)];
+            if !app.config.issues().is_empty() {
+                lines.push("Problems:".to_string());
+                lines.extend(
+                    app.config
+                        .issues()
+                        .iter()
+                        .map(|issue| format!("  ⚠️ {issue}")),
+                );
+            }
+            Ok(lines.join("\n"))
+        }
+        ("get", key) if !key.is_empty() && !key.contains(' ') => {
+            let entry = config_entry(&app.config, key)?;
+            Ok(format_entry(&entry))
+        }
+        ("set", rest) => {
+            let (source, rest) = match rest.split_once(' ') {
+                Some(("--project", rest)) => (ConfigSource::Project, rest),
+                Some(("--local", rest)) => (ConfigSource::Local, rest),
+                _ => (ConfigSource::User, rest),
+            };
+            match rest.split_once(' ') {
+                Some((key, value)) if !value.trim().is_empty() => {
+                    let message = config_set(&mut app.config, source, key, value.trim())?;
+                    app.apply_config();
+                    Ok(message)
+                }
+                _ => bail!("Usage: /config set [--project | --local] <key> <value>"),
+            }
+        }
+        _ => bail!("Usage: /config [list | get <key> | set [--project | --local] <key> <value>]"),
+    }
+}
+
+/// Lists profiles, or switches to one. Protected profiles need `--confirm`
+/// so production is never used by accident.
+fn execute_profile_command(app: &mut AppState, argument: &str) -> Result<String> {
+    let (name, flag) = argument.split_once(' ').unwrap_or((argument, ""));
+    if name.is_empty() {
+        return Ok(profiles_list(&app.config));
+    }
+    let confirmed = match flag.trim() {
+        "" => false,
+        "--confirm" => true,
+        _ => bail!("Usage: /profile [<name> [--confirm]]"),
+    };
+    if app.config.is_protected(name) && !confirmed {
+        bail!("Profile '{name}' is protected. Enter `/profile {name} --confirm` to switch to it.");
+    }
+    app.config.use_profile(Some(name))?;
+    app.apply_config();
+    let mut lines = vec![format!("Switched to profile '{name}'.")];
+    lines.extend(profile_settings(&app.config));
+    Ok(lines.join("\n"))
+}
+
+/// Profiles defined in the config files, marking the active one.
+pub fn profiles_list(config: &LayeredConfig) -> String {
+    let profiles = config.profiles();
+    if profiles.is_empty() {
+        return "No profiles. Add a [profile.<name>] section to a config file.".to_string();
+    }
+    let mut lines = vec!["Profiles:".to_string()];
+    lines.extend(profiles.iter().map(|name| {
+        let marker = if config.active_profile() == Some(name) {
+            "*"
+        } else {
+            " "
+        };
+        let protected = if config.is_protected(name) {
+            " (protected)"
+        } else {
+            ""
+        };
+        format!("  {marker} {name}{protected}")
+    }));
+    lines.join("\n")
+}
+
+/// The effective values of the settings a profile switches.
+fn profile_settings(config: &LayeredConfig) -> Vec<String> {
+    PROFILE_KEYS
+        .iter()
+        .filter_map(|key| {
+            let (value, source) = config.get(key)?;
+            Some(format!("  {key} = {value} ({source})"))
+        })
+        .collect()
+}
+
+fn execute_memory_command(app: &mut AppState, argument: &str) -> Result<String> {
+    let (action, scope) = argument.split_once(' ').unwrap_or((argument, ""));
+    match (action, scope.trim()) {
+        ("" | "list", "") => Ok(memory_list(&app.instructions)),
+        ("show", "") => Ok(app.instructions.system_prompt()),
+        ("edit", scope) => {
+            let path = match scope {
+                "" => app
+                    .instructions
+                    .files
+                    .iter()
+                    .rev()
+                    .find(|file| file.scope == InstructionScope::Project)
+                    .map(|file| file.path.clone())
+                    .or_else(|| app.instructions.path_for(InstructionScope::Project)),
+                "project" => app.instructions.path_for(InstructionScope::Project),
+                "user" => app.instructions.path_for(InstructionScope::User),
+                _ => bail!("Usage: /memory edit [user|project]"),
+            }
+            .context("No user config directory: set $HOME or $XDG_CONFIG_HOME")?;
+            let message = format!("Opening {} in {}.", display_path(&path), editor_command());
+            app.instructions_edit_requested = Some(path);
+            Ok(message)
+        }
+        _ => bail!("Usage: /memory [list|show|edit [user|project]]"),
+    }
+}
+
+/// The loaded instruction files with what they include and any problems.
+pub fn memory_list(instructions: &Instructions) -> String {
+    if instructions.files.is_empty() {
+        return format!(
+            "No {INSTRUCTIONS_FILE_NAME} files found. Use /init to create one for this \
+             repository, or /memory edit to write one."
+        );
+    }
+    let mut lines = vec![format!(
+        "Instruction files ({} characters of system prompt):",
+        instructions.system_prompt().chars().count()
+    )];
+    for file in &instructions.files {
+        lines.push(format!(
+            "  {:<8} {} ({} lines)",
+            file.scope,
+            display_path(&file.path),
+            file.contents.lines().count()
+        ));
+        lines.extend(
+            file.includes
+                .iter()
+                .map(|path| format!("           includes {}", display_path(path))),
+        );
+        lines.extend(
+            file.problems
+                .iter()
+                .map(|problem| format!("           ⚠️ {problem}")),
+        );
+    }
+    lines.push(
+        "Use /memory show to see the system prompt, /memory edit to change a file.".to_string(),
+    );
+    lines.join("\n")
+}
+
+/// Writes a starter `FOUNDRY.md` to the project root, drafted from the
+/// repository's manifests, README and layout, then asks the model to
+/// analyse the repository and refine it. Never overwrites one.
+pub fn init_instructions(app: &mut AppState) -> Result<String> {
+    if app.agent.is_running() {
+        bail!("A turn is still running; press Esc to interrupt it");
+    }
+    let path = app
+        .instructions
+        .path_for(InstructionScope::Project)
+        .context("No project directory")?;
+    let starter = starter_instructions(app.instructions.root());
+    let mut file = match fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&path)
+    {
+        Ok(file) => file,
+        Err(err) if err.kind() == io::ErrorKind::AlreadyExists => bail!(
+            "{} already exists. Use /memory edit to change it.",
+            display_path(&path)
+        ),
+        Err(err) => {
+            return Err(err).with_context(|| format!("Failed to create {}", path.display()));
+        }
+    };
+    file.write_all(starter.as_bytes())
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    app.instructions.reload();
+    app.pending_turn = Some(format!(
+        "Analyse this repository and improve the {INSTRUCTIONS_FILE_NAME} drafted at its root. \
+         Read the README, the manifests and enough of the source to learn how the project \
+         is built and tested, how it is laid out and which conventions its code follows. \
+         Then rewrite the file as concise guidance for working in this repository, keeping \
+         any @include lines."
+    ));
+    Ok(format!(
+        "Created {} ({} lines). Asking the model to refine it; it is sent with every prompt.",
+        display_path(&path),
+        starter.lines().count()
+    ))
+}
+
+fn complete_memory_action(partial: &str) -> Vec<Completion> {
+    complete_from(
+        partial,
+        [
+            ("list", "List the loaded instruction files"),
+            ("show", "Print the assembled system prompt"),
+            ("edit", "Open an instruction file in $EDITOR"),
+        ],
+    )
+}
+
+fn complete_profile_name(partial: &str) -> Vec<Completion> {
+    let config = std::env::current_dir()
+        .ok()
+        .and_then(|cwd| LayeredConfig::load(&cwd, ConfigFile::default()).ok());
+    let Some(config) = config else {
+        return Vec::new();
+    };
+    config
+        .profiles()
+        .into_iter()
+        .filter(|name| name.starts_with(partial))
+        .map(|name| {
+            let description = if config.is_protected(&name) {
+                "Protected profile"
+            } else {
+                "Profile"
+            };
+            Completion::new(name, description)
+        })
+        .collect()
+}
+
+fn execute_sessions_command(app: &AppState, argument: &str) -> Result<String> {
+    let Some(store) = SessionStore::for_project(&app.workspace_root) else {
+        bail!("Failed to locate the state directory: no home directory");
+    };
+    match argument.split_once(' ') {
+        None if argument.is_empty() || argument == "list" => sessions_list(&store),
+        Some(("show", id)) => sessions_show(&store, id.trim()),
+        Some(("rm", id)) => sessions_remove(&store, id.trim()),
+        _ => bail!("Usage: /sessions [list | show <id> | rm <id>]"),
+    }
+}
+
+fn complete_config_action(partial: &str) -> Vec<Completion> {
+    complete_from(
+        partial,
+        [
+            ("list", "Show every setting"),
+            ("get", "Show one setting"),
+            ("set", "Change a setting"),
+        ],
+    )
+}
+
+fn complete_sessions_action(partial: &str) -> Vec<Completion> {
+    complete_from(
+        partial,
+        [
+            ("list", "List recorded sessions"),
+            ("show", "Print a session transcript"),
+            ("rm", "Delete a session"),
+        ],
+    )
+}
+
+// Account, config, model and session commands. These back both the slash
+// commands above and the matching `foundry` subcommands.
+
+/// The active profile's credentials, which fall back to the shared ones.
+pub fn credential_store(config: &LayeredConfig) -> Result<CredentialStore> {
+    CredentialStore::for_profile(config.active_profile())
+        .context("Failed to locate the state directory: no home directory")
+}
+
+/// Signed in when an API key is set in the environment or an unexpired
+/// credential is saved. The Azure CLI is not asked, since that is slow.
+pub fn connection_state(store: Option<&CredentialStore>) -> ConnectionState {
+    let api_key = std::env::var(API_KEY_ENV).is_ok_and(|key| !key.trim().is_empty());
+    let saved = store
+        .and_then(|store| store.load().ok().flatten())
+        .is_some_and(|credential| !credential.is_expired());
+    if api_key || saved {
+        ConnectionState::Connected
+    } else {
+        ConnectionState::SignedOut
+    }
+}
+
+/// Signs in with `method` and saves the credential. Azure CLI sign-ins
+/// save only that the CLI should be asked for tokens, not a token.
+pub fn login(store: &CredentialStore, method: LoginMethod, tenant: Option<&str>) -> Result<String> {
+    let (credential, shown) = match method {
+        LoginMethod::ApiKey(key) => {
+            let key = key.trim();
+            if key.is_empty() {
+                bail!("The API key is empty");
+            }
+            let credential = Credential::ApiKey {
+                key: key.to_string(),
+            };
+            (credential.clone(), credential)
+        }
+        LoginMethod::Device | LoginMethod::Browser | LoginMethod::AzureCli => {
+            if method != LoginMethod::AzureCli {
+                azure_cli::login(method == LoginMethod::Device, tenant)?;
+            }
+            let credential = Credential::AzureCli {
+                tenant: tenant.map(str::to_string),
+            };
+            (credential, azure_cli::access_token(tenant)?)
+        }
+    };
+    store.save(&credential)?;
+    let mut lines = vec!["Signed in.".to_string()];
+    lines.extend(describe_credential(&shown));
+    Ok(lines.join("\n"))
+}
+
+pub fn logout(store: &CredentialStore) -> Result<String> {
+    Ok(if store.clear()? {
+        "Signed out.".to_string()
+    } else {
+        "Not signed in.".to_string()
+    })
+}
+
+/// The saved credential and, for tokens, the account it belongs to. Azure
+/// CLI tokens are for `tenant`, or else the tenant saved at sign-in.
+pub fn whoami(store: &CredentialStore, tenant: Option<&str>) -> Result<String> {
+    match store.load()? {
+        Some(Credential::AzureCli { tenant: saved }) => {
+            let token = azure_cli::access_token(tenant.or(saved.as_deref()))?;
+            let mut lines = vec!["Signed in through the Azure CLI".to_string()];
+            lines.extend(describe_credential(&token));
+            Ok(lines.join("\n"))
+        }
+        Some(credential) => Ok(describe_credential(&credential).join("\n")),
+        None => Ok("Not signed in. Run `foundry login` to sign in.".to_string()),
+    }
+}
+
+fn describe_credential(credential: &Credential) -> Vec<String> {
+    match credential {
+        Credential::ApiKey { key } => {
+            vec![format!("Using API key {}", Credential::masked_key(key))]
+        }
+        Credential::AzureCli { tenant } => {
+            let mut lines = vec!["Using the Azure CLI sign-in".to_string()];
+            lines.extend(
+                tenant
+                    .iter()
+                    .map(|tenant| format!("  {:<10} {tenant}", "Tenant")),
+            );
+            lines
+        }
+        Credential::AccessToken { token, expires_on } => {
+            let mut lines = vec!["Using an Entra ID access token".to_string()];
+            let claims = match decode_claims(token) {
+                Ok(claims) => claims,
+                Err(err) => {
+                    lines.push(format!("  Claims:    unreadable ({err:#})"));
+                    return lines;
+                }
+            };
+            let fields = [
+                ("Account", claims.account()),
+                ("Name", claims.name.as_deref()),
+                ("Tenant", claims.tid.as_deref()),
+                ("Object ID", claims.oid.as_deref()),
+                ("Audience", claims.aud.as_deref()),
+            ];
+            lines.extend(
+                fields
+                    .into_iter()
+                    .filter_map(|(label, value)| Some(format!("  {label:<10} {}", value?))),
+            );
+            if let Some(expires_on) = expires_on.or(claims.exp) {
+                lines.push(format!(
+                    "  {:<10} {}",
+                    "Expires",
+                    describe_expiry(expires_on)
+                ));
+            }
+            lines
+        }
+    }
+}
+
+fn describe_expiry(expires_on: u64) -> String {
+    let now = unix_now();
+    if expires_on > now {
+        format!(
+            "in {}",
+            format_elapsed(Duration::from_secs(expires_on - now))
+        )
+    } else {
+        format!(
+            "{} ago (expired, sign in again)",
+            format_elapsed(Duration::from_secs(now - expires_on))
+        )
+    }
+}
+
+/// The effective value of `key` as TOML.
+pub fn config_get(config: &LayeredConfig, key: &str) -> Result<String> {
+    config_entry(config, key).map(|entry| entry.value)
+}
+
+fn config_entry(config: &LayeredConfig, key: &str) -> Result<ConfigEntry> {
+    match config.entries().into_iter().find(|entry| entry.key == key) {
+        Some(entry) => Ok(entry),
+        None => bail!("'{key}' is not set"),
+    }
+}
+
+/// Writes `key` to the config file of `source`, noting when a higher layer
+/// still overrides it.
+pub fn config_set(
+    config: &mut LayeredConfig,
+    source: ConfigSource,
+    key: &str,
+    value: &str,
+) -> Result<String> {
+    let file = config.set(source, key, value)?;
+    let mut message = format!(
+        "Set {key} = {} in {}",
+        file.get(key).map(ToString::to_string).unwrap_or_default(),
+        file.path.display()
+    );
+    let entry = config_entry(config, key)?;
+    if entry.source != source {
+        message.push_str(&format!(
+            "\nStill overridden by {}",
+            describe_source(&entry)
+        ));
+    }
+    Ok(message)
+}
+
+/// Every effective setting with where its value came from.
+pub fn config_list(config: &LayeredConfig) -> String {
+    let entries = config.entries();
+    if entries.is_empty() {
+        return "No settings.".to_string();
+    }
+    let width = entries
+        .iter()
+        .map(|entry| entry.key.len() + entry.value.len())
+        .max()
+        .unwrap_or_default();
+    let mut lines = vec!["Settings (highest precedence wins):".to_string()];
+    lines.extend(entries.iter().map(|entry| {
+        let setting = format!("{} = {}", entry.key, entry.value);
+        format!("  {setting:<0$}  {1}", width + 3, describe_source(entry))
+    }));
+    lines.join("\n")
+}
+
+fn format_entry(entry: &ConfigEntry) -> String {
+    format!(
+        "{} = {}  {}",
+        entry.key,
+        entry.value,
+        describe_source(entry)
+    )
+}
+
+/// `(project: /repo/.foundry/config.toml)`, `(env: FOUNDRY_THEME)` or `(default)`.
+fn describe_source(entry: &ConfigEntry) -> String {
+    match &entry.origin {
+        Some(origin) => format!("({}: {origin})", entry.source),
+        None => format!("({})", entry.source),
+    }
+}
+
+/// Model deployment names from the `deployment` and `deployments` settings.
+pub fn deployment_names(config: &LayeredConfig) -> Vec<String> {
+    let active = config.get_str("deployment");
+    let listed = config
+        .get("deployments")
+        .and_then(|(value, _)| value.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|value| value.as_str());
+    let mut names = Vec::<String>::new();
+    for name in active.into_iter().chain(listed) {
+        if !names.iter().any(|existing| existing == name) {
+            names.push(name.to_string());
+        }
+    }
+    names
+}
+
+pub fn models_list(config: &LayeredConfig) -> String {
+    let names = deployment_names(config);
+    if names.is_empty() {
+        return "No model deployments configured. Add one with `foundry config set deployment <name>`."
+            .to_string();
+    }
+    let active = config.get_str("deployment");
+    let mut lines = vec!["Model deployments:".to_string()];
+    lines.extend(names.iter().map(|name| {
+        let marker = if Some(name.as_str()) == active {
+            "*"
+        } else {
+            " "
+        };
+        format!("  {marker} {name}")
+    }));
+    lines.join("\n")
+}
+
+pub fn sessions_list(store: &SessionStore) -> Result<String> {
+    let list = store.list()?;
+    let warnings = list
+        .skipped
+        .iter()
+        .map(|problem| format!("warning: skipped {problem}"));
+    if list.sessions.is_empty() {
+        let mut lines = vec!["No recorded sessions in this project.".to_string()];
+        lines.extend(warnings);
+        return Ok(lines.join("\n"));
+    }
+    let mut lines = vec!["Sessions (newest first):".to_string()];
+    lines.extend(list.sessions.iter().map(|session| {
+        let age = SystemTime::now()
+            .duration_since(session.modified)
+            .unwrap_or_default();
+        format!(
+            "  {}  {:>7} ago  {} turn(s)  {}",
+            session.id,
+            format_elapsed(age),
+            session.turns,
+            session
+                .title
+                .as_deref()
+                .and_then(|title| title.lines().next())
+                .unwrap_or_default()
+        )
+    }));
+    lines.extend(warnings);
+    Ok(lines.join("\n"))
+}
+
+/// The transcript of the session whose id starts with `id`.
+pub fn sessions_show(store: &SessionStore, id: &str) -> Result<String> {
+    let id = store.resolve(id)?;
+    let mut lines = vec![format!("Session {id}")];
+    for event in store.load(&id)? {
+        match event {
+            Event::UserMessage { text } => lines.push(format!("\n> {text}")),
+            Event::MessageDelta { text } => lines.push(text),
+            Event::ToolUse { name, input, .. } => lines.push(format!("⏺ {name}({input})")),
+            Event::Error { message } => lines.push(format!("⚠️ {message}")),
+            _ => {}
+        }
+    }
+    Ok(lines.join("\n"))
+}
+
+pub fn sessions_remove(store: &SessionStore, id: &str) -> Result<String> {
+    let id = store.resolve(id)?;
+    store.remove(&id)?;
+    Ok(format!("Removed session {id}."))
+}
+
+fn execute_theme_command(app: &mut AppState, name: &str) -> String {
+    if name.is_empty() {
+        let mut lines = vec![format!("Current theme: {}", app.theme.name)];
+        lines.extend(
+            available_themes()
+                .into_iter()
+                .map(|(name, description)| format!("  {name:<14} - {description}")),
+        );
+        return lines.join("\n");
+    }
+
+    match load_theme(name) {
+        Ok(theme) => {
+            app.theme = theme;
+            format!("Switched to theme: {name}")
+        }
+        Err(err) => format!("{err:#}"),
+    }
+}
+
+fn complete_theme_name(partial: &str) -> Vec<Completion> {
+    available_themes()
+        .into_iter()
+        .filter(|(name, _)| name.starts_with(partial))
+        .map(|(name, description)| Completion::new(name, description))
+        .collect()
 }
 
 pub fn execute_command(input: &str) -> String {
@@ -37,8 +843,6 @@ fn execute_system_command(command: &str) -> String {
         "help" => get_help_message().join("\n"),
         "clear" => "Screen cleared (simulated)".to_string(),
         "exit" => "Goodbye!".to_string(),
-        "login" => "Login functionality not yet implemented.".to_string(),
-        "logout" => "Logout functionality not yet implemented.".to_string(),
         _ => {
             format!("Unknown system command: /{cmd}\nType /help for available commands.")
         }
@@ -58,6 +862,7 @@ fn handle_user_input(input: &str) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use foundry_core::testing::temp_dir;
 
     #[test]
     fn test_execute_system_help_command() {
@@ -66,8 +871,9 @@ mod tests {
         assert!(result.contains("Available System Commands"));
         assert!(result.contains("/help     - Show this help message"));
         assert!(result.contains("/exit     - Exit the application"));
-        assert!(result.contains("/login    - Login to system"));
-        assert!(result.contains("/logout   - Logout from system"));
+        assert!(result.contains("/login    - Sign in with your Azure CLI account"));
+        assert!(result.contains("/logout   - Sign out and forget saved credentials"));
+        assert!(result.contains("/sessions - List, show or remove recorded sessions"));
     }
 
     #[test]
@@ -85,17 +891,212 @@ mod tests {
     }
 
     #[test]
-    fn test_execute_system_login_command() {
-        // Test /login command returns not implemented message
-        let result = execute_command("/login");
-        assert_eq!(result, "Login functionality not yet implemented.");
+    fn test_api_key_login_whoami_and_logout() {
+        // Test an API key is saved, shown masked, signs the status bar in and
+        // is forgotten on logout
+        let dir = temp_dir();
+        let store = CredentialStore::new(dir.path().join("credentials.json"));
+        assert!(whoami(&store, None).unwrap().starts_with("Not signed in"));
+        assert!(login(&store, LoginMethod::ApiKey("  ".to_string()), None).is_err());
+
+        let result = login(&store, LoginMethod::ApiKey("secret-3f9a".to_string()), None).unwrap();
+        assert_eq!(result, "Signed in.\nUsing API key ••••3f9a");
+        assert_eq!(whoami(&store, None).unwrap(), "Using API key ••••3f9a");
+        assert_eq!(connection_state(Some(&store)), ConnectionState::Connected);
+        assert_eq!(logout(&store).unwrap(), "Signed out.");
+        if std::env::var(API_KEY_ENV).is_err() {
+            assert_eq!(connection_state(Some(&store)), ConnectionState::SignedOut);
+        }
+        assert_eq!(logout(&store).unwrap(), "Not signed in.");
     }
 
     #[test]
-    fn test_execute_system_logout_command() {
-        // Test /logout command returns not implemented message
-        let result = execute_command("/logout");
-        assert_eq!(result, "Logout functionality not yet implemented.");
+    fn test_whoami_describes_token_claims() {
+        // Test token claims and expiry are listed for access tokens
+        // {"upn":"ada@contoso.com","tid":"tenant-1"}
+        let payload = "eyJ1cG4iOiJhZGFAY29udG9zby5jb20iLCJ0aWQiOiJ0ZW5hbnQtMSJ9";
+        let credential = Credential::AccessToken {
+            token: format!("header.{payload}.signature"),
+            expires_on: Some(unix_now() - 120),
+        };
+        let lines = describe_credential(&credential);
+        assert!(lines.contains(&"  Account    ada@contoso.com".to_string()));
+        assert!(lines.contains(&"  Tenant     tenant-1".to_string()));
+        assert!(lines.last().unwrap().contains("expired"));
+    }
+
+    #[test]
+    fn test_profile_command_guards_protected_profiles() {
+        // Test /profile lists profiles and needs --confirm for protected ones
+        let dir = temp_dir();
+        let root = dir.path();
+        std::fs::write(
+            root.join("user.toml"),
+            "[profile.dev]\ndeployment = \"gpt-4.1\"\n\n\
+             [profile.prod]\ndeployment = \"o3\"\nprotected = true\n",
+        )
+        .unwrap();
+        let config = LayeredConfig::load_from(
+            root.join("user.toml"),
+            root,
+            |_| None,
+            ConfigFile::default(),
+        )
+        .unwrap();
+        let mut app = AppState {
+            config,
+            ..AppState::default()
+        };
+
+        let result = execute_app_command(&mut app, "/profile").unwrap();
+        assert_eq!(result, "Profiles:\n    dev\n    prod (protected)");
+        let result = execute_app_command(&mut app, "/profile dev").unwrap();
+        assert!(result.contains("deployment = \"gpt-4.1\" (profile)"));
+        assert_eq!(app.status_bar.profile.as_deref(), Some("dev"));
+        assert_eq!(app.status_bar.model.as_deref(), Some("gpt-4.1"));
+
+        let result = execute_app_command(&mut app, "/profile prod").unwrap();
+        assert!(result.starts_with("⚠️ Profile 'prod' is protected"));
+        assert_eq!(app.status_bar.profile.as_deref(), Some("dev"));
+        execute_app_command(&mut app, "/profile prod --confirm").unwrap();
+        assert_eq!(app.status_bar.model.as_deref(), Some("o3"));
+        assert!(app.status_bar.profile_protected);
+        let result = execute_app_command(&mut app, "/profile qa").unwrap();
+        assert_eq!(result, "⚠️ Unknown profile 'qa'");
+    }
+
+    #[test]
+    fn test_memory_and_init_commands() {
+        // Test /init drafts a FOUNDRY.md once for the model to refine and /memory
+        // lists, shows and edits it
+        let dir = temp_dir();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::write(root.join("Cargo.toml"), "[package]").unwrap();
+        let mut app = AppState {
+            instructions: Instructions::load_from(
+                Some(root.join("config")),
+                root.to_path_buf(),
+                root.join("src"),
+            ),
+            ..AppState::default()
+        };
+
+        let result = execute_app_command(&mut app, "/memory").unwrap();
+        assert!(result.starts_with("No FOUNDRY.md files found."));
+        let result = execute_app_command(&mut app, "/init").unwrap();
+        assert!(result.starts_with("Created "));
+        assert!(
+            app.pending_turn
+                .take()
+                .unwrap()
+                .starts_with("Analyse this repository")
+        );
+        assert!(
+            std::fs::read_to_string(root.join("FOUNDRY.md"))
+                .unwrap()
+                .contains("`cargo test --workspace`")
+        );
+        let result = execute_app_command(&mut app, "/init").unwrap();
+        assert!(result.ends_with("already exists. Use /memory edit to change it."));
+        assert!(app.pending_turn.is_none());
+
+        let result = execute_app_command(&mut app, "/memory").unwrap();
+        assert!(result.contains("  project  "));
+        let result = execute_app_command(&mut app, "/memory show").unwrap();
+        assert!(result.contains("## Commands"));
+
+        execute_app_command(&mut app, "/memory edit").unwrap();
+        assert_eq!(
+            app.instructions_edit_requested.take(),
+            Some(root.join("FOUNDRY.md"))
+        );
+        execute_app_command(&mut app, "/memory edit user").unwrap();
+        assert_eq!(
+            app.instructions_edit_requested.take(),
+            Some(root.join("config").join("FOUNDRY.md"))
+        );
+        let result = execute_app_command(&mut app, "/memory edit team").unwrap();
+        assert_eq!(result, "⚠️ Usage: /memory edit [user|project]");
+    }
+
+    #[test]
+    fn test_config_and_models_commands() {
+        // Test settings are listed with their source, set per layer, and deployments are listed
+        let dir = temp_dir();
+        let root = dir.path();
+        let mut cli = ConfigFile::default();
+        cli.set("theme", "light").unwrap();
+        let mut config =
+            LayeredConfig::load_from(root.join("user.toml"), root, |_| None, cli).unwrap();
+        assert!(config_get(&config, "deployment").is_err());
+        let listing = config_list(&config);
+        let theme = listing
+            .lines()
+            .find(|line| line.contains("theme = \"light\""));
+        assert!(theme.unwrap().ends_with("(cli)"));
+
+        config_set(&mut config, ConfigSource::Project, "deployment", "gpt-4.1").unwrap();
+        config_set(
+            &mut config,
+            ConfigSource::User,
+            "deployments",
+            r#"["gpt-4.1", "o3"]"#,
+        )
+        .unwrap();
+        let message = config_set(&mut config, ConfigSource::User, "theme", "dark").unwrap();
+        assert!(message.ends_with("Still overridden by (cli)"));
+        assert_eq!(config_get(&config, "deployment").unwrap(), "\"gpt-4.1\"");
+        let listing = config_list(&config);
+        let deployment = listing.lines().find(|line| line.contains("deployment = "));
+        assert!(deployment.unwrap().ends_with(".foundry/config.toml)"));
+        assert_eq!(deployment_names(&config), vec!["gpt-4.1", "o3"]);
+        assert_eq!(
+            models_list(&config),
+            "Model deployments:\n  * gpt-4.1\n    o3"
+        );
+    }
+
+    #[test]
+    fn test_sessions_commands() {
+        // Test recorded sessions are listed, shown by id prefix and removed
+        let dir = temp_dir();
+        let store = SessionStore::new(dir.path().to_path_buf());
+        assert_eq!(
+            sessions_list(&store).unwrap(),
+            "No recorded sessions in this project."
+        );
+
+        let events = [
+            Event::UserMessage {
+                text: "review this".to_string(),
+            },
+            Event::MessageDelta {
+                text: "Looks good".to_string(),
+            },
+        ];
+        for event in &events {
+            store.append("feed-1", event).unwrap();
+        }
+        assert!(sessions_list(&store).unwrap().contains("feed-1"));
+        assert_eq!(
+            sessions_show(&store, "feed").unwrap(),
+            "Session feed-1\n\n> review this\nLooks good"
+        );
+        assert_eq!(
+            sessions_remove(&store, "feed").unwrap(),
+            "Removed session feed-1."
+        );
+    }
+
+    #[test]
+    fn test_account_command_usage_errors() {
+        // Test malformed /config and /sessions arguments show their usage
+        let mut app = AppState::default();
+        let result = execute_app_command(&mut app, "/config get").unwrap();
+        assert!(result.contains("Usage: /config"));
+        let result = execute_app_command(&mut app, "/sessions delete x").unwrap();
+        assert!(result.contains("Usage: /sessions"));
     }
 
     #[test]
@@ -148,4 +1149,71 @@ mod tests {
         let result = execute_command("/");
         assert!(result.contains("Unknown system command"));
     }
+
+    #[test]
+    fn test_execute_theme_command() {
+        // Test /theme lists themes, switches by name and rejects unknown names
+        let mut app = AppState::default();
+
+        let result = execute_app_command(&mut app, "/theme").unwrap();
+        assert!(result.contains("Current theme: dark"));
+        assert!(result.contains("high-contrast"));
+
+        let result = execute_app_command(&mut app, "/theme light").unwrap();
+        assert_eq!(result, "Switched to theme: light");
+        assert_eq!(app.theme.name, "light");
+
+        let result = execute_app_command(&mut app, "/theme sepia").unwrap();
+        assert!(result.contains("Unknown theme: sepia"));
+        assert_eq!(app.theme.name, "light");
+
+        assert!(
+            execute_app_command(&mut app, "/keys")
+                .unwrap()
+                .contains("reverse-search")
+        );
+        assert!(
+            execute_app_command(&mut app, "/vim")
+                .unwrap()
+                .contains("enabled")
+        );
+        assert!(app.vim.is_some());
+        assert!(
+            execute_app_command(&mut app, "/vim")
+                .unwrap()
+                .contains("disabled")
+        );
+        assert!(app.vim.is_none());
+        assert!(execute_app_command(&mut app, "/help").is_none());
+        assert!(execute_app_command(&mut app, "hello").is_none());
+    }
+
+    #[test]
+    fn test_execute_copy_command() {
+        // Test /copy queues the last reply for the clipboard
+        let mut app = AppState::default();
+        let result = execute_app_command(&mut app, "/copy").unwrap();
+        assert_eq!(result, "Nothing to copy yet.");
+        assert!(app.pending_copy.is_none());
+
+        app.add_output("> hello".to_string());
+        app.add_output("You said: hello".to_string());
+        let result = execute_app_command(&mut app, "/copy").unwrap();
+        assert!(result.contains("1 lines"));
+        assert_eq!(app.pending_copy.as_deref(), Some("You said: hello"));
+    }
+
+    #[test]
+    fn test_execute_diff_command() {
+        // Test /diff opens the session changes in the diff viewer
+        let mut app = AppState::default();
+        let result = execute_app_command(&mut app, "/diff").unwrap();
+        assert_eq!(result, "No files changed in this session.");
+        assert!(app.diff_view.is_none());
+
+        app.record_change("a.txt".into(), "a\n", "b\n".to_string());
+        let result = execute_app_command(&mut app, "/diff").unwrap();
+        assert_eq!(result, "Showing changes to 1 file(s).");
+        assert_eq!(app.diff_view.unwrap().purpose, DiffPurpose::Session);
+    }
 }
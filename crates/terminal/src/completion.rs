@@ -0,0 +1,289 @@
+use crate::commands::{SYSTEM_COMMANDS, find_system_command};
+
+/// Maximum number of suggestions shown in the popup at once.
+pub const MAX_VISIBLE_COMPLETIONS: usize = 8;
+/// Maximum number of fuzzy file matches kept for an `@` mention.
+pub const MAX_FILE_COMPLETIONS: usize = 50;
+
+/// A single suggestion: the text that replaces the token being completed and
+/// a short description shown alongside it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Completion {
+    pub value: String,
+    pub description: String,
+}
+
+impl Completion {
+    pub fn new(value: impl Into<String>, description: impl Into<String>) -> Self {
+        Self {
+            value: value.into(),
+            description: description.into(),
+        }
+    }
+}
+
+/// What the popup is completing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompletionKind {
+    Command,
+    Argument,
+    File,
+}
+
+/// Suggestions for the current input and which one is highlighted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompletionPopup {
+    pub kind: CompletionKind,
+    pub items: Vec<Completion>,
+    pub selected: usize,
+    /// Input text preceding the token being completed.
+    prefix: String,
+    /// Input text following the token, kept as-is when a suggestion is applied.
+    suffix: String,
+}
+
+impl CompletionPopup {
+    /// Builds the popup for `input` with the cursor at character `cursor`, or
+    /// `None` when there is nothing to suggest. Slash commands are completed
+    /// when the whole input is a command; otherwise an `@` mention under the
+    /// cursor is fuzzy-matched against `workspace_files`.
+    pub fn for_input(input: &str, cursor: usize, workspace_files: &[String]) -> Option<Self> {
+        let split = input
+            .char_indices()
+            .nth(cursor)
+            .map_or(input.len(), |(index, _)| index);
+        let (before, after) = input.split_at(split);
+
+        let (kind, prefix, suffix, items) = match before.strip_prefix('/') {
+            Some(rest) if after.is_empty() => match rest.split_once(' ') {
+                None => (
+                    CompletionKind::Command,
+                    "/".to_string(),
+                    "",
+                    complete_command_name(rest),
+                ),
+                Some((name, argument)) => {
+                    let complete = find_system_command(name)?.complete_argument?;
+                    (
+                        CompletionKind::Argument,
+                        format!("/{name} "),
+                        "",
+                        complete(argument.trim_start()),
+                    )
+                }
+            },
+            _ => {
+                let token_start = before
+                    .rfind(char::is_whitespace)
+                    .map_or(0, |index| index + 1);
+                let query = before[token_start..].strip_prefix('@')?;
+                // The rest of the token is replaced along with the part
+                // before the cursor.
+                let token_end = after.find(char::is_whitespace).unwrap_or(after.len());
+                (
+                    CompletionKind::File,
+                    before[..=token_start].to_string(),
+                    &after[token_end..],
+                    complete_file_path(query, workspace_files),
+                )
+            }
+        };
+
+        let popup = Self {
+            kind,
+            items,
+            selected: 0,
+            prefix,
+            suffix: suffix.to_string(),
+        };
+
+        if popup.items.is_empty() || (popup.items.len() == 1 && popup.apply().0 == input) {
+            return None;
+        }
+        Some(popup)
+    }
+
+    pub fn select_next(&mut self) {
+        self.selected = (self.selected + 1) % self.items.len();
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.checked_sub(1).unwrap_or(self.items.len() - 1);
+    }
+
+    /// Returns the input with the highlighted suggestion applied and the
+    /// character position the cursor should move to. Commands that take an
+    /// argument get a trailing space so argument completion can start
+    /// straight away.
+    pub fn apply(&self) -> (String, usize) {
+        let value = &self.items[self.selected].value;
+        let takes_argument = self.kind == CompletionKind::Command
+            && find_system_command(value)
+                .is_some_and(|command| command.complete_argument.is_some());
+
+        let completed = if takes_argument {
+            format!("{}{value} ", self.prefix)
+        } else {
+            format!("{}{value}", self.prefix)
+        };
+        let cursor = completed.chars().count();
+        (format!("{completed}{}", self.suffix), cursor)
+    }
+
+    /// Index of the first item to draw so the selection stays visible.
+    pub fn scroll_offset(&self) -> usize {
+        (self.selected + 1).saturating_sub(MAX_VISIBLE_COMPLETIONS)
+    }
+}
+
+fn complete_command_name(partial: &str) -> Vec<Completion> {
+    SYSTEM_COMMANDS
+        .iter()
+        .filter(|command| command.name.starts_with(partial))
+        .map(|command| Completion::new(command.name, command.description))
+        .collect()
+}
+
+fn complete_file_path(query: &str, workspace_files: &[String]) -> Vec<Completion> {
+    let mut matches = workspace_files
+        .iter()
+        .filter_map(|path| Some((fuzzy_score(path, query)?, path)))
+        .collect::<Vec<_>>();
+    matches.sort_by(|(a_score, a_path), (b_score, b_path)| {
+        b_score
+            .cmp(a_score)
+            .then_with(|| a_path.len().cmp(&b_path.len()))
+    });
+
+    matches
+        .into_iter()
+        .take(MAX_FILE_COMPLETIONS)
+        .map(|(_, path)| Completion::new(path.as_str(), ""))
+        .collect()
+}
+
+/// Scores `candidate` when the characters of `query` appear in it in order,
+/// ignoring case. Runs of adjacent characters and characters that start a
+/// path segment or word score higher; skipped characters cost a little.
+fn fuzzy_score(candidate: &str, query: &str) -> Option<i64> {
+    let candidate = candidate.chars().collect::<Vec<_>>();
+    let mut score = 0;
+    let mut next = 0;
+    let mut previous: Option<usize> = None;
+    for wanted in query.chars() {
+        let offset = candidate[next..]
+            .iter()
+            .position(|c| c.to_lowercase().eq(wanted.to_lowercase()))?;
+        let index = next + offset;
+        score += 1;
+        if index == 0 || matches!(candidate[index - 1], '/' | '_' | '-' | '.' | ' ') {
+            score += 3;
+        }
+        match previous {
+            Some(previous) if previous + 1 == index => score += 5,
+            Some(_) => score -= offset as i64,
+            None => {}
+        }
+        previous = Some(index);
+        next = index + 1;
+    }
+    Some(score)
+}
+
+/// Filters `candidates` by prefix, for use by argument completers.
+pub fn complete_from<'a>(
+    partial: &str,
+    candidates: impl IntoIterator<Item = (&'a str, &'a str)>,
+) -> Vec<Completion> {
+    candidates
+        .into_iter()
+        .filter(|(value, _)| value.starts_with(partial))
+        .map(|(value, description)| Completion::new(value, description))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn popup_for(input: &str) -> Option<CompletionPopup> {
+        CompletionPopup::for_input(input, input.chars().count(), &[])
+    }
+
+    #[test]
+    fn test_popup_lists_matching_commands() {
+        // Test command names are matched by prefix with descriptions
+        let popup = popup_for("/lo").unwrap();
+        let names = popup
+            .items
+            .iter()
+            .map(|item| item.value.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["login", "logout"]);
+        assert_eq!(
+            popup.items[0].description,
+            "Sign in with your Azure CLI account"
+        );
+        assert_eq!(popup.apply().0, "/login");
+
+        assert_eq!(popup_for("/").unwrap().items.len(), SYSTEM_COMMANDS.len());
+    }
+
+    #[test]
+    fn test_popup_hidden_without_suggestions() {
+        // Test plain text, unknown commands and exact matches show no popup
+        assert!(popup_for("hello").is_none());
+        assert!(popup_for("/zzz").is_none());
+        assert!(popup_for("/help").is_none());
+        assert!(popup_for("/help topic").is_none());
+    }
+
+    #[test]
+    fn test_popup_selection_wraps() {
+        // Test navigation wraps in both directions
+        let mut popup = popup_for("/lo").unwrap();
+        popup.select_previous();
+        assert_eq!(popup.apply().0, "/logout");
+        popup.select_next();
+        assert_eq!(popup.apply().0, "/login");
+    }
+
+    #[test]
+    fn test_complete_from_filters_by_prefix() {
+        // Test the argument completion helper keeps matching candidates
+        let items = complete_from("d", [("dark", "Dark theme"), ("light", "Light theme")]);
+        assert_eq!(items, [Completion::new("dark", "Dark theme")]);
+    }
+
+    #[test]
+    fn test_popup_fuzzy_matches_mentions() {
+        // Test @ mentions fuzzy-match files and replace only the token
+        let files = ["src/bin/foundry.rs", "src/lib.rs", "README.md"].map(String::from);
+        let input = "explain @src/bin/fo please";
+        let popup = CompletionPopup::for_input(input, 19, &files).unwrap();
+
+        assert_eq!(popup.items[0].value, "src/bin/foundry.rs");
+        assert_eq!(
+            popup.apply(),
+            ("explain @src/bin/foundry.rs please".to_string(), 27)
+        );
+
+        let popup = CompletionPopup::for_input("see @src/lixyz now", 11, &files).unwrap();
+        assert_eq!(popup.items[0].value, "src/lib.rs");
+        assert_eq!(popup.apply(), ("see @src/lib.rs now".to_string(), 15));
+
+        assert!(CompletionPopup::for_input("@zzz", 4, &files).is_none());
+        assert!(CompletionPopup::for_input("@README.md", 10, &files).is_none());
+        assert!(CompletionPopup::for_input("mail@x", 6, &files).is_none());
+    }
+
+    #[test]
+    fn test_fuzzy_score_prefers_adjacent_and_segment_matches() {
+        // Test matches are in order, case-insensitive and ranked sensibly
+        assert_eq!(fuzzy_score("src/lib.rs", "xyz"), None);
+        assert_eq!(fuzzy_score("src/lib.rs", "bil"), None);
+        assert!(fuzzy_score("README.md", "readme").is_some());
+        assert!(fuzzy_score("src/lib.rs", "lib") > fuzzy_score("src/ledit.bs", "lib"));
+        assert!(fuzzy_score("src/app.rs", "app") > fuzzy_score("src/wrapper.rs", "app"));
+    }
+}
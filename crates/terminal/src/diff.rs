@@ -0,0 +1,319 @@
+use similar::{ChangeTag, TextDiff};
+use std::ops::Range;
+use std::path::PathBuf;
+
+/// Unchanged lines shown around each change.
+pub const DIFF_CONTEXT_LINES: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffLineKind {
+    Context,
+    Added,
+    Removed,
+}
+
+/// A line of a hunk. The text is split into segments so words changed
+/// within the line (marked `true`) can be highlighted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffLine {
+    pub kind: DiffLineKind,
+    /// 1-based line numbers in the old and new file.
+    pub old_line: Option<usize>,
+    pub new_line: Option<usize>,
+    pub segments: Vec<(bool, String)>,
+}
+
+impl DiffLine {
+    pub fn text(&self) -> String {
+        self.segments
+            .iter()
+            .map(|(_, text)| text.as_str())
+            .collect()
+    }
+}
+
+/// A group of nearby changes with their surrounding context.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hunk {
+    /// 0-based line ranges covered in the old and new file.
+    pub old_range: Range<usize>,
+    pub new_range: Range<usize>,
+    pub lines: Vec<DiffLine>,
+}
+
+impl Hunk {
+    pub fn header(&self) -> String {
+        format!(
+            "@@ -{},{} +{},{} @@",
+            self.old_range.start + 1,
+            self.old_range.len(),
+            self.new_range.start + 1,
+            self.new_range.len()
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum HunkDecision {
+    #[default]
+    Pending,
+    Accepted,
+    Rejected,
+}
+
+/// The changes to one file, with a review decision per hunk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileDiff {
+    pub path: PathBuf,
+    pub old: String,
+    pub new: String,
+    pub hunks: Vec<Hunk>,
+    pub decisions: Vec<HunkDecision>,
+}
+
+impl FileDiff {
+    pub fn new(path: impl Into<PathBuf>, old: impl Into<String>, new: impl Into<String>) -> Self {
+        let old = old.into();
+        let new = new.into();
+        let hunks = compute_hunks(&old, &new);
+        Self {
+            path: path.into(),
+            decisions: vec![HunkDecision::Pending; hunks.len()],
+            old,
+            new,
+            hunks,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hunks.is_empty()
+    }
+
+    /// Number of added and removed lines.
+    pub fn stats(&self) -> (usize, usize) {
+        let lines = self.hunks.iter().flat_map(|hunk| &hunk.lines);
+        lines.fold((0, 0), |(added, removed), line| match line.kind {
+            DiffLineKind::Added => (added + 1, removed),
+            DiffLineKind::Removed => (added, removed + 1),
+            DiffLineKind::Context => (added, removed),
+        })
+    }
+
+    /// The old content with only the accepted hunks applied.
+    pub fn apply_accepted(&self) -> String {
+        let old_lines = self.old.split_inclusive('\n').collect::<Vec<_>>();
+        let new_lines = self.new.split_inclusive('\n').collect::<Vec<_>>();
+        let mut result = String::new();
+        let mut position = 0;
+
+        for (hunk, decision) in self.hunks.iter().zip(&self.decisions) {
+            result.extend(old_lines[position..hunk.old_range.start].iter().copied());
+            if *decision == HunkDecision::Accepted {
+                result.extend(new_lines[hunk.new_range.clone()].iter().copied());
+            } else {
+                result.extend(old_lines[hunk.old_range.clone()].iter().copied());
+            }
+            position = hunk.old_range.end;
+        }
+        result.extend(old_lines[position..].iter().copied());
+        result
+    }
+}
+
+fn compute_hunks(old: &str, new: &str) -> Vec<Hunk> {
+    let diff = TextDiff::from_lines(old, new);
+    diff.grouped_ops(DIFF_CONTEXT_LINES)
+        .into_iter()
+        .filter_map(|group| {
+            let first = group.first()?;
+            let last = group.last()?;
+            let lines = group
+                .iter()
+                .flat_map(|op| diff.iter_inline_changes(op))
+                .map(|change| {
+                    let mut segments = change
+                        .iter_strings_lossy()
+                        .map(|(emphasized, text)| (emphasized, text.into_owned()))
+                        .collect::<Vec<_>>();
+                    if let Some((_, text)) = segments.last_mut() {
+                        let trimmed = text.trim_end_matches(['\n', '\r']).len();
+                        text.truncate(trimmed);
+                    }
+                    segments.retain(|(_, text)| !text.is_empty());
+                    DiffLine {
+                        kind: match change.tag() {
+                            ChangeTag::Equal => DiffLineKind::Context,
+                            ChangeTag::Insert => DiffLineKind::Added,
+                            ChangeTag::Delete => DiffLineKind::Removed,
+                        },
+                        old_line: change.old_index().map(|index| index + 1),
+                        new_line: change.new_index().map(|index| index + 1),
+                        segments,
+                    }
+                })
+                .collect();
+            Some(Hunk {
+                old_range: first.old_range().start..last.old_range().end,
+                new_range: first.new_range().start..last.new_range().end,
+                lines,
+            })
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DiffLayout {
+    #[default]
+    Unified,
+    SideBySide,
+}
+
+/// Why the diff viewer is open.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffPurpose {
+    /// Proposed edits waiting for approval; accepted hunks are written.
+    Review,
+    /// Read-only view of the changes made in this session.
+    Session,
+}
+
+/// State of the open diff viewer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffView {
+    pub files: Vec<FileDiff>,
+    pub purpose: DiffPurpose,
+    pub layout: DiffLayout,
+    pub file: usize,
+    pub hunk: usize,
+}
+
+impl DiffView {
+    pub fn new(files: Vec<FileDiff>, purpose: DiffPurpose) -> Self {
+        Self {
+            files: files.into_iter().filter(|file| !file.is_empty()).collect(),
+            purpose,
+            layout: DiffLayout::default(),
+            file: 0,
+            hunk: 0,
+        }
+    }
+
+    pub fn current_file(&self) -> Option<&FileDiff> {
+        self.files.get(self.file)
+    }
+
+    /// Moves to the next hunk, continuing into the next file.
+    pub fn next_hunk(&mut self) {
+        let Some(file) = self.current_file() else {
+            return;
+        };
+        if self.hunk + 1 < file.hunks.len() {
+            self.hunk += 1;
+        } else if self.file + 1 < self.files.len() {
+            self.file += 1;
+            self.hunk = 0;
+        }
+    }
+
+    /// Moves to the previous hunk, continuing into the previous file.
+    pub fn previous_hunk(&mut self) {
+        if self.hunk > 0 {
+            self.hunk -= 1;
+        } else if self.file > 0 {
+            self.file -= 1;
+            self.hunk = self.files[self.file].hunks.len().saturating_sub(1);
+        }
+    }
+
+    /// Records a decision for the selected hunk and moves on to the next.
+    pub fn decide(&mut self, decision: HunkDecision) {
+        if let Some(slot) = self
+            .files
+            .get_mut(self.file)
+            .and_then(|file| file.decisions.get_mut(self.hunk))
+        {
+            *slot = decision;
+        }
+        self.next_hunk();
+    }
+
+    pub fn decide_all(&mut self, decision: HunkDecision) {
+        for file in &mut self.files {
+            file.decisions.fill(decision);
+        }
+    }
+
+    pub fn toggle_layout(&mut self) {
+        self.layout = match self.layout {
+            DiffLayout::Unified => DiffLayout::SideBySide,
+            DiffLayout::SideBySide => DiffLayout::Unified,
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OLD: &str = "fn main() {\n    let x = 1;\n    println!(\"{x}\");\n}\n";
+    const NEW: &str = "fn main() {\n    let x = 2;\n    println!(\"{x}\");\n}\n";
+
+    #[test]
+    fn test_hunks_and_word_highlights() {
+        // Test a one-word change produces a hunk with the word emphasized
+        let diff = FileDiff::new("main.rs", OLD, NEW);
+        assert_eq!(diff.hunks.len(), 1);
+        assert_eq!(diff.stats(), (1, 1));
+
+        let hunk = &diff.hunks[0];
+        assert_eq!(hunk.header(), "@@ -1,4 +1,4 @@");
+        let removed = &hunk.lines[1];
+        assert_eq!(removed.kind, DiffLineKind::Removed);
+        assert_eq!(removed.old_line, Some(2));
+        assert_eq!(removed.text(), "    let x = 1;");
+        assert_eq!(removed.segments[1], (true, "1;".to_string()));
+    }
+
+    #[test]
+    fn test_apply_accepted_hunks_only() {
+        // Test only accepted hunks change the content
+        let old = (1..=20).map(|i| format!("line {i}\n")).collect::<String>();
+        let new = old
+            .replace("line 2\n", "line two\n")
+            .replace("line 18\n", "");
+        let mut diff = FileDiff::new("notes.txt", old.as_str(), new.as_str());
+        assert_eq!(diff.hunks.len(), 2);
+        assert_eq!(diff.apply_accepted(), old);
+
+        diff.decisions = vec![HunkDecision::Rejected, HunkDecision::Accepted];
+        assert_eq!(diff.apply_accepted(), old.replace("line 18\n", ""));
+
+        diff.decisions = vec![HunkDecision::Accepted; 2];
+        assert_eq!(diff.apply_accepted(), new);
+    }
+
+    #[test]
+    fn test_diff_view_navigation() {
+        // Test hunk navigation crosses files and decisions advance the selection
+        let files = vec![
+            FileDiff::new("a.rs", OLD, NEW),
+            FileDiff::new("same.rs", OLD, OLD),
+            FileDiff::new("b.rs", OLD, NEW),
+        ];
+        let mut view = DiffView::new(files, DiffPurpose::Review);
+        assert_eq!(view.files.len(), 2);
+
+        view.decide(HunkDecision::Accepted);
+        assert_eq!((view.file, view.hunk), (1, 0));
+        view.next_hunk();
+        assert_eq!((view.file, view.hunk), (1, 0));
+        view.previous_hunk();
+        assert_eq!((view.file, view.hunk), (0, 0));
+        assert_eq!(view.files[0].decisions, vec![HunkDecision::Accepted]);
+
+        view.decide_all(HunkDecision::Rejected);
+        assert_eq!(view.files[0].decisions, vec![HunkDecision::Rejected]);
+        view.toggle_layout();
+        assert_eq!(view.layout, DiffLayout::SideBySide);
+    }
+}
@@ -0,0 +1,726 @@
+use anyhow::Result;
+use foundry_core::config::{ConfigSource, DEFAULT_API_VERSION, LayeredConfig};
+use identity::credentials::unix_now;
+use identity::{Credential, CredentialStore, decode_claims, resolve_credential};
+use serde::Serialize;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::process::Command;
+use std::time::Duration;
+use url::{Host, Url};
+
+use crate::status::format_elapsed;
+
+/// API versions this build knows how to talk to.
+pub const SUPPORTED_API_VERSIONS: &[&str] =
+    &["2024-10-21", "2025-01-01-preview", "2025-04-01-preview"];
+
+/// Tokens expiring sooner than this are flagged.
+const TOKEN_EXPIRY_WARNING: Duration = Duration::from_secs(5 * 60);
+
+/// Clock differences beyond this break token validation.
+const MAX_CLOCK_SKEW: Duration = Duration::from_secs(5 * 60);
+
+const NETWORK_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
+    Skip,
+}
+
+impl CheckStatus {
+    fn symbol(&self) -> &'static str {
+        match self {
+            Self::Pass => "✓",
+            Self::Warn => "!",
+            Self::Fail => "✗",
+            Self::Skip => "-",
+        }
+    }
+}
+
+/// The outcome of one diagnostic.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Check {
+    pub name: &'static str,
+    pub status: CheckStatus,
+    pub detail: String,
+}
+
+impl Check {
+    fn new(name: &'static str, status: CheckStatus, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            status,
+            detail: detail.into(),
+        }
+    }
+}
+
+/// Runs every diagnostic against `config`, or reports why it failed to
+/// load. Network checks are skipped when `offline`.
+pub fn run_checks(config: &Result<LayeredConfig>, offline: bool) -> Vec<Check> {
+    let env = |name: &str| std::env::var(name).ok();
+    let mut checks = vec![
+        check_colors(env("COLORTERM").as_deref()),
+        check_unicode(&env),
+        check_terminal_size(crossterm::terminal::size().ok()),
+    ];
+
+    checks.push(match config {
+        Ok(config) => {
+            let files = [
+                ConfigSource::User,
+                ConfigSource::Project,
+                ConfigSource::Local,
+            ]
+            .into_iter()
+            .filter_map(|source| config.layer(source))
+            .filter(|file| file.path.exists())
+            .map(|file| file.path.display().to_string())
+            .collect::<Vec<_>>();
+            let detail = if files.is_empty() {
+                "none found, using defaults".to_string()
+            } else {
+                files.join(", ")
+            };
+            match config.issues() {
+                [] => Check::new("Config files", CheckStatus::Pass, detail),
+                [issue] => Check::new("Config files", CheckStatus::Warn, issue.to_string()),
+                [issue, rest @ ..] => Check::new(
+                    "Config files",
+                    CheckStatus::Warn,
+                    format!("{issue} (+{} more)", rest.len()),
+                ),
+            }
+        }
+        Err(err) => Check::new("Config files", CheckStatus::Fail, format!("{err:#}")),
+    });
+    let setting = |key: &str| {
+        config
+            .as_ref()
+            .ok()
+            .and_then(|config| config.get_str(key))
+            .map(str::to_string)
+    };
+    checks.push(check_api_version(setting("api_version").as_deref()));
+
+    let store = match config {
+        Ok(config) => CredentialStore::for_profile(config.active_profile()),
+        Err(_) => CredentialStore::default_location(),
+    };
+    match resolve_credential(store.as_ref(), setting("tenant").as_deref()) {
+        Ok((credential, source)) => {
+            checks.push(Check::new(
+                "Credentials",
+                CheckStatus::Pass,
+                format!("found in {}", source.label()),
+            ));
+            checks.push(check_token_expiry(&credential, unix_now()));
+        }
+        Err(err) => {
+            checks.push(Check::new(
+                "Credentials",
+                CheckStatus::Fail,
+                format!("{err:#}"),
+            ));
+            checks.push(Check::new(
+                "Token expiry",
+                CheckStatus::Skip,
+                "no credentials",
+            ));
+        }
+    }
+
+    checks.push(check_proxy(&env));
+    checks.extend(check_endpoint(
+        setting("endpoint").as_deref(),
+        &env,
+        offline,
+    ));
+    checks.push(check_sandbox());
+    checks
+}
+
+fn check_colors(colorterm: Option<&str>) -> Check {
+    match colorterm {
+        Some("truecolor" | "24bit") => {
+            Check::new("Terminal colors", CheckStatus::Pass, "truecolor")
+        }
+        _ => Check::new(
+            "Terminal colors",
+            CheckStatus::Warn,
+            "COLORTERM does not advertise truecolor; themes fall back to the nearest colors",
+        ),
+    }
+}
+
+fn check_unicode(env: &impl Fn(&str) -> Option<String>) -> Check {
+    // The first locale variable that is set wins, as in setlocale(3).
+    let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+        .into_iter()
+        .find_map(|name| env(name).filter(|value| !value.is_empty()));
+    match locale {
+        Some(locale) if locale.to_lowercase().replace('-', "").contains("utf8") => {
+            Check::new("Unicode", CheckStatus::Pass, locale)
+        }
+        Some(locale) => Check::new(
+            "Unicode",
+            CheckStatus::Warn,
+            format!("locale {locale} is not UTF-8; symbols may not render"),
+        ),
+        None if cfg!(windows) => Check::new("Unicode", CheckStatus::Pass, "Windows console"),
+        None => Check::new(
+            "Unicode",
+            CheckStatus::Warn,
+            "no locale set; symbols may not render",
+        ),
+    }
+}
+
+fn check_terminal_size(size: Option<(u16, u16)>) -> Check {
+    match size {
+        Some((columns, rows)) if columns >= 80 && rows >= 24 => Check::new(
+            "Terminal size",
+            CheckStatus::Pass,
+            format!("{columns}x{rows}"),
+        ),
+        Some((columns, rows)) => Check::new(
+            "Terminal size",
+            CheckStatus::Warn,
+            format!("{columns}x{rows} is smaller than 80x24"),
+        ),
+        None => Check::new("Terminal size", CheckStatus::Warn, "not a terminal"),
+    }
+}
+
+fn check_api_version(api_version: Option<&str>) -> Check {
+    match api_version {
+        None => Check::new(
+            "API version",
+            CheckStatus::Pass,
+            format!("{DEFAULT_API_VERSION} (default)"),
+        ),
+        Some(version) if SUPPORTED_API_VERSIONS.contains(&version) => {
+            Check::new("API version", CheckStatus::Pass, version)
+        }
+        Some(version) => Check::new(
+            "API version",
+            CheckStatus::Fail,
+            format!(
+                "{version} is not supported; use one of {}",
+                SUPPORTED_API_VERSIONS.join(", ")
+            ),
+        ),
+    }
+}
+
+fn check_token_expiry(credential: &Credential, now: u64) -> Check {
+    let Credential::AccessToken { token, expires_on } = credential else {
+        return Check::new("Token expiry", CheckStatus::Pass, "API keys do not expire");
+    };
+    let Some(expires_on) =
+        expires_on.or_else(|| decode_claims(token).ok().and_then(|claims| claims.exp))
+    else {
+        return Check::new("Token expiry", CheckStatus::Warn, "token has no expiry");
+    };
+    if expires_on <= now {
+        return Check::new(
+            "Token expiry",
+            CheckStatus::Fail,
+            format!(
+                "expired {} ago; run `foundry login`",
+                format_elapsed(Duration::from_secs(now - expires_on))
+            ),
+        );
+    }
+    let remaining = Duration::from_secs(expires_on - now);
+    let status = if remaining < TOKEN_EXPIRY_WARNING {
+        CheckStatus::Warn
+    } else {
+        CheckStatus::Pass
+    };
+    Check::new(
+        "Token expiry",
+        status,
+        format!("expires in {}", format_elapsed(remaining)),
+    )
+}
+
+fn check_proxy(env: &impl Fn(&str) -> Option<String>) -> Check {
+    let proxies = [
+        "HTTPS_PROXY",
+        "https_proxy",
+        "HTTP_PROXY",
+        "http_proxy",
+        "ALL_PROXY",
+    ]
+    .into_iter()
+    .filter_map(|name| Some((name, env(name).filter(|value| !value.is_empty())?)))
+    .collect::<Vec<_>>();
+    let Some((name, proxy)) = proxies.first() else {
+        return Check::new("Proxy", CheckStatus::Pass, "none");
+    };
+    if !proxy.contains("://") {
+        return Check::new(
+            "Proxy",
+            CheckStatus::Warn,
+            format!("{name}={proxy} has no scheme, e.g. http://"),
+        );
+    }
+    let no_proxy = env("NO_PROXY")
+        .or_else(|| env("no_proxy"))
+        .map(|hosts| format!(", bypassed for {hosts}"))
+        .unwrap_or_default();
+    Check::new(
+        "Proxy",
+        CheckStatus::Pass,
+        format!("{name}={}{no_proxy}", redact_userinfo(proxy)),
+    )
+}
+
+/// Hides any password in a proxy URL.
+fn redact_userinfo(url: &str) -> String {
+    match url.split_once("://") {
+        Some((scheme, rest)) => match rest.split_once('@') {
+            Some((_, host)) => format!("{scheme}://***@{host}"),
+            None => url.to_string(),
+        },
+        None => url.to_string(),
+    }
+}
+
+/// The proxy curl uses for HTTPS requests to `host`, unless `NO_PROXY`
+/// bypasses it.
+fn proxy_for(host: &str, env: &impl Fn(&str) -> Option<String>) -> Option<String> {
+    let proxy = ["HTTPS_PROXY", "https_proxy", "ALL_PROXY", "all_proxy"]
+        .into_iter()
+        .find_map(|name| env(name).filter(|value| !value.is_empty()))?;
+    let host = host.to_ascii_lowercase();
+    let no_proxy = env("NO_PROXY")
+        .or_else(|| env("no_proxy"))
+        .unwrap_or_default();
+    let bypassed = no_proxy.split(',').any(|entry| {
+        let entry = entry.trim().to_ascii_lowercase();
+        let domain = entry.trim_start_matches('.');
+        entry == "*"
+            || (!domain.is_empty() && (host == domain || host.ends_with(&format!(".{domain}"))))
+    });
+    (!bypassed).then_some(proxy)
+}
+
+/// Resolves and connects to the endpoint, then makes an HTTPS request with
+/// curl to check TLS and compare clocks with the server. Behind a proxy the
+/// endpoint may not resolve or be reachable directly, so only curl, which
+/// goes through the proxy, is used.
+fn check_endpoint(
+    endpoint: Option<&str>,
+    env: &impl Fn(&str) -> Option<String>,
+    offline: bool,
+) -> Vec<Check> {
+    let skip = |detail: &str| {
+        ["DNS", "TLS", "Clock skew"]
+            .map(|name| Check::new(name, CheckStatus::Skip, detail))
+            .to_vec()
+    };
+    if offline {
+        return skip("offline");
+    }
+    let Some(endpoint) = endpoint else {
+        let mut checks = skip("no endpoint configured");
+        checks[0] = Check::new(
+            "DNS",
+            CheckStatus::Warn,
+            "no endpoint configured; run `foundry config set endpoint <url>`",
+        );
+        return checks;
+    };
+    let Some((host, port)) = endpoint_host(endpoint) else {
+        let mut checks = skip("invalid endpoint");
+        checks[0] = Check::new(
+            "DNS",
+            CheckStatus::Fail,
+            format!("{endpoint} is not an https:// URL"),
+        );
+        return checks;
+    };
+
+    let mut checks = Vec::new();
+    let target = if let Some(proxy) = proxy_for(&host, env) {
+        let proxy = redact_userinfo(&proxy);
+        checks.push(Check::new(
+            "DNS",
+            CheckStatus::Skip,
+            format!("resolved by proxy {proxy}"),
+        ));
+        format!("proxy {proxy}")
+    } else {
+        let address = match (host.as_str(), port).to_socket_addrs() {
+            Ok(mut addresses) => addresses.next(),
+            Err(err) => {
+                let mut checks = skip("DNS failed");
+                checks[0] = Check::new("DNS", CheckStatus::Fail, format!("{host}: {err}"));
+                return checks;
+            }
+        };
+        let Some(address) = address else {
+            let mut checks = skip("DNS failed");
+            checks[0] = Check::new("DNS", CheckStatus::Fail, format!("{host}: no addresses"));
+            return checks;
+        };
+        checks.push(Check::new(
+            "DNS",
+            CheckStatus::Pass,
+            format!("{host} → {}", address.ip()),
+        ));
+
+        if let Err(err) = TcpStream::connect_timeout(&address, NETWORK_TIMEOUT) {
+            checks.push(Check::new(
+                "TLS",
+                CheckStatus::Fail,
+                format!("cannot connect to {address}: {err}"),
+            ));
+            checks.push(Check::new("Clock skew", CheckStatus::Skip, "unreachable"));
+            return checks;
+        }
+        address.to_string()
+    };
+
+    let output = Command::new("curl")
+        .args(["--silent", "--show-error", "--head", "--max-time"])
+        .arg(NETWORK_TIMEOUT.as_secs().to_string())
+        .arg(endpoint)
+        .output();
+    let headers = match output {
+        Ok(output) if output.status.success() => {
+            String::from_utf8_lossy(&output.stdout).into_owned()
+        }
+        Ok(output) => {
+            checks.push(Check::new(
+                "TLS",
+                CheckStatus::Fail,
+                String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            ));
+            checks.push(Check::new("Clock skew", CheckStatus::Skip, "no response"));
+            return checks;
+        }
+        Err(_) => {
+            checks.push(Check::new(
+                "TLS",
+                CheckStatus::Warn,
+                format!("connected to {target}, but curl is not installed to check TLS"),
+            ));
+            checks.push(Check::new("Clock skew", CheckStatus::Skip, "no response"));
+            return checks;
+        }
+    };
+    let status_line = headers.lines().next().unwrap_or_default().trim();
+    checks.push(Check::new("TLS", CheckStatus::Pass, status_line));
+
+    let server_time = headers.lines().find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        name.eq_ignore_ascii_case("date")
+            .then(|| parse_http_date(value.trim()))
+            .flatten()
+    });
+    checks.push(match server_time {
+        Some(server_time) => check_clock_skew(server_time, unix_now()),
+        None => Check::new(
+            "Clock skew",
+            CheckStatus::Skip,
+            "server sent no Date header",
+        ),
+    });
+    checks
+}
+
+/// The host and port of an `https://` URL, with IPv6 addresses unbracketed.
+fn endpoint_host(endpoint: &str) -> Option<(String, u16)> {
+    let url = Url::parse(endpoint).ok()?;
+    if url.scheme() != "https" {
+        return None;
+    }
+    let host = match url.host()? {
+        Host::Domain(domain) => domain.to_string(),
+        Host::Ipv4(address) => address.to_string(),
+        Host::Ipv6(address) => address.to_string(),
+    };
+    Some((host, url.port_or_known_default()?))
+}
+
+fn check_clock_skew(server_time: u64, local_time: u64) -> Check {
+    let skew = Duration::from_secs(server_time.abs_diff(local_time));
+    let direction = if local_time >= server_time {
+        "ahead of"
+    } else {
+        "behind"
+    };
+    if skew > MAX_CLOCK_SKEW {
+        Check::new(
+            "Clock skew",
+            CheckStatus::Fail,
+            format!(
+                "local clock is {} {direction} the server; tokens may be rejected",
+                format_elapsed(skew)
+            ),
+        )
+    } else {
+        Check::new(
+            "Clock skew",
+            CheckStatus::Pass,
+            format!("within {}", format_elapsed(skew)),
+        )
+    }
+}
+
+/// Parses an IMF-fixdate such as `Sun, 06 Nov 1994 08:49:37 GMT` into
+/// Unix time.
+fn parse_http_date(date: &str) -> Option<u64> {
+    let mut parts = date.split_whitespace().skip(1);
+    let day = parts.next()?.parse::<u64>().ok()?;
+    let month = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ]
+    .iter()
+    .position(|name| Some(*name) == parts.clone().next())? as u64
+        + 1;
+    let year = parts.nth(1)?.parse::<u64>().ok()?;
+    let mut time = parts
+        .next()?
+        .split(':')
+        .map(|part| part.parse::<u64>().ok());
+    let (hours, minutes, seconds) = (time.next()??, time.next()??, time.next()??);
+
+    // Days since the epoch for a proleptic Gregorian date, shifting the year
+    // to start in March so leap days fall at its end.
+    let (year, month) = if month <= 2 {
+        (year - 1, month + 9)
+    } else {
+        (year, month - 3)
+    };
+    let era = year / 400;
+    let year_of_era = year % 400;
+    let day_of_year = (153 * month + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = (era * 146_097 + day_of_era).checked_sub(719_468)?;
+    Some(days * 86_400 + hours * 3_600 + minutes * 60 + seconds)
+}
+
+/// Whether tools run in an OS sandbox. None does yet: the agent's file tools
+/// only refuse paths outside the workspace.
+fn check_sandbox() -> Check {
+    Check::new(
+        "Sandbox",
+        CheckStatus::Warn,
+        "commands run unsandboxed; file tools are limited to the workspace",
+    )
+}
+
+/// A report with one line per check and a summary.
+pub fn render_report(checks: &[Check]) -> String {
+    let width = checks
+        .iter()
+        .map(|check| check.name.len())
+        .max()
+        .unwrap_or(0);
+    let mut lines = checks
+        .iter()
+        .map(|check| {
+            format!(
+                "{} {:<width$}  {}",
+                check.status.symbol(),
+                check.name,
+                check.detail
+            )
+        })
+        .collect::<Vec<_>>();
+    let count = |status| checks.iter().filter(|check| check.status == status).count();
+    lines.push(String::new());
+    lines.push(format!(
+        "{} passed, {} warnings, {} failed, {} skipped",
+        count(CheckStatus::Pass),
+        count(CheckStatus::Warn),
+        count(CheckStatus::Fail),
+        count(CheckStatus::Skip)
+    ));
+    lines.join("\n")
+}
+
+/// The checks as JSON, for attaching to bug reports.
+pub fn render_json(checks: &[Check]) -> Result<String> {
+    #[derive(Serialize)]
+    struct Report<'a> {
+        version: &'static str,
+        os: &'static str,
+        checks: &'a [Check],
+    }
+    Ok(serde_json::to_string_pretty(&Report {
+        version: env!("CARGO_PKG_VERSION"),
+        os: std::env::consts::OS,
+        checks,
+    })?)
+}
+
+pub fn has_failures(checks: &[Check]) -> bool {
+    checks.iter().any(|check| check.status == CheckStatus::Fail)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env_from(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars = vars
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect::<Vec<_>>();
+        move |name| {
+            vars.iter()
+                .find(|(var, _)| var == name)
+                .map(|(_, value)| value.clone())
+        }
+    }
+
+    #[test]
+    fn test_terminal_checks() {
+        // Test color, locale and size checks pass or warn appropriately
+        assert_eq!(check_colors(Some("truecolor")).status, CheckStatus::Pass);
+        assert_eq!(check_colors(None).status, CheckStatus::Warn);
+
+        let env = env_from(&[("LC_ALL", "C"), ("LANG", "en_US.UTF-8")]);
+        assert_eq!(check_unicode(&env).status, CheckStatus::Warn);
+        let env = env_from(&[("LANG", "en_US.utf8")]);
+        assert_eq!(check_unicode(&env).status, CheckStatus::Pass);
+
+        assert_eq!(
+            check_terminal_size(Some((120, 40))).status,
+            CheckStatus::Pass
+        );
+        assert_eq!(
+            check_terminal_size(Some((60, 20))).status,
+            CheckStatus::Warn
+        );
+    }
+
+    #[test]
+    fn test_api_version_and_token_expiry() {
+        // Test unknown API versions fail and tokens are flagged near expiry
+        assert_eq!(check_api_version(None).status, CheckStatus::Pass);
+        assert_eq!(
+            check_api_version(Some("2019-01-01")).status,
+            CheckStatus::Fail
+        );
+
+        let token = |expires_on| Credential::AccessToken {
+            token: String::new(),
+            expires_on: Some(expires_on),
+        };
+        assert_eq!(
+            check_token_expiry(&token(900), 1_000).status,
+            CheckStatus::Fail
+        );
+        assert_eq!(
+            check_token_expiry(&token(1_060), 1_000).status,
+            CheckStatus::Warn
+        );
+        assert_eq!(
+            check_token_expiry(&token(5_000), 1_000).status,
+            CheckStatus::Pass
+        );
+        let key = Credential::ApiKey {
+            key: "k".to_string(),
+        };
+        assert_eq!(check_token_expiry(&key, 1_000).status, CheckStatus::Pass);
+    }
+
+    #[test]
+    fn test_proxy_check_redacts_passwords() {
+        // Test proxy settings are reported without credentials
+        let env = env_from(&[
+            ("HTTPS_PROXY", "http://user:pw@proxy:8080"),
+            ("NO_PROXY", "localhost"),
+        ]);
+        let check = check_proxy(&env);
+        assert_eq!(check.status, CheckStatus::Pass);
+        assert_eq!(
+            check.detail,
+            "HTTPS_PROXY=http://***@proxy:8080, bypassed for localhost"
+        );
+        assert_eq!(
+            check_proxy(&env_from(&[("HTTP_PROXY", "proxy:8080")])).status,
+            CheckStatus::Warn
+        );
+
+        assert_eq!(
+            proxy_for("contoso.openai.azure.com", &env),
+            Some("http://user:pw@proxy:8080".to_string())
+        );
+        let env = env_from(&[
+            ("HTTPS_PROXY", "http://proxy:8080"),
+            ("NO_PROXY", "localhost, .azure.com"),
+        ]);
+        assert_eq!(proxy_for("contoso.openai.azure.com", &env), None);
+        assert_eq!(proxy_for("localhost", &env), None);
+        assert_eq!(
+            proxy_for("example.com", &env_from(&[("HTTP_PROXY", "http://proxy")])),
+            None
+        );
+    }
+
+    #[test]
+    fn test_endpoint_parsing_and_clock_skew() {
+        // Test endpoint hosts, HTTP dates and skew thresholds
+        assert_eq!(
+            endpoint_host("https://contoso.openai.azure.com/openai"),
+            Some(("contoso.openai.azure.com".to_string(), 443))
+        );
+        assert_eq!(
+            endpoint_host("https://localhost:8443"),
+            Some(("localhost".to_string(), 8443))
+        );
+        assert_eq!(
+            endpoint_host("https://[::1]:8443/openai"),
+            Some(("::1".to_string(), 8443))
+        );
+        assert_eq!(endpoint_host("http://insecure.example.com"), None);
+
+        assert_eq!(
+            parse_http_date("Sun, 06 Nov 1994 08:49:37 GMT"),
+            Some(784_111_777)
+        );
+        assert_eq!(
+            parse_http_date("Thu, 29 Feb 2024 00:00:00 GMT"),
+            Some(1_709_164_800)
+        );
+        assert_eq!(parse_http_date("yesterday"), None);
+
+        assert_eq!(check_clock_skew(1_000, 1_030).status, CheckStatus::Pass);
+        assert_eq!(check_clock_skew(1_000, 2_000).status, CheckStatus::Fail);
+        assert!(
+            check_endpoint(None, &env_from(&[]), true)
+                .iter()
+                .all(|check| check.status == CheckStatus::Skip)
+        );
+    }
+
+    #[test]
+    fn test_render_report_and_json() {
+        // Test the report summarizes statuses and the JSON lists every check
+        let checks = vec![
+            Check::new("Config file", CheckStatus::Pass, "ok"),
+            Check::new("DNS", CheckStatus::Fail, "no host"),
+        ];
+        let report = render_report(&checks);
+        assert!(report.contains("✓ Config file  ok"));
+        assert!(report.contains("✗ DNS          no host"));
+        assert!(report.ends_with("1 passed, 0 warnings, 1 failed, 0 skipped"));
+        assert!(has_failures(&checks));
+
+        let json =
+            serde_json::from_str::<serde_json::Value>(&render_json(&checks).unwrap()).unwrap();
+        assert_eq!(json["checks"][1]["status"], "fail");
+    }
+}
@@ -0,0 +1,177 @@
+use anyhow::{Context, Result, bail};
+use foundry_core::paths::display_path;
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use crate::app::AppState;
+use crate::terminal::{TerminalType, restore_terminal, resume_terminal};
+
+#[cfg(windows)]
+const FALLBACK_EDITOR: &str = "notepad";
+#[cfg(not(windows))]
+const FALLBACK_EDITOR: &str = "vi";
+
+/// The user's editor from `$VISUAL` or `$EDITOR`, falling back to a
+/// platform default. The value may include arguments, e.g. `code --wait`.
+pub fn editor_command() -> String {
+    ["VISUAL", "EDITOR"]
+        .into_iter()
+        .filter_map(|name| env::var(name).ok())
+        .find(|value| !value.trim().is_empty())
+        .unwrap_or_else(|| FALLBACK_EDITOR.to_string())
+}
+
+/// Writes `initial` to a temporary file, runs `editor` on it and returns the
+/// edited contents without the trailing newline most editors add.
+pub fn edit_text(editor: &str, initial: &str) -> Result<String> {
+    let file = tempfile::Builder::new()
+        .prefix("foundry-prompt-")
+        .suffix(".md")
+        .tempfile()
+        .context("Failed to create a temporary file for the prompt")?;
+    fs::write(file.path(), initial)
+        .with_context(|| format!("Failed to write {}", file.path().display()))?;
+
+    edit_file(editor, file.path())?;
+    // Read by path: many editors save by replacing the file.
+    let contents = fs::read_to_string(file.path()).context("Failed to read edited prompt")?;
+    Ok(contents
+        .strip_suffix('\n')
+        .map(|text| text.strip_suffix('\r').unwrap_or(text))
+        .unwrap_or(&contents)
+        .to_string())
+}
+
+/// Runs `editor` on `path` and waits for it to exit. The editor is run by
+/// the shell, as git does, so it may be quoted or carry arguments.
+pub fn edit_file(editor: &str, path: &Path) -> Result<()> {
+    if editor.trim().is_empty() {
+        bail!("No editor configured");
+    }
+
+    let status = editor_process(editor, path)
+        .status()
+        .with_context(|| format!("Failed to launch editor `{editor}`"))?;
+    if status.code() == Some(SHELL_NOT_FOUND) {
+        bail!("Failed to launch editor `{editor}`: command not found");
+    }
+    if !status.success() {
+        bail!("Editor `{editor}` exited with {status}");
+    }
+    Ok(())
+}
+
+/// Exit status the shell uses when a command cannot be found.
+#[cfg(not(windows))]
+const SHELL_NOT_FOUND: i32 = 127;
+#[cfg(windows)]
+const SHELL_NOT_FOUND: i32 = 9009;
+
+#[cfg(not(windows))]
+fn editor_process(editor: &str, path: &Path) -> Command {
+    let mut command = Command::new("sh");
+    command
+        .arg("-c")
+        .arg(format!("{editor} \"$1\""))
+        .arg(editor)
+        .arg(path);
+    command
+}
+
+#[cfg(windows)]
+fn editor_process(editor: &str, path: &Path) -> Command {
+    let mut command = Command::new("cmd");
+    command.arg("/C").arg(editor).arg(path);
+    command
+}
+
+/// Suspends the TUI, edits the current input in the external editor and
+/// loads the result back. Editor failures leave the input untouched and are
+/// reported in the status bar.
+pub fn edit_input_in_editor(terminal: &mut TerminalType, app: &mut AppState) -> Result<()> {
+    restore_terminal(terminal)?;
+    let result = edit_text(&editor_command(), &app.expanded_input());
+    resume_terminal(terminal)?;
+
+    match result {
+        Ok(text) => app.set_input(&text),
+        Err(err) => app.set_status(format!("⚠️ {err:#}")),
+    }
+    Ok(())
+}
+
+/// Suspends the TUI, opens an instruction file in the external editor and
+/// reloads the instructions afterwards.
+pub fn edit_instructions_in_editor(
+    terminal: &mut TerminalType,
+    app: &mut AppState,
+    path: &Path,
+) -> Result<()> {
+    restore_terminal(terminal)?;
+    let result = path
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .with_context(|| format!("Failed to create {}", path.display()))
+        .and_then(|()| edit_file(&editor_command(), path));
+    resume_terminal(terminal)?;
+
+    app.instructions.reload();
+    match result {
+        Ok(()) => app.set_status(format!("📝 Reloaded {}", display_path(path))),
+        Err(err) => app.set_status(format!("⚠️ {err:#}")),
+    }
+    Ok(())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    /// Writes an executable editor script that runs `body` with the file
+    /// to edit as `$1`.
+    fn editor_script(dir: &Path, body: &str) -> PathBuf {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = dir.join("my editor.sh");
+        fs::write(&path, format!("#!/bin/sh\n{body}\n")).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_edit_text_returns_edited_contents() {
+        // Test the edited file is read back without its trailing newline
+        let dir = foundry_core::testing::temp_dir();
+        let script = editor_script(
+            dir.path(),
+            r#"[ "$(cat "$1")" = "a draft prompt" ] && printf 'a final prompt\n' > "$1""#,
+        );
+        let editor = format!("'{}'", script.display());
+        let edited = edit_text(&editor, "a draft prompt\n").unwrap();
+        assert_eq!(edited, "a final prompt");
+    }
+
+    #[test]
+    fn test_edit_file_passes_arguments_and_odd_paths() {
+        // Test editor arguments are kept and paths with spaces arrive whole
+        let dir = foundry_core::testing::temp_dir();
+        let script = editor_script(dir.path(), r#"printf '%s' "$1" > "$2""#);
+        let target = dir.path().join("notes file.md");
+        let editor = format!("\"{}\" --wait", script.display());
+        edit_file(&editor, &target).unwrap();
+        assert_eq!(fs::read_to_string(&target).unwrap(), "--wait");
+    }
+
+    #[test]
+    fn test_edit_text_reports_editor_failures() {
+        // Test failing and missing editors surface as errors
+        let err = edit_text("false", "prompt").unwrap_err();
+        assert!(err.to_string().contains("exited with"));
+
+        let err = edit_text("foundry-no-such-editor", "prompt").unwrap_err();
+        assert!(err.to_string().contains("Failed to launch editor"));
+    }
+}
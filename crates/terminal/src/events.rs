@@ -1,45 +1,428 @@
 use anyhow::Result;
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 
+use foundry_core::mentions::attach_file_references;
+
 use crate::app::AppState;
-use crate::commands::execute_command;
+use crate::commands::{execute_app_command, execute_command};
+use crate::completion::CompletionKind;
+use crate::diff::{DiffPurpose, HunkDecision};
+use crate::keymap::{Action, KeyChord, KeyResolution};
+use crate::vim::{VimMode, VimOutcome};
+
+/// Lines moved per scroll-up/scroll-down action.
+const SCROLL_STEP: usize = 5;
 
 pub fn handle_input(app: &mut AppState, key: KeyEvent) -> Result<()> {
-    match key.code {
-        KeyCode::Esc => {
-            app.quit();
+    for (key, resolution) in app.keymap.resolve(&mut app.pending_keys, key) {
+        handle_key(app, key, resolution);
+    }
+    Ok(())
+}
+
+fn handle_key(app: &mut AppState, key: KeyEvent, resolution: KeyResolution) {
+    if app.reverse_search.is_some() {
+        handle_reverse_search_input(app, &resolution);
+        return;
+    }
+
+    if app.completion.is_some() && handle_completion_input(app, &resolution) {
+        return;
+    }
+
+    if app.diff_view.is_some() {
+        handle_diff_input(app, &resolution);
+        return;
+    }
+
+    if app.console_search.is_some() && handle_console_search_input(app, &resolution) {
+        return;
+    }
+
+    if app.focus.is_some() && handle_focus_input(app, &resolution) {
+        return;
+    }
+
+    if app.vim.is_some() && handle_vim_input(app, key, &resolution) {
+        return;
+    }
+
+    match resolution {
+        KeyResolution::Action(action) => handle_action(app, action),
+        KeyResolution::Unbound(chord) => {
+            if let Some(c) = typed_char(chord) {
+                app.add_char(c);
+                app.refresh_completion();
+            }
         }
-        KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+    }
+}
+
+fn handle_action(app: &mut AppState, action: Action) {
+    match action {
+        Action::Quit => {
             app.quit();
         }
-        KeyCode::Enter => {
-            if !app.input.is_empty() {
-                let command = app.input.clone();
-                app.add_output(format!("> {command}"));
-                let result = execute_command(&command);
-                app.add_output(result);
-
-                if command.trim() == "/exit" {
-                    app.quit();
-                }
-
+        Action::Cancel => {
+            if app.agent.interrupt() {
+                app.run_agent();
+            } else if app.scroll_back > 0 {
+                app.scroll_back = 0;
+            } else {
                 app.clear_input();
             }
         }
-        KeyCode::Backspace => {
+        Action::ReverseSearch => {
+            app.start_reverse_search();
+        }
+        Action::Submit => {
+            if !app.input.is_empty() {
+                submit_input(app);
+            }
+        }
+        Action::Complete => {
+            app.refresh_completion();
+            if app
+                .completion
+                .as_ref()
+                .is_some_and(|popup| popup.items.len() == 1)
+            {
+                app.accept_completion();
+            }
+        }
+        Action::CompletePrevious => {}
+        Action::DeleteBackward => {
             app.remove_char();
+            app.refresh_completion();
         }
-        KeyCode::Left => {
+        Action::MoveLeft => {
             app.move_cursor_left();
+            app.refresh_completion();
         }
-        KeyCode::Right => {
+        Action::MoveRight => {
             app.move_cursor_right();
+            app.refresh_completion();
         }
-        KeyCode::Char(c) => {
-            app.add_char(c);
+        Action::MoveUp if app.is_cursor_on_first_line() => {
+            app.history_previous();
+        }
+        Action::MoveUp => {
+            app.move_cursor_up();
+        }
+        Action::MoveDown if app.is_cursor_on_last_line() => {
+            app.history_next();
+        }
+        Action::MoveDown => {
+            app.move_cursor_down();
+        }
+        Action::HistoryPrev => {
+            app.history_previous();
+        }
+        Action::HistoryNext => {
+            app.history_next();
+        }
+        Action::ScrollUp => {
+            app.scroll_up(SCROLL_STEP);
+        }
+        Action::ScrollDown => {
+            app.scroll_down(SCROLL_STEP);
+        }
+        Action::OpenEditor => {
+            app.editor_requested = true;
+        }
+        Action::Suspend => {
+            app.suspend_requested = true;
+        }
+        Action::FocusOutput => {
+            app.focus_output();
+        }
+        Action::SearchConsole => {
+            app.start_console_search();
+        }
+        Action::ToggleTranscript => {
+            app.toggle_transcript_mode();
+            let mode = if app.verbose_transcript {
+                "verbose"
+            } else {
+                "compact"
+            };
+            app.set_status(format!("Transcript mode: {mode}"));
+        }
+    }
+}
+
+/// Offers a key to vi mode, returning `false` for keys that should fall
+/// through to normal input handling.
+fn handle_vim_input(app: &mut AppState, key: KeyEvent, resolution: &KeyResolution) -> bool {
+    let Some(vim) = &mut app.vim else {
+        return false;
+    };
+
+    if vim.mode == VimMode::Insert {
+        if *resolution == KeyResolution::Action(Action::Cancel) {
+            vim.enter_normal(&app.input, &mut app.cursor_position);
+            return true;
+        }
+        return false;
+    }
+
+    let before = app.input.clone();
+    let outcome = vim.handle_key(&mut app.input, &mut app.cursor_position, key);
+    app.track_input_edit(&before);
+    match outcome {
+        VimOutcome::Handled => true,
+        VimOutcome::Action(action) => {
+            handle_action(app, action);
+            true
+        }
+        VimOutcome::Unhandled => false,
+    }
+}
+
+/// Returns the character to insert for an unbound key, ignoring chords
+/// that carry Ctrl or Alt.
+fn typed_char(chord: KeyChord) -> Option<char> {
+    match chord.code {
+        KeyCode::Char(c)
+            if !chord
+                .modifiers
+                .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+        {
+            Some(c)
+        }
+        _ => None,
+    }
+}
+
+fn submit_input(app: &mut AppState) {
+    let command = app.expanded_input();
+    let is_command = command.trim_start().starts_with('/');
+    if !is_command && app.agent.is_running() {
+        app.set_status("⚠️ A turn is still running; press Esc to interrupt it".to_string());
+        return;
+    }
+
+    app.scroll_back = 0;
+    app.add_output(format!("> {}", app.input));
+    if let Err(err) = app.history.push(&command) {
+        app.set_status(format!("⚠️ {err:#}"));
+    }
+    if is_command {
+        let result =
+            execute_app_command(app, &command).unwrap_or_else(|| execute_command(&command));
+        app.add_output(result);
+        if let Some(message) = app.pending_turn.take()
+            && let Err(err) = app.start_turn(&message)
+        {
+            app.set_status(format!("⚠️ {err:#}"));
+        }
+    } else {
+        // Messages for the model carry the contents of any @-mentioned files.
+        let attached = attach_file_references(&command, &app.workspace_root);
+        if let Some(warning) = attached.warning() {
+            app.set_status(format!("⚠️ {warning}"));
+        }
+        if let Err(err) = app.start_turn(&attached.text) {
+            app.set_status(format!("⚠️ {err:#}"));
+        }
+    }
+
+    if command.trim() == "/exit" {
+        app.quit();
+    }
+
+    app.clear_input();
+}
+
+/// Handles keys while the diff viewer is open. The viewer is modal, so
+/// unrelated keys are ignored.
+fn handle_diff_input(app: &mut AppState, resolution: &KeyResolution) {
+    let Some(view) = &mut app.diff_view else {
+        return;
+    };
+    let review = view.purpose == DiffPurpose::Review;
+    let key = match resolution {
+        KeyResolution::Unbound(chord) => typed_char(*chord),
+        _ => None,
+    };
+
+    match (resolution, key) {
+        (KeyResolution::Action(Action::MoveDown), _) | (_, Some('n' | 'j' | ']')) => {
+            view.next_hunk();
+        }
+        (KeyResolution::Action(Action::MoveUp), _) | (_, Some('p' | 'k' | '[')) => {
+            view.previous_hunk();
+        }
+        (_, Some('s')) => view.toggle_layout(),
+        (_, Some('a')) if review => view.decide(HunkDecision::Accepted),
+        (_, Some('r')) if review => view.decide(HunkDecision::Rejected),
+        (_, Some('A')) if review => view.decide_all(HunkDecision::Accepted),
+        (_, Some('R')) if review => view.decide_all(HunkDecision::Rejected),
+        (KeyResolution::Action(Action::Submit), _) if review => match app.submit_review() {
+            Ok(written) => app.set_status(format!("✅ Applied edits to {written} file(s)")),
+            Err(err) => app.set_status(format!("⚠️ {err:#}")),
+        },
+        (KeyResolution::Action(Action::Cancel | Action::Submit), _) => {
+            if review {
+                app.set_status("Proposed edits discarded".to_string());
+            }
+            app.discard_review();
+        }
+        (KeyResolution::Action(Action::Quit), _) => app.quit(),
+        _ => {}
+    }
+}
+
+/// Handles keys while the console search is open. While the query is being
+/// edited every key is consumed; afterwards n/N jump between matches and
+/// other keys close the search and fall through, returning `false`.
+fn handle_console_search_input(app: &mut AppState, resolution: &KeyResolution) -> bool {
+    let Some(search) = &mut app.console_search else {
+        return false;
+    };
+    let key = match resolution {
+        KeyResolution::Unbound(chord) => typed_char(*chord),
+        _ => None,
+    };
+
+    if search.editing {
+        match (resolution, key) {
+            (KeyResolution::Action(Action::Cancel | Action::Quit), _) => {
+                app.close_console_search();
+            }
+            (KeyResolution::Action(Action::Submit), _) => search.editing = false,
+            (KeyResolution::Action(Action::Complete), _) => app.toggle_search_regex(),
+            (KeyResolution::Action(Action::MoveUp | Action::HistoryPrev), _) => {
+                app.jump_to_search_match(true);
+            }
+            (KeyResolution::Action(Action::MoveDown | Action::HistoryNext), _) => {
+                app.jump_to_search_match(false);
+            }
+            (KeyResolution::Action(Action::DeleteBackward), _) => {
+                let mut query = search.query.clone();
+                query.pop();
+                app.update_console_search(query);
+            }
+            (_, Some(c)) => {
+                let query = format!("{}{c}", search.query);
+                app.update_console_search(query);
+            }
+            _ => {}
+        }
+        return true;
+    }
+
+    match (resolution, key) {
+        (_, Some('n')) => app.jump_to_search_match(true),
+        (_, Some('N')) => app.jump_to_search_match(false),
+        (KeyResolution::Action(Action::SearchConsole), _) | (_, Some('/')) => {
+            search.editing = true;
+        }
+        (KeyResolution::Action(Action::Cancel), _) => app.close_console_search(),
+        _ => {
+            app.close_console_search();
+            return false;
+        }
+    }
+    true
+}
+
+/// Handles keys while a console message is focused, returning `false` for
+/// keys that leave focus mode and should be handled as usual.
+fn handle_focus_input(app: &mut AppState, resolution: &KeyResolution) -> bool {
+    match resolution {
+        KeyResolution::Action(Action::MoveUp | Action::HistoryPrev | Action::FocusOutput) => {
+            app.move_focus(false);
+        }
+        KeyResolution::Action(Action::MoveDown | Action::HistoryNext) => app.move_focus(true),
+        KeyResolution::Action(Action::Cancel) => {
+            app.focus = None;
+            app.scroll_back = 0;
+        }
+        KeyResolution::Action(Action::Submit) => app.yank_focus(),
+        KeyResolution::Unbound(chord) if typed_char(*chord) == Some('y') => app.yank_focus(),
+        KeyResolution::Unbound(chord) if typed_char(*chord) == Some('/') => {
+            app.focus = None;
+            app.start_console_search();
+        }
+        KeyResolution::Unbound(chord) if typed_char(*chord) == Some('e') => {
+            if let Some(focus) = app.focus {
+                app.toggle_tool_call(focus.message());
+            }
+        }
+        _ => {
+            app.focus = None;
+            return false;
+        }
+    }
+    true
+}
+
+/// Handles keys while the completion popup is open, returning `false` for
+/// keys that should fall through to normal input handling.
+fn handle_completion_input(app: &mut AppState, resolution: &KeyResolution) -> bool {
+    let Some(popup) = &mut app.completion else {
+        return false;
+    };
+    let KeyResolution::Action(action) = resolution else {
+        return false;
+    };
+
+    match action {
+        Action::MoveUp | Action::CompletePrevious => popup.select_previous(),
+        Action::MoveDown => popup.select_next(),
+        Action::Complete => app.accept_completion(),
+        Action::Cancel => app.completion = None,
+        Action::Submit => {
+            let kind = popup.kind;
+            app.accept_completion();
+            // Keep editing after picking a file, or a command that still
+            // needs an argument; otherwise run the completed command.
+            return kind == CompletionKind::File || app.input.ends_with(' ');
+        }
+        _ => return false,
+    }
+    true
+}
+
+fn handle_reverse_search_input(app: &mut AppState, resolution: &KeyResolution) {
+    let Some(search) = &app.reverse_search else {
+        return;
+    };
+
+    match resolution {
+        KeyResolution::Action(Action::ReverseSearch) => {
+            app.reverse_search_older();
+        }
+        KeyResolution::Action(Action::Quit | Action::Cancel) => {
+            app.cancel_reverse_search();
+        }
+        KeyResolution::Action(Action::Submit | Action::MoveLeft | Action::MoveRight) => {
+            app.accept_reverse_search();
+        }
+        KeyResolution::Action(Action::DeleteBackward) => {
+            let mut query = search.query.clone();
+            query.pop();
+            app.update_reverse_search(query);
+        }
+        KeyResolution::Unbound(chord) => {
+            if let Some(c) = typed_char(*chord) {
+                let query = format!("{}{c}", search.query);
+                app.update_reverse_search(query);
+            }
         }
         _ => {}
     }
+}
+
+pub fn handle_paste(app: &mut AppState, text: &str) -> Result<()> {
+    if let Some(search) = &app.reverse_search {
+        let query = format!("{}{text}", search.query);
+        app.update_reverse_search(query);
+        return Ok(());
+    }
+    app.paste(text);
+    app.refresh_completion();
     Ok(())
 }
 
@@ -56,13 +439,14 @@ mod tests {
         // Test quit key combinations set should_quit flag
         let mut app = AppState::default();
 
-        // Esc key should trigger quit
+        // Esc key should clear the input rather than quit
+        app.set_input("draft");
         let key = KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE);
         handle_input(&mut app, key).unwrap();
-        assert!(app.should_quit);
+        assert!(!app.should_quit);
+        assert_eq!(app.input, "");
 
         // Ctrl+C should trigger quit
-        app.should_quit = false;
         let key = KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL);
         handle_input(&mut app, key).unwrap();
         assert!(app.should_quit);
@@ -85,9 +469,11 @@ mod tests {
     #[test]
     fn test_handle_backspace() {
         // Test backspace removes character and adjusts cursor
-        let mut app = AppState::default();
-        app.input = "hello".to_string();
-        app.cursor_position = 5;
+        let mut app = AppState {
+            input: "hello".to_string(),
+            cursor_position: 5,
+            ..AppState::default()
+        };
 
         let key = KeyEvent::new(KeyCode::Backspace, KeyModifiers::NONE);
         handle_input(&mut app, key).unwrap();
@@ -98,9 +484,11 @@ mod tests {
     #[test]
     fn test_handle_arrow_keys() {
         // Test arrow keys move cursor correctly
-        let mut app = AppState::default();
-        app.input = "hello".to_string();
-        app.cursor_position = 2;
+        let mut app = AppState {
+            input: "hello".to_string(),
+            cursor_position: 2,
+            ..AppState::default()
+        };
 
         // Left arrow moves cursor left
         let key = KeyEvent::new(KeyCode::Left, KeyModifiers::NONE);
@@ -116,8 +504,10 @@ mod tests {
     #[test]
     fn test_handle_enter_key() {
         // Test enter executes command and adds to output history
-        let mut app = AppState::default();
-        app.input = "/echo test".to_string();
+        let mut app = AppState {
+            input: "/echo test".to_string(),
+            ..AppState::default()
+        };
 
         let initial_history_len = app.output_history.len();
 
@@ -150,8 +540,10 @@ mod tests {
     #[test]
     fn test_exit_command_quits_app() {
         // Test that typing "/exit" command quits the application
-        let mut app = AppState::default();
-        app.input = "/exit".to_string();
+        let mut app = AppState {
+            input: "/exit".to_string(),
+            ..AppState::default()
+        };
 
         let key = KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE);
         handle_input(&mut app, key).unwrap();
@@ -165,4 +557,415 @@ mod tests {
                 .any(|line| line.contains("> /exit"))
         );
     }
+
+    #[test]
+    fn test_handle_paste_does_not_submit() {
+        // Test pasted newlines are inserted rather than executing the input
+        let mut app = AppState::default();
+        let initial_history_len = app.output_history.len();
+
+        handle_paste(&mut app, "first line\nsecond line").unwrap();
+        assert_eq!(app.input, "first line\nsecond line");
+        assert_eq!(app.output_history.len(), initial_history_len);
+    }
+
+    #[test]
+    fn test_enter_submits_full_large_paste() {
+        // Test large pastes echo the chip but send the full content
+        let mut app = AppState::default();
+        let trace = vec!["at frame"; 20].join("\n");
+
+        handle_paste(&mut app, &trace).unwrap();
+        assert_eq!(app.input, "[Pasted 20 lines]");
+
+        let key = KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE);
+        handle_input(&mut app, key).unwrap();
+
+        assert!(
+            app.output_history
+                .contains(&"> [Pasted 20 lines]".to_string())
+        );
+        assert!(app.output_history.contains(&format!("You said: {trace}")));
+        assert!(app.pasted_blocks.is_empty());
+    }
+
+    #[test]
+    fn test_history_recall_with_arrow_keys() {
+        // Test Up/Down recall submitted prompts and restore the draft
+        let mut app = AppState::default();
+        for prompt in ["first prompt", "second prompt"] {
+            app.set_input(prompt);
+            handle_input(&mut app, KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)).unwrap();
+        }
+        app.set_input("draft");
+
+        handle_input(&mut app, KeyEvent::new(KeyCode::Up, KeyModifiers::NONE)).unwrap();
+        assert_eq!(app.input, "second prompt");
+        handle_input(&mut app, KeyEvent::new(KeyCode::Up, KeyModifiers::NONE)).unwrap();
+        assert_eq!(app.input, "first prompt");
+        handle_input(&mut app, KeyEvent::new(KeyCode::Down, KeyModifiers::NONE)).unwrap();
+        handle_input(&mut app, KeyEvent::new(KeyCode::Down, KeyModifiers::NONE)).unwrap();
+        assert_eq!(app.input, "draft");
+    }
+
+    #[test]
+    fn test_reverse_search_keys() {
+        // Test Ctrl+R search consumes typing and Esc cancels without quitting
+        let mut app = AppState::default();
+        app.history.push("explain the build").unwrap();
+
+        handle_input(
+            &mut app,
+            KeyEvent::new(KeyCode::Char('r'), KeyModifiers::CONTROL),
+        )
+        .unwrap();
+        for c in "build".chars() {
+            handle_input(
+                &mut app,
+                KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE),
+            )
+            .unwrap();
+        }
+        assert_eq!(app.input, "");
+        handle_input(&mut app, KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)).unwrap();
+        assert_eq!(app.input, "explain the build");
+
+        handle_input(
+            &mut app,
+            KeyEvent::new(KeyCode::Char('r'), KeyModifiers::CONTROL),
+        )
+        .unwrap();
+        handle_input(&mut app, KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE)).unwrap();
+        assert!(!app.should_quit);
+        assert!(app.reverse_search.is_none());
+    }
+
+    #[test]
+    fn test_completion_popup_keys() {
+        // Test typing opens the popup, arrows navigate and Tab accepts
+        let mut app = AppState::default();
+        for c in "/lo".chars() {
+            handle_input(
+                &mut app,
+                KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE),
+            )
+            .unwrap();
+        }
+        assert_eq!(app.completion.as_ref().unwrap().items.len(), 2);
+
+        handle_input(&mut app, KeyEvent::new(KeyCode::Down, KeyModifiers::NONE)).unwrap();
+        handle_input(&mut app, KeyEvent::new(KeyCode::Tab, KeyModifiers::NONE)).unwrap();
+        assert_eq!(app.input, "/logout");
+        assert!(app.completion.is_none());
+    }
+
+    #[test]
+    fn test_completion_popup_esc_and_enter() {
+        // Test Esc closes the popup without quitting and Enter runs the selection
+        let mut app = AppState::default();
+        for c in "/ex".chars() {
+            handle_input(
+                &mut app,
+                KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE),
+            )
+            .unwrap();
+        }
+        handle_input(&mut app, KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE)).unwrap();
+        assert!(app.completion.is_none());
+        assert!(!app.should_quit);
+
+        handle_input(&mut app, KeyEvent::new(KeyCode::Tab, KeyModifiers::NONE)).unwrap();
+        assert_eq!(app.input, "/exit");
+
+        app.set_input("/ex");
+        app.refresh_completion();
+        handle_input(&mut app, KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)).unwrap();
+        assert!(app.should_quit);
+    }
+
+    #[test]
+    fn test_enter_attaches_mentioned_files() {
+        // Test @-mentions pick a file on Enter and unknown ones are sent as text
+        let root = foundry_core::testing::temp_dir();
+        std::fs::write(root.path().join("notes.txt"), "remember this").unwrap();
+
+        let mut app = AppState {
+            workspace_root: root.path().to_path_buf(),
+            workspace_files: vec!["notes.txt".to_string()],
+            ..AppState::default()
+        };
+        for c in "read @no".chars() {
+            handle_input(
+                &mut app,
+                KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE),
+            )
+            .unwrap();
+        }
+        handle_input(&mut app, KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)).unwrap();
+        assert_eq!(app.input, "read @notes.txt");
+
+        handle_input(&mut app, KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)).unwrap();
+        assert!(
+            app.output_history
+                .contains(&"> read @notes.txt".to_string())
+        );
+        assert!(
+            app.output_history
+                .iter()
+                .any(|line| line.contains("<file path=\"notes.txt\">\nremember this"))
+        );
+
+        app.set_input("ask @alice");
+        handle_input(&mut app, KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)).unwrap();
+        assert!(app.input.is_empty());
+        assert!(app.output_history.contains(&"> ask @alice".to_string()));
+        assert!(app.status_text.contains("@alice is not a file"));
+    }
+
+    #[test]
+    fn test_custom_keymap_sequences() {
+        // Test user bindings, including multi-key sequences, drive actions
+        let mut app = AppState::default();
+        app.keymap
+            .apply_toml("[bindings]\n\"ctrl+c\" = \"none\"\n\"ctrl+x ctrl+q\" = \"quit\"")
+            .unwrap();
+
+        handle_input(
+            &mut app,
+            KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL),
+        )
+        .unwrap();
+        assert!(!app.should_quit);
+        assert_eq!(app.input, "");
+
+        handle_input(
+            &mut app,
+            KeyEvent::new(KeyCode::Char('x'), KeyModifiers::CONTROL),
+        )
+        .unwrap();
+        assert!(!app.should_quit);
+        handle_input(
+            &mut app,
+            KeyEvent::new(KeyCode::Char('q'), KeyModifiers::CONTROL),
+        )
+        .unwrap();
+        assert!(app.should_quit);
+    }
+
+    #[test]
+    fn test_broken_sequences_replay_their_keys() {
+        // Test keys held for a sequence that stops matching are typed
+        let mut app = AppState::default();
+        app.keymap
+            .apply_toml("[bindings]\n\"g g\" = \"scroll-up\"")
+            .unwrap();
+
+        for c in "gxgg".chars() {
+            handle_input(
+                &mut app,
+                KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE),
+            )
+            .unwrap();
+        }
+        assert_eq!(app.input, "gx");
+        assert!(app.scroll_back > 0);
+    }
+
+    #[test]
+    fn test_scroll_keys() {
+        // Test scrolling moves away from the bottom and snaps back on submit
+        let mut app = AppState::default();
+        let key = KeyEvent::new(KeyCode::PageUp, KeyModifiers::NONE);
+        handle_input(&mut app, key).unwrap();
+        assert!(app.scroll_back > 0);
+
+        handle_input(
+            &mut app,
+            KeyEvent::new(KeyCode::Char('a'), KeyModifiers::NONE),
+        )
+        .unwrap();
+        handle_input(&mut app, KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)).unwrap();
+        assert_eq!(app.scroll_back, 0);
+    }
+
+    #[test]
+    fn test_vim_mode_editing() {
+        // Test vi mode switches modes on Esc/i and edits with operators
+        let mut app = AppState {
+            vim: Some(crate::vim::Vim::default()),
+            ..AppState::default()
+        };
+        for c in "hello big world".chars() {
+            handle_input(
+                &mut app,
+                KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE),
+            )
+            .unwrap();
+        }
+        handle_input(&mut app, KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE)).unwrap();
+        assert_eq!(app.vim.as_ref().unwrap().mode, VimMode::Normal);
+        assert_eq!(app.input, "hello big world");
+
+        for c in "0wdw".chars() {
+            handle_input(
+                &mut app,
+                KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE),
+            )
+            .unwrap();
+        }
+        assert_eq!(app.input, "hello world");
+
+        for c in "Ax".chars() {
+            handle_input(
+                &mut app,
+                KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE),
+            )
+            .unwrap();
+        }
+        assert_eq!(app.input, "hello worldx");
+
+        handle_input(&mut app, KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)).unwrap();
+        assert!(
+            app.output_history
+                .contains(&"You said: hello worldx".to_string())
+        );
+        assert_eq!(app.vim.as_ref().unwrap().mode, VimMode::Insert);
+    }
+
+    #[test]
+    fn test_open_editor_requests_hand_off() {
+        // Test Ctrl+G asks the run loop to open the external editor
+        let mut app = AppState::default();
+        let key = KeyEvent::new(KeyCode::Char('g'), KeyModifiers::CONTROL);
+        handle_input(&mut app, key).unwrap();
+        assert!(app.editor_requested);
+        assert_eq!(app.input, "");
+
+        // Test Ctrl+Z asks the run loop to suspend the process
+        let key = KeyEvent::new(KeyCode::Char('z'), KeyModifiers::CONTROL);
+        handle_input(&mut app, key).unwrap();
+        assert!(app.suspend_requested);
+    }
+
+    #[test]
+    fn test_focus_mode_yanks_message() {
+        // Test Alt+Up focuses output, arrows move and y queues a copy
+        let mut app = AppState {
+            output_history: vec!["first".to_string(), "second".to_string()],
+            ..AppState::default()
+        };
+        handle_input(&mut app, KeyEvent::new(KeyCode::Up, KeyModifiers::ALT)).unwrap();
+        handle_input(&mut app, KeyEvent::new(KeyCode::Up, KeyModifiers::NONE)).unwrap();
+        handle_input(
+            &mut app,
+            KeyEvent::new(KeyCode::Char('y'), KeyModifiers::NONE),
+        )
+        .unwrap();
+        assert_eq!(app.pending_copy.as_deref(), Some("first"));
+        assert_eq!(app.focus, None);
+        assert_eq!(app.input, "");
+
+        // Test other keys leave focus mode and are typed as usual
+        handle_input(&mut app, KeyEvent::new(KeyCode::Up, KeyModifiers::ALT)).unwrap();
+        handle_input(
+            &mut app,
+            KeyEvent::new(KeyCode::Char('x'), KeyModifiers::NONE),
+        )
+        .unwrap();
+        assert_eq!(app.focus, None);
+        assert_eq!(app.input, "x");
+    }
+
+    #[test]
+    fn test_diff_viewer_keys() {
+        // Test the diff viewer navigates, decides hunks and closes on Esc
+        use crate::diff::FileDiff;
+        let mut app = AppState::default();
+        app.propose_edits(vec![
+            FileDiff::new("a.txt", "a\n", "b\n"),
+            FileDiff::new("b.txt", "c\n", "d\n"),
+        ]);
+        let press = |app: &mut AppState, c: char| {
+            handle_input(app, KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE)).unwrap();
+        };
+
+        press(&mut app, 'r');
+        press(&mut app, 'a');
+        press(&mut app, 's');
+        press(&mut app, 'x');
+        let view = app.diff_view.as_ref().unwrap();
+        assert_eq!(view.files[0].decisions, vec![HunkDecision::Rejected]);
+        assert_eq!(view.files[1].decisions, vec![HunkDecision::Accepted]);
+        assert_eq!(view.layout, crate::diff::DiffLayout::SideBySide);
+        assert_eq!(app.input, "");
+
+        handle_input(&mut app, KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE)).unwrap();
+        assert!(app.diff_view.is_none());
+        assert!(app.session_changes.is_empty());
+    }
+
+    #[test]
+    fn test_tool_call_keys() {
+        // Test e expands the focused tool call and Ctrl+O toggles verbose mode
+        let mut app = AppState::default();
+        let id = app.start_tool_call("bash", "ls");
+        app.finish_tool_call(id, true, &"file\n".repeat(20));
+        let entry = app.tool_calls[id].entry;
+
+        handle_input(&mut app, KeyEvent::new(KeyCode::Up, KeyModifiers::ALT)).unwrap();
+        handle_input(
+            &mut app,
+            KeyEvent::new(KeyCode::Char('e'), KeyModifiers::NONE),
+        )
+        .unwrap();
+        assert!(app.tool_calls[id].expanded);
+        assert_eq!(app.output_history[entry].lines().count(), 21);
+        assert!(app.focus.is_some());
+
+        handle_input(&mut app, KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE)).unwrap();
+        handle_input(
+            &mut app,
+            KeyEvent::new(KeyCode::Char('o'), KeyModifiers::CONTROL),
+        )
+        .unwrap();
+        assert!(app.verbose_transcript);
+        assert!(app.status_text.contains("verbose"));
+    }
+
+    #[test]
+    fn test_console_search_keys() {
+        // Test Ctrl+F searches as you type, Enter confirms and n/N navigate
+        let mut app = AppState {
+            output_history: vec![
+                "alpha".to_string(),
+                "beta".to_string(),
+                "alphabet".to_string(),
+            ],
+            ..AppState::default()
+        };
+        let press = |app: &mut AppState, code: KeyCode, modifiers: KeyModifiers| {
+            handle_input(app, KeyEvent::new(code, modifiers)).unwrap();
+        };
+
+        press(&mut app, KeyCode::Char('f'), KeyModifiers::CONTROL);
+        for c in "alp".chars() {
+            press(&mut app, KeyCode::Char(c), KeyModifiers::NONE);
+        }
+        press(&mut app, KeyCode::Enter, KeyModifiers::NONE);
+        let search = app.console_search.as_ref().unwrap();
+        assert_eq!(search.query, "alp");
+        assert!(!search.editing);
+        assert_eq!(search.current, Some(1));
+        assert_eq!(app.input, "");
+
+        press(&mut app, KeyCode::Char('n'), KeyModifiers::NONE);
+        assert_eq!(app.console_search.as_ref().unwrap().current, Some(0));
+        press(&mut app, KeyCode::Char('N'), KeyModifiers::SHIFT);
+        assert_eq!(app.console_search.as_ref().unwrap().current, Some(1));
+
+        // Test typing anything else closes the search and edits the input
+        press(&mut app, KeyCode::Char('x'), KeyModifiers::NONE);
+        assert!(app.console_search.is_none());
+        assert_eq!(app.input, "x");
+    }
 }
@@ -0,0 +1,692 @@
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use foundry_core::agent::{Agent, EchoModel, Model};
+use foundry_core::instructions::Instructions;
+use foundry_core::mentions::attach_file_references;
+use foundry_core::paths::{display_path, find_project_root};
+use foundry_core::permissions::PermissionPolicy;
+use foundry_core::protocol::{Event, InputMessage, SCHEMA_VERSION, TurnResult, new_session_id};
+use foundry_core::sessions::SessionStore;
+use std::io::{self, BufRead, IsTerminal, Read, Write};
+use std::path::Path;
+use std::time::Instant;
+
+/// The turn ran and its answer was printed.
+pub const EXIT_SUCCESS: u8 = 0;
+/// The turn failed, e.g. a referenced file could not be read.
+pub const EXIT_FAILURE: u8 = 1;
+/// The run was misused, e.g. no prompt was given.
+pub const EXIT_USAGE: u8 = 2;
+
+/// How a headless run reports its answer on stdout.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// The answer as plain text, streamed as it arrives.
+    #[default]
+    Text,
+    /// A single `TurnResult` object once the turn is done.
+    Json,
+    /// One `Event` per line as things happen, ending with the result.
+    StreamJson,
+}
+
+/// How a headless run receives its prompts.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum InputFormat {
+    /// One prompt from the command line and piped stdin.
+    #[default]
+    Text,
+    /// `InputMessage`s on stdin, one per line, for as many turns as the
+    /// driving program sends.
+    StreamJson,
+}
+
+/// A non-interactive run, as started by `foundry -p`.
+#[derive(Debug, Clone, Default)]
+pub struct PrintRequest {
+    pub prompt: String,
+    /// Context piped in on stdin, if any.
+    pub stdin: Option<String>,
+    pub permissions: PermissionPolicy,
+    pub format: OutputFormat,
+    pub input_format: InputFormat,
+    /// The configured deployment, which `-c deployment=<name>` overrides.
+    pub model: Option<String>,
+    /// Deployments a stream-json client may switch to with `set_model`.
+    pub deployments: Vec<String>,
+    /// `FOUNDRY.md` files assembled into the system prompt.
+    pub instructions: Instructions,
+}
+
+impl PrintRequest {
+    /// The message sent to the model: the prompt followed by the piped
+    /// context, or just the piped text when no prompt was given.
+    pub fn message(&self) -> String {
+        let prompt = self.prompt.trim();
+        let stdin = self.stdin.as_deref().map(str::trim_end).unwrap_or_default();
+        match (prompt.is_empty(), stdin.is_empty()) {
+            (_, true) => prompt.to_string(),
+            (true, false) => stdin.to_string(),
+            (false, false) => format!("{prompt}\n\n<stdin>\n{stdin}\n</stdin>"),
+        }
+    }
+}
+
+/// Reads everything piped to stdin, or nothing when stdin is a terminal.
+pub fn read_piped_stdin() -> Result<Option<String>> {
+    let mut stdin = io::stdin();
+    if stdin.is_terminal() {
+        return Ok(None);
+    }
+    let mut input = String::new();
+    stdin
+        .read_to_string(&mut input)
+        .context("Failed to read stdin")?;
+    Ok(Some(input).filter(|input| !input.trim().is_empty()))
+}
+
+/// Runs the request in the current directory, printing answers to stdout
+/// and problems to stderr. Returns the process exit status.
+pub fn run_print(request: &PrintRequest) -> Result<u8> {
+    let cwd = std::env::current_dir().context("Failed to determine current directory")?;
+    let root = find_project_root(&cwd);
+    let store = SessionStore::for_project(&cwd);
+    let mut out = io::stdout().lock();
+    let mut err = io::stderr().lock();
+    match request.input_format {
+        InputFormat::Text => print_turn(request, &root, store, &mut out, &mut err),
+        InputFormat::StreamJson => run_stream(
+            request,
+            Box::new(EchoModel),
+            &root,
+            store,
+            io::stdin().lock(),
+            &mut out,
+            &mut err,
+        ),
+    }
+}
+
+/// Why a message cannot be sent to the model, if it cannot.
+fn check_message(message: &str) -> Option<&'static str> {
+    if message.trim().is_empty() {
+        Some("No prompt given: pass one to -p or pipe it on stdin")
+    } else if message.trim_start().starts_with('/') {
+        Some("Slash commands are only available in the interactive console")
+    } else {
+        None
+    }
+}
+
+fn print_turn(
+    request: &PrintRequest,
+    root: &Path,
+    store: Option<SessionStore>,
+    out: &mut impl Write,
+    err: &mut impl Write,
+) -> Result<u8> {
+    let message = request.message();
+    if let Some(problem) = check_message(&message) {
+        writeln!(err, "{problem}")?;
+        return Ok(EXIT_USAGE);
+    }
+
+    let mut session = Session::new(request, Box::new(EchoModel), root, store, out, err);
+    session.announce()?;
+    let failed = session.run_turn(&message)?;
+    Ok(if failed { EXIT_FAILURE } else { EXIT_SUCCESS })
+}
+
+/// Serves turns for the `InputMessage`s read from `input` until it ends.
+/// Bad messages are reported as error events rather than ending the run,
+/// but make it exit with a failure. A prompt given on the command line runs
+/// as the first turn.
+fn run_stream(
+    request: &PrintRequest,
+    model: Box<dyn Model>,
+    root: &Path,
+    store: Option<SessionStore>,
+    input: impl BufRead,
+    out: &mut impl Write,
+    err: &mut impl Write,
+) -> Result<u8> {
+    if request.format != OutputFormat::StreamJson {
+        writeln!(
+            err,
+            "--input-format stream-json requires --output-format stream-json"
+        )?;
+        return Ok(EXIT_USAGE);
+    }
+
+    let mut session = Session::new(request, model, root, store, out, err);
+    session.announce()?;
+    if !request.prompt.trim().is_empty() {
+        session.run_turn(&request.prompt)?;
+    }
+    for line in input.lines() {
+        let line = line.context("Failed to read stdin")?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str::<InputMessage>(&line) {
+            Ok(message) => session.handle(message)?,
+            Err(error) => session.emit(Event::Error {
+                message: format!("Invalid input message: {error}"),
+            })?,
+        }
+    }
+    if let Some(pending) = session.agent.pending() {
+        let message = format!(
+            "Input ended while permission request '{}' was pending",
+            pending.id
+        );
+        session.emit(Event::Error { message })?;
+        session.agent.interrupt();
+        session.continue_turn()?;
+    }
+    Ok(if session.failed {
+        EXIT_FAILURE
+    } else {
+        EXIT_SUCCESS
+    })
+}
+
+/// A headless session: writes the events of each turn in the requested
+/// format while collecting them into the turn's result.
+struct Session<'a, O: Write, E: Write> {
+    format: OutputFormat,
+    agent: Agent,
+    root: &'a Path,
+    out: &'a mut O,
+    err: &'a mut E,
+    /// Where the transcript is recorded, until recording fails.
+    store: Option<SessionStore>,
+    session_id: String,
+    model: Option<String>,
+    deployments: Vec<String>,
+    instructions: Vec<String>,
+    result: TurnResult,
+    started: Instant,
+    /// Whether any error was reported during the session.
+    failed: bool,
+}
+
+impl<'a, O: Write, E: Write> Session<'a, O, E> {
+    fn new(
+        request: &PrintRequest,
+        model: Box<dyn Model>,
+        root: &'a Path,
+        store: Option<SessionStore>,
+        out: &'a mut O,
+        err: &'a mut E,
+    ) -> Self {
+        let session_id = new_session_id();
+        let mut agent = Agent::new(model, root, request.permissions);
+        // Nobody is around to answer a permission request in text mode.
+        agent.unattended = request.input_format == InputFormat::Text;
+        agent.set_system_prompt(request.instructions.system_prompt());
+        Self {
+            format: request.format,
+            agent,
+            root,
+            out,
+            err,
+            store,
+            result: TurnResult::new(session_id.clone()),
+            started: Instant::now(),
+            failed: false,
+            session_id,
+            model: request.model.clone(),
+            deployments: request.deployments.clone(),
+            instructions: request
+                .instructions
+                .files
+                .iter()
+                .map(|file| display_path(&file.path))
+                .collect(),
+        }
+    }
+
+    fn announce(&mut self) -> Result<()> {
+        self.emit(Event::System {
+            schema_version: SCHEMA_VERSION,
+            session_id: self.session_id.clone(),
+            cwd: display_path(self.root),
+            permission_mode: self.agent.policy.to_string(),
+            model: self.model.clone(),
+            instructions: self.instructions.clone(),
+        })
+    }
+
+    fn handle(&mut self, message: InputMessage) -> Result<()> {
+        match message {
+            InputMessage::UserMessage { text } => match self.agent.pending() {
+                Some(pending) => {
+                    let message = format!(
+                        "Permission request '{}' is pending; answer it or interrupt the turn",
+                        pending.id
+                    );
+                    self.emit(Event::Error { message })?;
+                }
+                None => {
+                    self.run_turn(&text)?;
+                }
+            },
+            InputMessage::SetModel { model } => {
+                if self.deployments.contains(&model) {
+                    self.agent.set_model(&model);
+                    self.model = Some(model);
+                    self.announce()?;
+                } else {
+                    let message = match self.deployments.as_slice() {
+                        [] => format!("Unknown deployment '{model}'; none are configured"),
+                        known => format!(
+                            "Unknown deployment '{model}', expected one of: {}",
+                            known.join(", ")
+                        ),
+                    };
+                    self.emit(Event::Error { message })?;
+                }
+            }
+            // Only a turn waiting for permission outlives the message that
+            // started it, so that is the one there is to interrupt.
+            InputMessage::Interrupt => {
+                if self.agent.interrupt() {
+                    self.continue_turn()?;
+                }
+            }
+            InputMessage::PermissionResponse { id, allow } => {
+                match self.agent.respond(&id, allow) {
+                    Ok(()) => self.continue_turn()?,
+                    Err(error) => self.emit(Event::Error {
+                        message: format!("{error:#}"),
+                    })?,
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Runs one turn and prints its result, returning whether it failed. A
+    /// turn that stops for permission is finished by `continue_turn` once
+    /// the request is answered.
+    fn run_turn(&mut self, message: &str) -> Result<bool> {
+        self.result = TurnResult::new(self.session_id.clone());
+        self.started = Instant::now();
+        self.emit(Event::UserMessage {
+            text: message.to_string(),
+        })?;
+        match check_message(message) {
+            Some(problem) => self.emit(Event::Error {
+                message: problem.to_string(),
+            })?,
+            None => {
+                let attached = attach_file_references(message, self.root);
+                if let Some(warning) = attached.warning() {
+                    writeln!(self.err, "Warning: {warning}")?;
+                }
+                self.agent.submit(&attached.text)?;
+            }
+        }
+        self.continue_turn()?;
+        Ok(self.result.is_error)
+    }
+
+    /// Emits the agent's events until the turn ends or waits for permission.
+    fn continue_turn(&mut self) -> Result<()> {
+        while let Some(event) = self.agent.step() {
+            self.emit(event)?;
+        }
+        if self.agent.pending().is_none() {
+            self.finish_turn()?;
+        }
+        Ok(())
+    }
+
+    fn emit(&mut self, event: Event) -> Result<()> {
+        self.failed |= matches!(event, Event::Error { .. });
+        self.result.record(&event);
+        if let Some(store) = &self.store
+            && let Err(error) = store.append(&self.session_id, &event)
+        {
+            writeln!(self.err, "Warning: session not recorded: {error:#}")?;
+            self.store = None;
+        }
+        match (self.format, &event) {
+            (OutputFormat::Text, Event::MessageDelta { text }) => write!(self.out, "{text}")?,
+            (OutputFormat::Text, Event::Error { message }) => {
+                writeln!(self.err, "Error: {message}")?;
+            }
+            (OutputFormat::StreamJson, _) => {
+                writeln!(self.out, "{}", serde_json::to_string(&event)?)?;
+                self.out.flush()?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Prints whatever closes the turn's output.
+    fn finish_turn(&mut self) -> Result<()> {
+        self.result.duration_ms =
+            u64::try_from(self.started.elapsed().as_millis()).unwrap_or(u64::MAX);
+        match self.format {
+            OutputFormat::Text if !self.result.answer.is_empty() => writeln!(self.out)?,
+            OutputFormat::Text => {}
+            OutputFormat::Json => writeln!(self.out, "{}", serde_json::to_string(&self.result)?)?,
+            OutputFormat::StreamJson => {
+                let result = self.result.clone();
+                self.emit(Event::Result(result))?;
+            }
+        }
+        self.out.flush().context("Failed to write the answer")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use foundry_core::agent::ModelStep;
+    use foundry_core::testing::{ScriptedModel, temp_dir};
+    use std::fs;
+
+    fn run(request: &PrintRequest) -> (u8, String, String) {
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+        let status = print_turn(request, Path::new("."), None, &mut out, &mut err).unwrap();
+        (
+            status,
+            String::from_utf8(out).unwrap(),
+            String::from_utf8(err).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_message_includes_piped_context() {
+        // Test piped stdin is attached after the prompt, or used on its own
+        let mut request = PrintRequest {
+            prompt: " review this ".to_string(),
+            stdin: Some("+ added line\n".to_string()),
+            ..PrintRequest::default()
+        };
+        assert_eq!(
+            request.message(),
+            "review this\n\n<stdin>\n+ added line\n</stdin>"
+        );
+
+        request.prompt.clear();
+        assert_eq!(request.message(), "+ added line");
+        request.stdin = None;
+        assert_eq!(request.message(), "");
+    }
+
+    #[test]
+    fn test_print_turn_exit_statuses() {
+        // Test answers go to stdout and misuse or failures set the exit status
+        let (status, out, _) = run(&PrintRequest {
+            prompt: "hello".to_string(),
+            ..PrintRequest::default()
+        });
+        assert_eq!(status, EXIT_SUCCESS);
+        assert!(out.contains("hello"));
+
+        let (status, out, err) = run(&PrintRequest::default());
+        assert_eq!(status, EXIT_USAGE);
+        assert!(out.is_empty());
+        assert!(err.contains("No prompt given"));
+
+        let (status, _, _) = run(&PrintRequest {
+            prompt: "/help".to_string(),
+            ..PrintRequest::default()
+        });
+        assert_eq!(status, EXIT_USAGE);
+
+        let (status, out, err) = run(&PrintRequest {
+            prompt: "explain @does/not/exist.rs".to_string(),
+            ..PrintRequest::default()
+        });
+        assert_eq!(status, EXIT_SUCCESS);
+        assert!(out.contains("explain @does/not/exist.rs"));
+        assert!(err.contains("Warning: Not attached: @does/not/exist.rs"));
+    }
+
+    #[test]
+    fn test_unattended_tools_need_policy_approval() {
+        // Test tools the policy would ask about are denied without a user
+        let dir = temp_dir();
+        let write = || ModelStep::ToolCall {
+            name: "write_file".to_string(),
+            input: r#"{"path":"out.txt","content":"x"}"#.to_string(),
+        };
+        let mut request = PrintRequest::default();
+        for (permissions, written) in [
+            (PermissionPolicy::Default, false),
+            (PermissionPolicy::AcceptEdits, true),
+        ] {
+            request.permissions = permissions;
+            let (mut out, mut err) = (Vec::new(), Vec::new());
+            let model = Box::new(ScriptedModel::new([write()]));
+            let mut session = Session::new(&request, model, dir.path(), None, &mut out, &mut err);
+            session.run_turn("write it").unwrap();
+            assert!(!session.agent.is_running());
+            assert_eq!(dir.path().join("out.txt").exists(), written);
+        }
+    }
+
+    #[test]
+    fn test_instructions_are_the_system_prompt() {
+        // Test FOUNDRY.md files reach the model with every turn
+        let dir = temp_dir();
+        fs::write(dir.path().join("FOUNDRY.md"), "Run cargo test.").unwrap();
+        let request = PrintRequest {
+            instructions: Instructions::load_from(
+                None,
+                dir.path().to_path_buf(),
+                dir.path().to_path_buf(),
+            ),
+            ..PrintRequest::default()
+        };
+        let (mut out, mut err) = (Vec::new(), Vec::new());
+        let session = Session::new(
+            &request,
+            Box::new(EchoModel),
+            dir.path(),
+            None,
+            &mut out,
+            &mut err,
+        );
+        assert!(
+            session
+                .agent
+                .conversation
+                .system_prompt
+                .ends_with("Run cargo test.")
+        );
+    }
+
+    #[test]
+    fn test_json_output_formats() {
+        // Test json prints one result and stream-json prints events ending in it
+        let request = PrintRequest {
+            prompt: "hello".to_string(),
+            format: OutputFormat::Json,
+            ..PrintRequest::default()
+        };
+        let (status, out, _) = run(&request);
+        assert_eq!(status, EXIT_SUCCESS);
+        let result = serde_json::from_str::<TurnResult>(&out).unwrap();
+        assert_eq!(result.schema_version, SCHEMA_VERSION);
+        assert!(result.answer.contains("hello"));
+
+        let (status, out, err) = run(&PrintRequest {
+            prompt: "explain @does/not/exist.rs".to_string(),
+            format: OutputFormat::StreamJson,
+            ..PrintRequest::default()
+        });
+        assert_eq!(status, EXIT_SUCCESS);
+        assert!(err.contains("@does/not/exist.rs"));
+        let events = out
+            .lines()
+            .map(|line| serde_json::from_str::<Event>(line).unwrap())
+            .collect::<Vec<_>>();
+        assert!(matches!(events[0], Event::System { .. }));
+        assert!(matches!(events[1], Event::UserMessage { .. }));
+        assert!(matches!(events[2], Event::MessageDelta { .. }));
+        let Some(Event::Result(result)) = events.last() else {
+            panic!("stream should end with a result");
+        };
+        assert!(!result.is_error);
+        assert!(result.errors.is_empty());
+    }
+
+    #[test]
+    fn test_stream_input_serves_many_turns() {
+        // Test each user message gets a result and bad input becomes error events
+        let input = [
+            r#"{"type":"user_message","text":"first"}"#,
+            "not json",
+            r#"{"type":"set_model","model":"gpt-4.1"}"#,
+            r#"{"type":"set_model","model":"gpt-5"}"#,
+            r#"{"type":"permission_response","id":"p9","allow":true}"#,
+            r#"{"type":"user_message","text":"second"}"#,
+        ]
+        .join("\n");
+        let request = PrintRequest {
+            format: OutputFormat::StreamJson,
+            input_format: InputFormat::StreamJson,
+            deployments: vec!["gpt-4.1".to_string()],
+            ..PrintRequest::default()
+        };
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+        let status = run_stream(
+            &request,
+            Box::new(EchoModel),
+            Path::new("."),
+            None,
+            input.as_bytes(),
+            &mut out,
+            &mut err,
+        )
+        .unwrap();
+        assert_eq!(status, EXIT_FAILURE);
+
+        let events = String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str::<Event>(line).unwrap())
+            .collect::<Vec<_>>();
+        let answers = events
+            .iter()
+            .filter_map(|event| match event {
+                Event::Result(result) => Some(result.answer.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(answers, vec!["You said: first", "You said: second"]);
+        let errors = events
+            .iter()
+            .filter(|event| matches!(event, Event::Error { .. }))
+            .count();
+        assert_eq!(errors, 3);
+        assert!(events.contains(&Event::Error {
+            message: "Unknown deployment 'gpt-5', expected one of: gpt-4.1".to_string()
+        }));
+        assert!(events.contains(&Event::System {
+            schema_version: SCHEMA_VERSION,
+            session_id: session_id_of(&events),
+            cwd: display_path(Path::new(".")),
+            permission_mode: "default".to_string(),
+            model: Some("gpt-4.1".to_string()),
+            instructions: Vec::new(),
+        }));
+    }
+
+    #[test]
+    fn test_stream_input_answers_permission_requests() {
+        // Test a waiting turn resumes on a response and input ending interrupts it
+        let dir = temp_dir();
+        let write = |path: &str| ModelStep::ToolCall {
+            name: "write_file".to_string(),
+            input: serde_json::json!({ "path": path, "content": "x" }).to_string(),
+        };
+        let request = PrintRequest {
+            format: OutputFormat::StreamJson,
+            input_format: InputFormat::StreamJson,
+            ..PrintRequest::default()
+        };
+        let input = [
+            r#"{"type":"user_message","text":"first"}"#,
+            r#"{"type":"user_message","text":"too soon"}"#,
+            r#"{"type":"permission_response","id":"tool-1","allow":true}"#,
+            r#"{"type":"user_message","text":"second"}"#,
+        ]
+        .join("\n");
+        let answer = ModelStep::Answer {
+            text: "done".to_string(),
+            usage: Default::default(),
+        };
+        let model = ScriptedModel::new([write("a.txt"), answer, write("b.txt")]);
+        let mut out = Vec::new();
+        let status = run_stream(
+            &request,
+            Box::new(model),
+            dir.path(),
+            None,
+            input.as_bytes(),
+            &mut out,
+            &mut Vec::new(),
+        )
+        .unwrap();
+        assert_eq!(status, EXIT_FAILURE);
+        assert!(dir.path().join("a.txt").exists());
+        assert!(!dir.path().join("b.txt").exists());
+
+        let events = String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str::<Event>(line).unwrap())
+            .collect::<Vec<_>>();
+        let results = events
+            .iter()
+            .filter_map(|event| match event {
+                Event::Result(result) => Some(result),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].answer, "done");
+        assert!(results[0].errors[0].contains("is pending"));
+        assert!(results[1].errors[0].contains("Input ended"));
+        assert!(results[1].is_error);
+    }
+
+    fn session_id_of(events: &[Event]) -> String {
+        match &events[0] {
+            Event::System { session_id, .. } => session_id.clone(),
+            event => panic!("expected a system event first, got {event:?}"),
+        }
+    }
+
+    #[test]
+    fn test_stream_input_requires_stream_output() {
+        // Test stream-json input cannot be combined with text output
+        let request = PrintRequest {
+            input_format: InputFormat::StreamJson,
+            ..PrintRequest::default()
+        };
+        let mut err = Vec::new();
+        let status = run_stream(
+            &request,
+            Box::new(EchoModel),
+            Path::new("."),
+            None,
+            &b""[..],
+            &mut Vec::new(),
+            &mut err,
+        )
+        .unwrap();
+        assert_eq!(status, EXIT_USAGE);
+        assert!(String::from_utf8(err).unwrap().contains("--output-format"));
+    }
+}
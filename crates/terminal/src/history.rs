@@ -0,0 +1,315 @@
+use anyhow::{Context, Result};
+use std::collections::HashSet;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use foundry_core::paths::{find_project_root, project_key, state_dir};
+
+/// Maximum number of entries kept in a history file.
+pub const DEFAULT_HISTORY_CAPACITY: usize = 1000;
+
+/// Submitted prompts for the current project, oldest first.
+///
+/// Entries are stored one per line with backslashes and newlines escaped so
+/// that multi-line prompts survive a round trip through the file.
+#[derive(Debug, Clone)]
+pub struct History {
+    entries: Vec<String>,
+    capacity: usize,
+    path: Option<PathBuf>,
+    position: Option<usize>,
+    draft: String,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self {
+            entries: Vec::new(),
+            capacity: DEFAULT_HISTORY_CAPACITY,
+            path: None,
+            position: None,
+            draft: String::new(),
+        }
+    }
+}
+
+/// State of an in-progress Ctrl+R reverse search.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReverseSearch {
+    pub query: String,
+    pub match_index: Option<usize>,
+    pub original_input: String,
+}
+
+impl History {
+    /// Loads the history file for the project containing `cwd`.
+    pub fn for_project(cwd: &Path) -> Result<Self> {
+        let Some(dir) = state_dir() else {
+            return Ok(Self::default());
+        };
+        let project_root = find_project_root(cwd);
+        let path = dir.join("history").join(project_key(&project_root));
+        Self::load(path)
+    }
+
+    pub fn load(path: PathBuf) -> Result<Self> {
+        let mut history = Self {
+            entries: read_entries(&path)?,
+            path: Some(path),
+            ..Self::default()
+        };
+        history.truncate();
+        Ok(history)
+    }
+
+    pub fn entries(&self) -> &[String] {
+        &self.entries
+    }
+
+    /// Records a submitted prompt, moving duplicates to the most recent slot.
+    pub fn push(&mut self, entry: &str) -> Result<()> {
+        self.reset_navigation();
+        if entry.trim().is_empty() {
+            return Ok(());
+        }
+
+        self.entries.retain(|existing| existing != entry);
+        self.entries.push(entry.to_string());
+        self.truncate();
+        self.append(entry)
+    }
+
+    /// Steps back to an older entry, remembering `current` as the draft when
+    /// navigation starts so that stepping forward past the newest restores it.
+    pub fn older(&mut self, current: &str) -> Option<&str> {
+        let next_position = match self.position {
+            None if self.entries.is_empty() => return None,
+            None => {
+                self.draft = current.to_string();
+                self.entries.len() - 1
+            }
+            Some(0) => return None,
+            Some(position) => position - 1,
+        };
+        self.position = Some(next_position);
+        Some(&self.entries[next_position])
+    }
+
+    /// Steps forward to a newer entry, returning the saved draft at the end.
+    pub fn newer(&mut self) -> Option<String> {
+        let position = self.position?;
+        if position + 1 < self.entries.len() {
+            self.position = Some(position + 1);
+            Some(self.entries[position + 1].clone())
+        } else {
+            self.position = None;
+            Some(std::mem::take(&mut self.draft))
+        }
+    }
+
+    pub fn reset_navigation(&mut self) {
+        self.position = None;
+        self.draft.clear();
+    }
+
+    /// Finds the newest entry containing `query` strictly older than `before`
+    /// (or searching from the newest entry when `before` is `None`).
+    pub fn search(&self, query: &str, before: Option<usize>) -> Option<usize> {
+        let end = before.unwrap_or(self.entries.len());
+        self.entries[..end.min(self.entries.len())]
+            .iter()
+            .rposition(|entry| entry.contains(query))
+    }
+
+    pub fn get(&self, index: usize) -> Option<&str> {
+        self.entries.get(index).map(String::as_str)
+    }
+
+    fn truncate(&mut self) {
+        if self.entries.len() > self.capacity {
+            let excess = self.entries.len() - self.capacity;
+            self.entries.drain(..excess);
+        }
+    }
+
+    /// Appends `entry` to the file, so sessions running side by side never
+    /// overwrite each other's entries. Duplicates are dropped when the file
+    /// is read, and the file is compacted once it holds twice the capacity.
+    fn append(&self, entry: &str) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Failed to open history file {}", path.display()))?;
+        file.write_all(format!("{}\n", escape_entry(entry)).as_bytes())
+            .with_context(|| format!("Failed to write history file {}", path.display()))?;
+        drop(file);
+
+        let lines = fs::read_to_string(path).map_or(0, |contents| contents.lines().count());
+        if lines > self.capacity * 2 {
+            self.compact(path)?;
+        }
+        Ok(())
+    }
+
+    /// Rewrites the file with the deduplicated, capped entries on disk,
+    /// which include those appended by other sessions.
+    fn compact(&self, path: &Path) -> Result<()> {
+        let mut entries = read_entries(path)?;
+        let excess = entries.len().saturating_sub(self.capacity);
+        entries.drain(..excess);
+        let contents = entries
+            .iter()
+            .map(|entry| format!("{}\n", escape_entry(entry)))
+            .collect::<String>();
+        fs::write(path, contents)
+            .with_context(|| format!("Failed to write history file {}", path.display()))
+    }
+}
+
+/// Reads the entries in a history file, keeping only the newest copy of
+/// each. A missing file has no entries.
+fn read_entries(path: &Path) -> Result<Vec<String>> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => {
+            return Err(err)
+                .with_context(|| format!("Failed to read history file {}", path.display()));
+        }
+    };
+
+    let mut seen = HashSet::new();
+    let mut entries = contents
+        .lines()
+        .rev()
+        .map(unescape_entry)
+        .filter(|entry| seen.insert(entry.clone()))
+        .collect::<Vec<_>>();
+    entries.reverse();
+    Ok(entries)
+}
+
+fn escape_entry(entry: &str) -> String {
+    entry.replace('\\', "\\\\").replace('\n', "\\n")
+}
+
+fn unescape_entry(line: &str) -> String {
+    let mut entry = String::with_capacity(line.len());
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match (c, chars.clone().next()) {
+            ('\\', Some('n')) => {
+                entry.push('\n');
+                chars.next();
+            }
+            ('\\', Some('\\')) => {
+                entry.push('\\');
+                chars.next();
+            }
+            _ => entry.push(c),
+        }
+    }
+    entry
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use foundry_core::testing::temp_dir;
+
+    fn history_with(entries: &[&str]) -> History {
+        let mut history = History::default();
+        for entry in entries {
+            history.push(entry).unwrap();
+        }
+        history
+    }
+
+    #[test]
+    fn test_history_deduplicates_and_caps() {
+        // Test duplicates move to the newest slot and old entries are dropped
+        let mut history = history_with(&["one", "two", "one"]);
+        assert_eq!(history.entries(), ["two", "one"]);
+
+        history.capacity = 2;
+        history.push("three").unwrap();
+        assert_eq!(history.entries(), ["one", "three"]);
+    }
+
+    #[test]
+    fn test_history_navigation_restores_draft() {
+        // Test Up/Down navigation walks entries and returns to the draft
+        let mut history = history_with(&["first", "second"]);
+
+        assert_eq!(history.older("draft"), Some("second"));
+        assert_eq!(history.older("second"), Some("first"));
+        assert_eq!(history.older("first"), None);
+        assert_eq!(history.newer(), Some("second".to_string()));
+        assert_eq!(history.newer(), Some("draft".to_string()));
+        assert_eq!(history.newer(), None);
+    }
+
+    #[test]
+    fn test_history_search_finds_older_matches() {
+        // Test reverse search walks from newest to oldest matching entry
+        let history = history_with(&["cargo build", "git status", "cargo test"]);
+
+        assert_eq!(history.search("cargo", None), Some(2));
+        assert_eq!(history.search("cargo", Some(2)), Some(0));
+        assert_eq!(history.search("cargo", Some(0)), None);
+        assert_eq!(history.search("missing", None), None);
+    }
+
+    #[test]
+    fn test_history_round_trips_multiline_entries() {
+        // Test escaped entries are persisted and reloaded intact
+        let dir = temp_dir();
+        let path = dir.path().join("history");
+        let mut history = History::load(path.clone()).unwrap();
+        history.push("line one\nline \\two").unwrap();
+
+        let reloaded = History::load(path).unwrap();
+        assert_eq!(reloaded.entries(), ["line one\nline \\two"]);
+    }
+
+    #[test]
+    fn test_concurrent_sessions_keep_each_others_entries() {
+        // Test entries are appended, deduplicated on load and compacted past twice the cap
+        let dir = temp_dir();
+        let path = dir.path().join("history");
+        let mut first = History::load(path.clone()).unwrap();
+        let mut second = History::load(path.clone()).unwrap();
+        first.push("one").unwrap();
+        second.push("two").unwrap();
+        first.push("two").unwrap();
+        second.push("three").unwrap();
+
+        let reloaded = History::load(path.clone()).unwrap();
+        assert_eq!(reloaded.entries(), ["one", "two", "three"]);
+
+        second.capacity = 2;
+        second.push("four").unwrap();
+        let contents = fs::read_to_string(&path).unwrap();
+        assert_eq!(contents, "three\nfour\n");
+    }
+
+    #[test]
+    fn test_unreadable_history_is_an_error() {
+        // Test a history file that is not UTF-8 fails to load instead of being overwritten
+        let dir = temp_dir();
+        let path = dir.path().join("history");
+        fs::write(&path, [0xff, 0xfe, b'\n']).unwrap();
+        let err = History::load(path).unwrap_err();
+        assert!(err.to_string().starts_with("Failed to read history file"));
+    }
+}
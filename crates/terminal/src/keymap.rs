@@ -0,0 +1,527 @@
+use anyhow::{Context, Result, anyhow, bail};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use foundry_core::paths::config_dir;
+
+/// Something a key binding can trigger.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    Submit,
+    Quit,
+    Cancel,
+    Complete,
+    CompletePrevious,
+    MoveLeft,
+    MoveRight,
+    MoveUp,
+    MoveDown,
+    HistoryPrev,
+    HistoryNext,
+    ReverseSearch,
+    DeleteBackward,
+    ScrollUp,
+    ScrollDown,
+    OpenEditor,
+    Suspend,
+    FocusOutput,
+    ToggleTranscript,
+    SearchConsole,
+}
+
+/// Every action with the name used in keymap files and a description for `/keys`.
+pub const ACTIONS: &[(Action, &str, &str)] = &[
+    (Action::Submit, "submit", "Send the input"),
+    (Action::Quit, "quit", "Exit the application"),
+    (Action::Cancel, "cancel", "Close a popup or clear the input"),
+    (Action::Complete, "complete", "Complete the command or file"),
+    (
+        Action::CompletePrevious,
+        "complete-previous",
+        "Select the previous completion",
+    ),
+    (Action::MoveLeft, "move-left", "Move the cursor left"),
+    (Action::MoveRight, "move-right", "Move the cursor right"),
+    (
+        Action::MoveUp,
+        "move-up",
+        "Move up a line, or recall older history",
+    ),
+    (
+        Action::MoveDown,
+        "move-down",
+        "Move down a line, or recall newer history",
+    ),
+    (Action::HistoryPrev, "history-prev", "Recall older history"),
+    (Action::HistoryNext, "history-next", "Recall newer history"),
+    (Action::ReverseSearch, "reverse-search", "Search history"),
+    (
+        Action::DeleteBackward,
+        "delete-backward",
+        "Delete the character before the cursor",
+    ),
+    (Action::ScrollUp, "scroll-up", "Scroll the console up"),
+    (Action::ScrollDown, "scroll-down", "Scroll the console down"),
+    (
+        Action::OpenEditor,
+        "open-editor",
+        "Edit the input in $VISUAL or $EDITOR",
+    ),
+    (
+        Action::Suspend,
+        "suspend",
+        "Suspend to the shell (resume with fg)",
+    ),
+    (
+        Action::FocusOutput,
+        "focus-output",
+        "Select a message or code block in the console",
+    ),
+    (
+        Action::ToggleTranscript,
+        "toggle-transcript",
+        "Switch between compact and verbose tool output",
+    ),
+    (
+        Action::SearchConsole,
+        "search-console",
+        "Search the console output",
+    ),
+];
+
+const DEFAULT_BINDINGS: &[(&str, Action)] = &[
+    ("enter", Action::Submit),
+    ("ctrl+c", Action::Quit),
+    ("esc", Action::Cancel),
+    ("tab", Action::Complete),
+    ("backtab", Action::CompletePrevious),
+    ("left", Action::MoveLeft),
+    ("right", Action::MoveRight),
+    ("up", Action::MoveUp),
+    ("down", Action::MoveDown),
+    ("ctrl+p", Action::HistoryPrev),
+    ("ctrl+n", Action::HistoryNext),
+    ("ctrl+r", Action::ReverseSearch),
+    ("backspace", Action::DeleteBackward),
+    ("pageup", Action::ScrollUp),
+    ("pagedown", Action::ScrollDown),
+    ("ctrl+g", Action::OpenEditor),
+    ("ctrl+z", Action::Suspend),
+    ("alt+up", Action::FocusOutput),
+    ("ctrl+o", Action::ToggleTranscript),
+    ("ctrl+f", Action::SearchConsole),
+];
+
+impl Action {
+    pub fn from_name(name: &str) -> Option<Self> {
+        ACTIONS
+            .iter()
+            .find(|(_, action_name, _)| *action_name == name)
+            .map(|(action, _, _)| *action)
+    }
+
+    pub fn name(self) -> &'static str {
+        ACTIONS
+            .iter()
+            .find(|(action, _, _)| *action == self)
+            .map_or("", |(_, name, _)| name)
+    }
+}
+
+/// A single key press with its modifiers, e.g. `ctrl+r`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyChord {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl KeyChord {
+    /// Normalizes a terminal key event. Shift is folded into the character
+    /// for printable keys, since terminals report `A` with or without it.
+    pub fn from_event(key: KeyEvent) -> Self {
+        let mut modifiers = key.modifiers
+            & (KeyModifiers::CONTROL
+                | KeyModifiers::ALT
+                | KeyModifiers::SHIFT
+                | KeyModifiers::SUPER);
+        if matches!(key.code, KeyCode::Char(_)) {
+            modifiers.remove(KeyModifiers::SHIFT);
+        }
+        Self {
+            code: key.code,
+            modifiers,
+        }
+    }
+
+    /// Parses chords such as `ctrl+r`, `alt+enter`, `shift+a` or `pageup`.
+    pub fn parse(text: &str) -> Result<Self> {
+        let parts = text.split('+').map(str::trim).collect::<Vec<_>>();
+        let (key, modifier_names) = match parts.split_last() {
+            // A trailing `+` means the plus key itself, e.g. `ctrl++`.
+            Some((&"", rest)) if text.ends_with("++") => ("+", &rest[..rest.len() - 1]),
+            Some((key, rest)) => (*key, rest),
+            None => bail!("empty key binding"),
+        };
+
+        let mut modifiers = KeyModifiers::NONE;
+        for name in modifier_names {
+            modifiers |= match name.to_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" | "meta" | "option" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                "super" | "cmd" => KeyModifiers::SUPER,
+                other => bail!("unknown modifier `{other}` in `{text}`"),
+            };
+        }
+
+        let code = match key.to_lowercase().as_str() {
+            "enter" | "return" => KeyCode::Enter,
+            "esc" | "escape" => KeyCode::Esc,
+            "tab" => KeyCode::Tab,
+            "backtab" => KeyCode::BackTab,
+            "backspace" => KeyCode::Backspace,
+            "delete" | "del" => KeyCode::Delete,
+            "insert" => KeyCode::Insert,
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" => KeyCode::PageUp,
+            "pagedown" => KeyCode::PageDown,
+            "space" => KeyCode::Char(' '),
+            lower => match lower.strip_prefix('f').map(str::parse::<u8>) {
+                Some(Ok(number)) if (1..=24).contains(&number) => KeyCode::F(number),
+                _ => {
+                    let mut chars = key.chars();
+                    match (chars.next(), chars.next()) {
+                        (Some(c), None) => KeyCode::Char(c),
+                        _ => bail!("unknown key `{key}` in `{text}`"),
+                    }
+                }
+            },
+        };
+
+        // Fold shift into printable characters, matching `from_event`.
+        if let KeyCode::Char(c) = code {
+            let c = if modifiers.contains(KeyModifiers::SHIFT) {
+                c.to_ascii_uppercase()
+            } else {
+                c
+            };
+            modifiers.remove(KeyModifiers::SHIFT);
+            return Ok(Self {
+                code: KeyCode::Char(c),
+                modifiers,
+            });
+        }
+        Ok(Self { code, modifiers })
+    }
+}
+
+impl fmt::Display for KeyChord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (modifier, name) in [
+            (KeyModifiers::CONTROL, "Ctrl+"),
+            (KeyModifiers::ALT, "Alt+"),
+            (KeyModifiers::SHIFT, "Shift+"),
+            (KeyModifiers::SUPER, "Super+"),
+        ] {
+            if self.modifiers.contains(modifier) {
+                f.write_str(name)?;
+            }
+        }
+
+        match self.code {
+            KeyCode::Char(' ') => f.write_str("Space"),
+            KeyCode::Char(c) if self.modifiers.is_empty() => write!(f, "{c}"),
+            KeyCode::Char(c) => write!(f, "{}", c.to_ascii_uppercase()),
+            KeyCode::Up => f.write_str("↑"),
+            KeyCode::Down => f.write_str("↓"),
+            KeyCode::Left => f.write_str("←"),
+            KeyCode::Right => f.write_str("→"),
+            KeyCode::BackTab => f.write_str("Shift+Tab"),
+            KeyCode::PageUp => f.write_str("PgUp"),
+            KeyCode::PageDown => f.write_str("PgDn"),
+            KeyCode::F(number) => write!(f, "F{number}"),
+            other => write!(f, "{other:?}"),
+        }
+    }
+}
+
+/// Formats a key sequence as shown to users, e.g. `Ctrl+X Ctrl+E`.
+pub fn format_sequence(sequence: &[KeyChord]) -> String {
+    sequence
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// What a key fed to the keymap turned out to mean.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyResolution {
+    /// The keys pressed so far complete a binding.
+    Action(Action),
+    /// No binding matches; the key should be handled as plain input.
+    Unbound(KeyChord),
+}
+
+/// Maps key sequences to actions. Sequences are one or more chords
+/// separated by spaces, e.g. `ctrl+x ctrl+e`.
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    bindings: Vec<(Vec<KeyChord>, Action)>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let bindings = DEFAULT_BINDINGS
+            .iter()
+            .map(|(keys, action)| {
+                (
+                    parse_sequence(keys).expect("default key bindings are valid"),
+                    *action,
+                )
+            })
+            .collect();
+        Self { bindings }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct KeymapFile {
+    #[serde(default)]
+    bindings: BTreeMap<String, String>,
+}
+
+/// Default location of the user keymap file.
+pub fn default_keymap_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("keymap.toml"))
+}
+
+fn parse_sequence(text: &str) -> Result<Vec<KeyChord>> {
+    let sequence = text
+        .split_whitespace()
+        .map(KeyChord::parse)
+        .collect::<Result<Vec<_>>>()?;
+    if sequence.is_empty() {
+        bail!("empty key binding");
+    }
+    Ok(sequence)
+}
+
+impl Keymap {
+    /// Loads the defaults overlaid with the bindings in `path`, if it exists.
+    pub fn load(path: &Path) -> Result<Self> {
+        let mut keymap = Self::default();
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(keymap),
+            Err(err) => {
+                return Err(err)
+                    .with_context(|| format!("Failed to read keymap {}", path.display()));
+            }
+        };
+        keymap
+            .apply_toml(&contents)
+            .with_context(|| format!("Invalid keymap {}", path.display()))?;
+        Ok(keymap)
+    }
+
+    /// Applies `[bindings]` from a keymap file. Each entry replaces any
+    /// default bound to the same keys; the action `none` removes a binding.
+    pub fn apply_toml(&mut self, contents: &str) -> Result<()> {
+        let file: KeymapFile = toml::from_str(contents)?;
+        for (keys, action_name) in file.bindings {
+            let sequence = parse_sequence(&keys)?;
+            self.bindings.retain(|(existing, _)| *existing != sequence);
+            if action_name != "none" {
+                let action = Action::from_name(&action_name)
+                    .ok_or_else(|| anyhow!("unknown action `{action_name}` for `{keys}`"))?;
+                self.bindings.push((sequence, action));
+            }
+        }
+        Ok(())
+    }
+
+    /// Feeds `key` after the keys already in `pending` and returns every key
+    /// that is ready to handle, in order, with what it resolved to. Nothing
+    /// is returned while the keys are the start of a longer binding. When a
+    /// sequence stops matching its first key is handled on its own and the
+    /// rest are fed again, so `g x` with only `g g` bound types both keys.
+    pub fn resolve(
+        &self,
+        pending: &mut Vec<KeyEvent>,
+        key: KeyEvent,
+    ) -> Vec<(KeyEvent, KeyResolution)> {
+        pending.push(key);
+        let mut resolved = Vec::new();
+        while let Some(&first) = pending.first() {
+            let chords = pending
+                .iter()
+                .map(|key| KeyChord::from_event(*key))
+                .collect::<Vec<_>>();
+            if let Some((_, action)) = self.bindings.iter().find(|(keys, _)| *keys == chords) {
+                resolved.push((key, KeyResolution::Action(*action)));
+                pending.clear();
+            } else if self
+                .bindings
+                .iter()
+                .any(|(keys, _)| keys.len() > chords.len() && keys.starts_with(&chords))
+            {
+                break;
+            } else {
+                pending.remove(0);
+                resolved.push((first, KeyResolution::Unbound(chords[0])));
+            }
+        }
+        resolved
+    }
+
+    /// Key sequences bound to `action`, in binding order.
+    pub fn keys_for(&self, action: Action) -> Vec<&[KeyChord]> {
+        self.bindings
+            .iter()
+            .filter(|(_, bound)| *bound == action)
+            .map(|(keys, _)| keys.as_slice())
+            .collect()
+    }
+
+    /// The first binding for `action`, formatted for display.
+    pub fn primary_key(&self, action: Action) -> Option<String> {
+        self.keys_for(action)
+            .first()
+            .map(|keys| format_sequence(keys))
+    }
+
+    /// One line per action listing its bindings, for `/keys`.
+    pub fn describe(&self) -> Vec<String> {
+        ACTIONS
+            .iter()
+            .map(|(action, name, description)| {
+                let keys = self
+                    .keys_for(*action)
+                    .iter()
+                    .map(|keys| format_sequence(keys))
+                    .collect::<Vec<_>>();
+                let keys = if keys.is_empty() {
+                    "(unbound)".to_string()
+                } else {
+                    keys.join(", ")
+                };
+                format!("  {keys:<16} {name:<18} {description}")
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+        KeyEvent::new(code, modifiers)
+    }
+
+    #[test]
+    fn test_parse_key_chords() {
+        // Test modifiers, named keys and shifted characters parse consistently
+        let chord = KeyChord::parse("ctrl+r").unwrap();
+        assert_eq!(chord.code, KeyCode::Char('r'));
+        assert_eq!(chord.modifiers, KeyModifiers::CONTROL);
+
+        assert_eq!(KeyChord::parse("PageUp").unwrap().code, KeyCode::PageUp);
+        assert_eq!(KeyChord::parse("f5").unwrap().code, KeyCode::F(5));
+        assert_eq!(
+            KeyChord::parse("shift+a").unwrap(),
+            KeyChord::from_event(key(KeyCode::Char('A'), KeyModifiers::SHIFT))
+        );
+        assert_eq!(KeyChord::parse("ctrl++").unwrap().code, KeyCode::Char('+'));
+        assert!(KeyChord::parse("hyper+x").is_err());
+        assert!(KeyChord::parse("enterr").is_err());
+    }
+
+    #[test]
+    fn test_resolve_multi_key_sequences() {
+        // Test sequences wait for further keys and replay them when broken
+        let mut keymap = Keymap::default();
+        keymap
+            .apply_toml("[bindings]\n\"ctrl+x ctrl+q\" = \"quit\"\n\"g g\" = \"scroll-up\"")
+            .unwrap();
+        let mut pending = Vec::new();
+
+        let ctrl_x = key(KeyCode::Char('x'), KeyModifiers::CONTROL);
+        let ctrl_q = key(KeyCode::Char('q'), KeyModifiers::CONTROL);
+        assert!(keymap.resolve(&mut pending, ctrl_x).is_empty());
+        assert_eq!(
+            keymap.resolve(&mut pending, ctrl_q),
+            [(ctrl_q, KeyResolution::Action(Action::Quit))]
+        );
+        assert!(pending.is_empty());
+
+        keymap.resolve(&mut pending, ctrl_x);
+        let tab = key(KeyCode::Tab, KeyModifiers::NONE);
+        assert_eq!(
+            keymap.resolve(&mut pending, tab),
+            [
+                (ctrl_x, KeyResolution::Unbound(KeyChord::from_event(ctrl_x))),
+                (tab, KeyResolution::Action(Action::Complete)),
+            ]
+        );
+
+        let g = key(KeyCode::Char('g'), KeyModifiers::NONE);
+        let x = key(KeyCode::Char('x'), KeyModifiers::NONE);
+        assert!(keymap.resolve(&mut pending, g).is_empty());
+        assert_eq!(
+            keymap.resolve(&mut pending, x),
+            [
+                (g, KeyResolution::Unbound(KeyChord::from_event(g))),
+                (x, KeyResolution::Unbound(KeyChord::from_event(x))),
+            ]
+        );
+
+        keymap.resolve(&mut pending, g);
+        assert_eq!(
+            keymap.resolve(&mut pending, g),
+            [(g, KeyResolution::Action(Action::ScrollUp))]
+        );
+        assert!(pending.is_empty());
+    }
+
+    #[test]
+    fn test_keymap_overrides_and_unbinds() {
+        // Test user bindings replace defaults and `none` removes them
+        let mut keymap = Keymap::default();
+        keymap
+            .apply_toml("[bindings]\n\"ctrl+c\" = \"none\"\n\"ctrl+q\" = \"quit\"")
+            .unwrap();
+
+        assert_eq!(keymap.primary_key(Action::Quit), Some("Ctrl+Q".to_string()));
+        assert!(keymap.describe().iter().any(|line| line.contains("Ctrl+Q")));
+        assert!(
+            keymap
+                .apply_toml("[bindings]\n\"ctrl+q\" = \"explode\"")
+                .is_err()
+        );
+        assert!(keymap.apply_toml("[bindngs]").is_err());
+    }
+
+    #[test]
+    fn test_every_action_has_a_name() {
+        // Test each bound action is named, found by its name and listed in /keys
+        let keymap = Keymap::default();
+        let listing = keymap.describe().join("\n");
+        for (_, action) in DEFAULT_BINDINGS {
+            assert!(!action.name().is_empty(), "{action:?} has no name");
+            assert_eq!(Action::from_name(action.name()), Some(*action));
+            assert!(listing.contains(action.name()), "{action:?} is not listed");
+        }
+    }
+}
@@ -1,35 +1,174 @@
 pub mod app;
+pub mod clipboard;
 pub mod commands;
+pub mod completion;
+pub mod diff;
+pub mod doctor;
+pub mod editor;
 pub mod events;
+pub mod headless;
+pub mod history;
+pub mod keymap;
+pub mod search;
+pub mod selection;
+pub mod signals;
+pub mod status;
 pub mod terminal;
+pub mod theme;
+pub mod tool_call;
 pub mod ui;
+pub mod vim;
 
 pub use app::AppState;
-pub use events::{handle_input, should_quit};
-pub use terminal::{TerminalType, restore_terminal, setup_terminal};
-pub use ui::render_ui;
+pub use events::{handle_input, handle_paste, should_quit};
+pub use history::History;
+pub use keymap::{Action, Keymap, default_keymap_path};
+pub use terminal::{
+    ScreenMode, TerminalType, install_panic_hook, restore_terminal, resume_terminal,
+    setup_inline_terminal, setup_terminal, setup_terminal_for, suspend_terminal,
+};
+pub use theme::{Theme, detect_theme};
+pub use ui::{print_to_scrollback, render_inline_ui, render_ui};
 
 use anyhow::Context;
 use crossterm::event::{self, Event};
+use editor::{edit_input_in_editor, edit_instructions_in_editor};
+use foundry_core::agent::{Agent, EchoModel};
+use foundry_core::config::LayeredConfig;
+use foundry_core::instructions::Instructions;
+use foundry_core::mentions::list_workspace_files;
+use foundry_core::paths::find_project_root;
+use foundry_core::permissions::PermissionPolicy;
+use signals::{SignalEvent, SignalWatcher};
+use status::StatusBar;
+use std::time::{Duration, Instant};
+use ui::console_text_width;
 
-pub fn run() -> anyhow::Result<()> {
-    let mut terminal = setup_terminal()?;
+/// Interval between ticks, which animate the status bar. Signals are also
+/// checked at least this often.
+const TICK_RATE: Duration = Duration::from_millis(100);
+
+pub fn run(mode: ScreenMode, config: LayeredConfig) -> anyhow::Result<()> {
     let mut app_state = AppState::default();
-    let result = run_app(&mut terminal, &mut app_state);
+    let cwd = std::env::current_dir().context("Failed to determine current directory")?;
+    match History::for_project(&cwd) {
+        Ok(history) => app_state.history = history,
+        Err(err) => app_state.set_status(format!("⚠️ {err:#}")),
+    }
+    app_state.workspace_root = find_project_root(&cwd);
+    app_state.agent = Agent::new(
+        Box::new(EchoModel),
+        app_state.workspace_root.clone(),
+        PermissionPolicy::default(),
+    );
+    app_state.workspace_files = list_workspace_files(&app_state.workspace_root);
+    app_state.instructions = Instructions::load(&cwd);
+    app_state.status_bar = StatusBar::for_directory(cwd, &app_state.workspace_root);
+    let config_status = config_issues_status(&config);
+    app_state.config = config;
+    app_state.apply_config();
+    // Config problems are shown rather than aborting, so a typo does not
+    // lock the user out of the TUI.
+    if let Some(status) = config_status {
+        app_state.set_status(status);
+    }
+
+    let signals = SignalWatcher::install()?;
+    install_panic_hook();
+    let mut terminal = setup_terminal_for(mode)?;
+    let result = run_app(&mut terminal, &mut app_state, &signals, mode);
     restore_terminal(&mut terminal)?;
     result
 }
 
-fn run_app(terminal: &mut TerminalType, app_state: &mut AppState) -> anyhow::Result<()> {
+/// The first config problem, and how many more there are.
+fn config_issues_status(config: &LayeredConfig) -> Option<String> {
+    let (first, rest) = config.issues().split_first()?;
+    Some(if rest.is_empty() {
+        format!("⚠️ {first}")
+    } else {
+        format!("⚠️ {first} (+{} more, see /config)", rest.len())
+    })
+}
+
+fn run_app(
+    terminal: &mut TerminalType,
+    app_state: &mut AppState,
+    signals: &SignalWatcher,
+    mode: ScreenMode,
+) -> anyhow::Result<()> {
+    // Output lines already printed to the scrollback in inline mode.
+    let mut printed_lines = 0;
+    let mut last_tick = Instant::now();
     loop {
-        render_ui(terminal, app_state)?;
+        match mode {
+            ScreenMode::Fullscreen => {
+                let width = terminal
+                    .size()
+                    .context("Failed to read terminal size")?
+                    .width;
+                app_state.console_width = console_text_width(width);
+                render_ui(terminal, app_state)?;
+            }
+            ScreenMode::Inline => {
+                // Running tool calls still change, so they are printed once
+                // they finish.
+                let settled = app_state.settled_output_len();
+                let pending = app_state.output_history.get(printed_lines..settled);
+                print_to_scrollback(terminal, pending.unwrap_or_default(), app_state)?;
+                printed_lines = printed_lines.max(settled);
+                render_inline_ui(terminal, app_state)?;
+            }
+        }
 
-        if let Event::Key(key) = event::read().context("Failed to read input event")? {
-            handle_input(app_state, key)?;
-            if should_quit(app_state) {
-                break;
+        let timeout = TICK_RATE.saturating_sub(last_tick.elapsed());
+        if event::poll(timeout).context("Failed to poll input events")? {
+            match event::read().context("Failed to read input event")? {
+                Event::Key(key) => handle_input(app_state, key)?,
+                Event::Paste(text) => handle_paste(app_state, &text)?,
+                Event::Resize(..) => terminal.autoresize().context("Failed to resize terminal")?,
+                _ => {}
             }
         }
+
+        if last_tick.elapsed() >= TICK_RATE {
+            app_state.on_tick();
+            last_tick = Instant::now();
+        }
+
+        match signals.take() {
+            Some(SignalEvent::Terminate) => app_state.quit(),
+            Some(SignalEvent::Suspend) => app_state.suspend_requested = true,
+            Some(SignalEvent::Continue) => terminal.clear().context("Failed to redraw terminal")?,
+            None => {}
+        }
+
+        if app_state.suspend_requested {
+            app_state.suspend_requested = false;
+            if let Err(err) = suspend_terminal(terminal) {
+                app_state.set_status(format!("⚠️ {err:#}"));
+            }
+        }
+
+        if let Some(text) = app_state.pending_copy.take() {
+            match clipboard::copy_to_clipboard(terminal.backend_mut(), &text) {
+                Ok(method) => app_state.set_status(format!("📋 Copied to clipboard via {method}")),
+                Err(err) => app_state.set_status(format!("⚠️ {err:#}")),
+            }
+        }
+
+        if app_state.editor_requested {
+            app_state.editor_requested = false;
+            edit_input_in_editor(terminal, app_state)?;
+        }
+
+        if let Some(path) = app_state.instructions_edit_requested.take() {
+            edit_instructions_in_editor(terminal, app_state, &path)?;
+        }
+
+        if should_quit(app_state) {
+            break;
+        }
     }
     Ok(())
 }
@@ -61,8 +200,8 @@ mod tests {
         handle_input(&mut app, key).unwrap();
         assert_eq!(app.input, "h");
 
-        // Esc key should trigger quit state
-        let key = KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE);
+        // Ctrl+C should trigger quit state
+        let key = KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL);
         handle_input(&mut app, key).unwrap();
         assert!(should_quit(&app));
     }
@@ -0,0 +1,176 @@
+use regex::{Regex, RegexBuilder};
+use std::collections::HashMap;
+use std::ops::Range;
+
+/// A match in the console: a byte range within one line of an entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchMatch {
+    pub entry: usize,
+    pub line: usize,
+    pub range: Range<usize>,
+}
+
+/// The matches on one console line, each with whether it is the current one.
+pub type LineMatches = Vec<(Range<usize>, bool)>;
+
+/// Incremental search over the console output. The query is plain text
+/// unless `regex` is set, and is case-insensitive unless it contains an
+/// uppercase letter.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConsoleSearch {
+    pub query: String,
+    pub regex: bool,
+    /// Whether keys edit the query; otherwise they navigate between matches.
+    pub editing: bool,
+    pub matches: Vec<SearchMatch>,
+    pub current: Option<usize>,
+    /// Why the query could not be used, e.g. an invalid regex.
+    pub error: Option<String>,
+}
+
+impl ConsoleSearch {
+    pub fn new() -> Self {
+        Self {
+            editing: true,
+            ..Self::default()
+        }
+    }
+
+    /// Finds all matches of the query and selects the newest one.
+    pub fn update(&mut self, output: &[String]) {
+        self.matches.clear();
+        self.current = None;
+        self.error = None;
+        if self.query.is_empty() {
+            return;
+        }
+
+        let pattern = match build_pattern(&self.query, self.regex) {
+            Ok(pattern) => pattern,
+            Err(err) => {
+                self.error = Some(err.to_string());
+                return;
+            }
+        };
+        for (entry, text) in output.iter().enumerate() {
+            for (line, content) in text.split('\n').enumerate() {
+                self.matches.extend(
+                    pattern
+                        .find_iter(content)
+                        .filter(|found| !found.is_empty())
+                        .map(|found| SearchMatch {
+                            entry,
+                            line,
+                            range: found.range(),
+                        }),
+                );
+            }
+        }
+        self.current = self.matches.len().checked_sub(1);
+    }
+
+    /// Moves to the previous match, towards older output.
+    pub fn older(&mut self) {
+        if let Some(current) = self.current {
+            self.current = Some(current.saturating_sub(1));
+        }
+    }
+
+    /// Moves to the next match, towards newer output.
+    pub fn newer(&mut self) {
+        if let Some(current) = self.current {
+            self.current = Some((current + 1).min(self.matches.len() - 1));
+        }
+    }
+
+    pub fn current_match(&self) -> Option<&SearchMatch> {
+        self.matches.get(self.current?)
+    }
+
+    /// Matches grouped by console line, keyed by entry and line index.
+    pub fn matches_by_line(&self) -> HashMap<(usize, usize), LineMatches> {
+        let mut grouped = HashMap::<_, Vec<_>>::new();
+        for (index, found) in self.matches.iter().enumerate() {
+            grouped
+                .entry((found.entry, found.line))
+                .or_default()
+                .push((found.range.clone(), Some(index) == self.current));
+        }
+        grouped
+    }
+
+    /// `3/12`, or `0/0` when nothing matches.
+    pub fn position(&self) -> String {
+        let current = self.current.map_or(0, |current| current + 1);
+        format!("{current}/{}", self.matches.len())
+    }
+}
+
+fn build_pattern(query: &str, regex: bool) -> Result<Regex, regex::Error> {
+    let pattern = if regex {
+        query.to_string()
+    } else {
+        regex::escape(query)
+    };
+    RegexBuilder::new(&pattern)
+        .case_insensitive(!query.chars().any(char::is_uppercase))
+        .build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn output() -> Vec<String> {
+        vec![
+            "> find the Error".to_string(),
+            "error: one\nno problem\nerror: two".to_string(),
+        ]
+    }
+
+    #[test]
+    fn test_plain_search_uses_smart_case() {
+        // Test lowercase queries ignore case and uppercase ones do not
+        let mut search = ConsoleSearch::new();
+        search.query = "error".to_string();
+        search.update(&output());
+        assert_eq!(search.matches.len(), 3);
+        assert_eq!(search.position(), "3/3");
+        assert_eq!(
+            search.current_match(),
+            Some(&SearchMatch {
+                entry: 1,
+                line: 2,
+                range: 0..5
+            })
+        );
+
+        search.query = "Error".to_string();
+        search.update(&output());
+        assert_eq!(search.matches.len(), 1);
+    }
+
+    #[test]
+    fn test_regex_search_and_navigation() {
+        // Test regex queries, invalid patterns and n/N navigation bounds
+        let mut search = ConsoleSearch::new();
+        search.regex = true;
+        search.query = "(one|two)$".to_string();
+        search.update(&output());
+        assert_eq!(search.matches.len(), 2);
+
+        search.newer();
+        assert_eq!(search.current, Some(1));
+        search.older();
+        search.older();
+        assert_eq!(search.current, Some(0));
+        let grouped = search.matches_by_line();
+        assert_eq!(grouped[&(1, 0)], [(7..10, true)]);
+        assert_eq!(grouped[&(1, 2)], [(7..10, false)]);
+
+        search.query = "(".to_string();
+        search.update(&output());
+        assert!(search.error.is_some());
+        assert_eq!(search.position(), "0/0");
+    }
+}
@@ -0,0 +1,165 @@
+use std::ops::Range;
+
+/// Something in the console that can be focused and copied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelectionTarget {
+    /// A whole console entry.
+    Message(usize),
+    /// The `block`-th fenced code block inside a console entry.
+    CodeBlock { message: usize, block: usize },
+}
+
+impl SelectionTarget {
+    pub fn message(&self) -> usize {
+        match *self {
+            Self::Message(message) | Self::CodeBlock { message, .. } => message,
+        }
+    }
+
+    /// Lines of the entry covered by this target.
+    pub fn line_range(&self, output: &[String]) -> Range<usize> {
+        let Some(entry) = output.get(self.message()) else {
+            return 0..0;
+        };
+        match *self {
+            Self::Message(_) => 0..entry.split('\n').count(),
+            Self::CodeBlock { block, .. } => {
+                code_block_ranges(entry).get(block).cloned().unwrap_or(0..0)
+            }
+        }
+    }
+
+    /// The text copied when this target is yanked. Code blocks are copied
+    /// without their fences.
+    pub fn text(&self, output: &[String]) -> Option<String> {
+        let entry = output.get(self.message())?;
+        match self {
+            Self::Message(_) => Some(entry.clone()),
+            Self::CodeBlock { .. } => {
+                let range = self.line_range(output);
+                if range.is_empty() {
+                    return None;
+                }
+                let lines = entry.split('\n').collect::<Vec<_>>();
+                let mut body = &lines[range.start + 1..range.end];
+                if let Some((last, rest)) = body.split_last()
+                    && last.trim_start().starts_with("```")
+                {
+                    body = rest;
+                }
+                Some(body.join("\n"))
+            }
+        }
+    }
+}
+
+/// Line ranges of the fenced code blocks in `text`, including the fences.
+/// An unterminated block runs to the end of the text.
+pub fn code_block_ranges(text: &str) -> Vec<Range<usize>> {
+    let mut ranges = Vec::new();
+    let mut start = None;
+    let mut line_count = 0;
+    for (index, line) in text.split('\n').enumerate() {
+        line_count = index + 1;
+        if line.trim_start().starts_with("```") {
+            match start.take() {
+                Some(open) => ranges.push(open..index + 1),
+                None => start = Some(index),
+            }
+        }
+    }
+    if let Some(open) = start {
+        ranges.push(open..line_count);
+    }
+    ranges
+}
+
+/// Every focusable target in console order: each non-empty entry followed
+/// by the code blocks inside it.
+pub fn selection_targets(output: &[String]) -> Vec<SelectionTarget> {
+    let mut targets = Vec::new();
+    for (message, entry) in output.iter().enumerate() {
+        if entry.trim().is_empty() {
+            continue;
+        }
+        targets.push(SelectionTarget::Message(message));
+        targets.extend(
+            (0..code_block_ranges(entry).len())
+                .map(|block| SelectionTarget::CodeBlock { message, block }),
+        );
+    }
+    targets
+}
+
+/// The output that followed the most recent prompt, ignoring slash commands
+/// and their output.
+pub fn last_reply(output: &[String]) -> Option<String> {
+    let mut reply = None;
+    let mut current: Option<Vec<&str>> = None;
+    for entry in output {
+        if let Some(prompt) = entry.strip_prefix("> ") {
+            if let Some(lines) = current.take().filter(|lines| !lines.is_empty()) {
+                reply = Some(lines.join("\n"));
+            }
+            current = (!prompt.trim_start().starts_with('/')).then(Vec::new);
+        } else if let Some(lines) = &mut current {
+            lines.push(entry);
+        }
+    }
+    current
+        .filter(|lines| !lines.is_empty())
+        .map(|lines| lines.join("\n"))
+        .or(reply)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entries(entries: &[&str]) -> Vec<String> {
+        entries.iter().map(|entry| entry.to_string()).collect()
+    }
+
+    #[test]
+    fn test_code_blocks_are_selectable() {
+        // Test code blocks become targets of their own and copy without fences
+        let output = entries(&["> show me", "Here:\n```rust\nfn main() {}\n```\nDone", ""]);
+        let targets = selection_targets(&output);
+        assert_eq!(
+            targets,
+            vec![
+                SelectionTarget::Message(0),
+                SelectionTarget::Message(1),
+                SelectionTarget::CodeBlock {
+                    message: 1,
+                    block: 0
+                },
+            ]
+        );
+        assert_eq!(targets[2].line_range(&output), 1..4);
+        assert_eq!(targets[2].text(&output).unwrap(), "fn main() {}");
+        assert_eq!(targets[1].text(&output).unwrap(), output[1]);
+    }
+
+    #[test]
+    fn test_unterminated_code_block() {
+        // Test a block missing its closing fence runs to the end of the entry
+        assert_eq!(code_block_ranges("a\n```\nb\nc"), vec![1..4]);
+        let output = entries(&["```\nb"]);
+        let target = SelectionTarget::CodeBlock {
+            message: 0,
+            block: 0,
+        };
+        assert_eq!(target.text(&output).unwrap(), "b");
+    }
+
+    #[test]
+    fn test_last_reply_skips_commands() {
+        // Test the last reply ignores slash commands and their output
+        let output = entries(&["> hello", "Hi there", "> /keys", "Key bindings:"]);
+        assert_eq!(last_reply(&output).unwrap(), "Hi there");
+
+        let output = entries(&["Welcome", "> /help", "Commands"]);
+        assert_eq!(last_reply(&output), None);
+    }
+}
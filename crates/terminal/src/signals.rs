@@ -0,0 +1,116 @@
+/// A process signal the run loop needs to react to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignalEvent {
+    /// SIGTERM or SIGHUP: restore the terminal and exit.
+    Terminate,
+    /// SIGTSTP sent from outside (e.g. `kill -TSTP`): suspend like Ctrl+Z.
+    Suspend,
+    /// SIGCONT: the process was resumed and the screen must be redrawn.
+    Continue,
+}
+
+#[cfg(unix)]
+pub use unix::{SignalWatcher, suspend_process};
+
+#[cfg(not(unix))]
+pub use fallback::{SignalWatcher, suspend_process};
+
+#[cfg(unix)]
+mod unix {
+    use super::SignalEvent;
+    use anyhow::{Context, Result};
+    use signal_hook::consts::{SIGCONT, SIGHUP, SIGSTOP, SIGTERM, SIGTSTP};
+    use signal_hook::flag;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    /// Records signals as flags so they can be handled between events
+    /// instead of inside a signal handler.
+    pub struct SignalWatcher {
+        terminate: Arc<AtomicBool>,
+        suspend: Arc<AtomicBool>,
+        resume: Arc<AtomicBool>,
+    }
+
+    impl SignalWatcher {
+        pub fn install() -> Result<Self> {
+            let watcher = Self {
+                terminate: Arc::new(AtomicBool::new(false)),
+                suspend: Arc::new(AtomicBool::new(false)),
+                resume: Arc::new(AtomicBool::new(false)),
+            };
+            for (signal, slot) in [
+                (SIGTERM, &watcher.terminate),
+                (SIGHUP, &watcher.terminate),
+                (SIGTSTP, &watcher.suspend),
+                (SIGCONT, &watcher.resume),
+            ] {
+                flag::register(signal, Arc::clone(slot))
+                    .context("Failed to register signal handler")?;
+            }
+            Ok(watcher)
+        }
+
+        /// Takes the most urgent pending signal, if any.
+        pub fn take(&self) -> Option<SignalEvent> {
+            if self.terminate.swap(false, Ordering::Relaxed) {
+                Some(SignalEvent::Terminate)
+            } else if self.suspend.swap(false, Ordering::Relaxed) {
+                Some(SignalEvent::Suspend)
+            } else if self.resume.swap(false, Ordering::Relaxed) {
+                Some(SignalEvent::Continue)
+            } else {
+                None
+            }
+        }
+    }
+
+    /// Stops the process until it receives SIGCONT. SIGSTOP is used because
+    /// SIGTSTP is caught by the watcher.
+    pub fn suspend_process() -> Result<()> {
+        signal_hook::low_level::raise(SIGSTOP).context("Failed to suspend process")
+    }
+}
+
+#[cfg(not(unix))]
+mod fallback {
+    use super::SignalEvent;
+    use anyhow::{Result, bail};
+
+    /// Job-control signals do not exist on this platform.
+    pub struct SignalWatcher;
+
+    impl SignalWatcher {
+        pub fn install() -> Result<Self> {
+            Ok(Self)
+        }
+
+        pub fn take(&self) -> Option<SignalEvent> {
+            None
+        }
+    }
+
+    pub fn suspend_process() -> Result<()> {
+        bail!("Suspending is not supported on this platform")
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use signal_hook::consts::{SIGCONT, SIGHUP};
+    use signal_hook::low_level::raise;
+
+    #[test]
+    fn test_signals_are_recorded() {
+        // Test raised signals are reported once, in order of urgency
+        let watcher = SignalWatcher::install().unwrap();
+        assert_eq!(watcher.take(), None);
+
+        raise(SIGCONT).unwrap();
+        raise(SIGHUP).unwrap();
+        assert_eq!(watcher.take(), Some(SignalEvent::Terminate));
+        assert_eq!(watcher.take(), Some(SignalEvent::Continue));
+        assert_eq!(watcher.take(), None);
+    }
+}
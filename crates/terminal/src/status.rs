@@ -0,0 +1,179 @@
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use foundry_core::paths::git_branch;
+
+/// Frames of the activity spinner, advanced once per tick.
+pub const SPINNER_FRAMES: &[&str] = &["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+
+/// Ticks between re-reading the git branch, which may change while running.
+const GIT_REFRESH_TICKS: u64 = 50;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ConnectionState {
+    #[default]
+    SignedOut,
+    Connecting,
+    Connected,
+    Offline,
+}
+
+impl ConnectionState {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::SignedOut => "signed out",
+            Self::Connecting => "connecting",
+            Self::Connected => "connected",
+            Self::Offline => "offline",
+        }
+    }
+}
+
+/// What the app is busy with, shown with a spinner while not idle.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Activity {
+    #[default]
+    Idle,
+    Thinking,
+    RunningTool(String),
+}
+
+impl Activity {
+    pub fn label(&self) -> String {
+        match self {
+            Self::Idle => String::new(),
+            Self::Thinking => "Thinking".to_string(),
+            Self::RunningTool(tool) => format!("Running {tool}"),
+        }
+    }
+}
+
+/// Live session details shown in the status bar.
+#[derive(Debug, Clone, Default)]
+pub struct StatusBar {
+    pub connection: ConnectionState,
+    /// Active model deployment.
+    pub model: Option<String>,
+    /// Active config profile, and whether it is marked protected.
+    pub profile: Option<String>,
+    pub profile_protected: bool,
+    pub activity: Activity,
+    pub activity_started: Option<Instant>,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    /// Estimated cost in US dollars.
+    pub cost: f64,
+    pub cwd: PathBuf,
+    pub git_branch: Option<String>,
+    pub ticks: u64,
+}
+
+impl StatusBar {
+    pub fn for_directory(cwd: PathBuf, project_root: &Path) -> Self {
+        Self {
+            git_branch: git_branch(project_root),
+            cwd,
+            ..Self::default()
+        }
+    }
+
+    pub fn start_activity(&mut self, activity: Activity) {
+        if activity == Activity::Idle {
+            self.finish_activity();
+            return;
+        }
+        // Switching between busy states keeps the original start time.
+        if self.activity == Activity::Idle {
+            self.activity_started = Some(Instant::now());
+        }
+        self.activity = activity;
+    }
+
+    pub fn finish_activity(&mut self) {
+        self.activity = Activity::Idle;
+        self.activity_started = None;
+    }
+
+    pub fn add_usage(&mut self, input_tokens: u64, output_tokens: u64, cost: f64) {
+        self.input_tokens += input_tokens;
+        self.output_tokens += output_tokens;
+        self.cost += cost;
+    }
+
+    /// Advances the spinner and periodically refreshes the git branch.
+    pub fn on_tick(&mut self, project_root: &Path) {
+        self.ticks = self.ticks.wrapping_add(1);
+        if self.ticks.is_multiple_of(GIT_REFRESH_TICKS) {
+            self.git_branch = git_branch(project_root);
+        }
+    }
+
+    pub fn spinner(&self) -> &'static str {
+        SPINNER_FRAMES[(self.ticks % SPINNER_FRAMES.len() as u64) as usize]
+    }
+
+    pub fn elapsed(&self) -> Option<Duration> {
+        self.activity_started.map(|started| started.elapsed())
+    }
+}
+
+/// Formats a duration as `12s` or `3m 07s`.
+pub fn format_elapsed(elapsed: Duration) -> String {
+    let seconds = elapsed.as_secs();
+    if seconds < 60 {
+        format!("{seconds}s")
+    } else {
+        format!("{}m {:02}s", seconds / 60, seconds % 60)
+    }
+}
+
+/// Formats a token count compactly, e.g. `950` or `12.3k`.
+pub fn format_tokens(tokens: u64) -> String {
+    match tokens {
+        0..1_000 => tokens.to_string(),
+        1_000..1_000_000 => format!("{:.1}k", tokens as f64 / 1_000.0),
+        _ => format!("{:.1}M", tokens as f64 / 1_000_000.0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_activity_tracking() {
+        // Test busy states share a start time that is cleared when idle
+        let mut status = StatusBar::default();
+        assert_eq!(status.elapsed(), None);
+
+        status.start_activity(Activity::Thinking);
+        let started = status.activity_started;
+        assert!(started.is_some());
+        status.start_activity(Activity::RunningTool("grep".to_string()));
+        assert_eq!(status.activity_started, started);
+        assert_eq!(status.activity.label(), "Running grep");
+
+        status.start_activity(Activity::Idle);
+        assert_eq!(status.activity, Activity::Idle);
+        assert_eq!(status.elapsed(), None);
+    }
+
+    #[test]
+    fn test_spinner_advances_on_tick() {
+        // Test each tick shows the next spinner frame
+        let mut status = StatusBar::default();
+        let first = status.spinner();
+        status.on_tick(Path::new("."));
+        assert_ne!(status.spinner(), first);
+    }
+
+    #[test]
+    fn test_formatting() {
+        // Test elapsed times and token counts are formatted compactly
+        assert_eq!(format_elapsed(Duration::from_secs(9)), "9s");
+        assert_eq!(format_elapsed(Duration::from_secs(187)), "3m 07s");
+        assert_eq!(format_tokens(950), "950");
+        assert_eq!(format_tokens(12_345), "12.3k");
+        assert_eq!(format_tokens(2_500_000), "2.5M");
+    }
+}
@@ -1,30 +1,140 @@
 use anyhow::{Context, Result};
 use crossterm::{
+    cursor::Show,
+    event::{DisableBracketedPaste, EnableBracketedPaste},
     execute,
+    style::Print,
     terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
 };
-use ratatui::{Terminal, backend::CrosstermBackend};
+use ratatui::{Terminal, TerminalOptions, Viewport, backend::CrosstermBackend};
 use std::io::{self, Stdout};
+use std::panic;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::signals::suspend_process;
 
 pub type TerminalType = Terminal<CrosstermBackend<Stdout>>;
 
+/// How the TUI occupies the terminal.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ScreenMode {
+    /// Takes over the alternate screen; the conversation is gone on exit.
+    #[default]
+    Fullscreen,
+    /// Redraws only a small live region and prints finished output into the
+    /// normal terminal scrollback.
+    Inline,
+}
+
+/// Rows reserved for the live region in inline mode.
+pub const INLINE_VIEWPORT_HEIGHT: u16 = 12;
+
+// The panic hook has no access to the terminal, so the active mode is
+// recorded here to know whether to leave the alternate screen.
+static INLINE_ACTIVE: AtomicBool = AtomicBool::new(false);
+
+fn is_inline() -> bool {
+    INLINE_ACTIVE.load(Ordering::Relaxed)
+}
+
 pub fn setup_terminal() -> Result<TerminalType> {
     enable_raw_mode().context("Failed to enable raw mode")?;
     let mut stdout = io::stdout();
     execute!(stdout, EnterAlternateScreen).context("Failed to enter alternate screen")?;
+    execute!(stdout, EnableBracketedPaste).context("Failed to enable bracketed paste")?;
     let backend = CrosstermBackend::new(stdout);
     let terminal = Terminal::new(backend).context("Failed to create terminal")?;
+    INLINE_ACTIVE.store(false, Ordering::Relaxed);
+    Ok(terminal)
+}
+
+/// Sets up inline mode: a viewport of `INLINE_VIEWPORT_HEIGHT` rows below
+/// the cursor, leaving the rest of the screen and its scrollback alone.
+pub fn setup_inline_terminal() -> Result<TerminalType> {
+    enable_raw_mode().context("Failed to enable raw mode")?;
+    let mut stdout = io::stdout();
+    execute!(stdout, EnableBracketedPaste).context("Failed to enable bracketed paste")?;
+    let backend = CrosstermBackend::new(stdout);
+    let options = TerminalOptions {
+        viewport: Viewport::Inline(INLINE_VIEWPORT_HEIGHT),
+    };
+    let terminal = Terminal::with_options(backend, options).context("Failed to create terminal")?;
+    INLINE_ACTIVE.store(true, Ordering::Relaxed);
     Ok(terminal)
 }
 
+pub fn setup_terminal_for(mode: ScreenMode) -> Result<TerminalType> {
+    match mode {
+        ScreenMode::Fullscreen => setup_terminal(),
+        ScreenMode::Inline => setup_inline_terminal(),
+    }
+}
+
 pub fn restore_terminal(terminal: &mut TerminalType) -> Result<()> {
     disable_raw_mode().context("Failed to disable raw mode")?;
-    execute!(terminal.backend_mut(), LeaveAlternateScreen)
-        .context("Failed to leave alternate screen")?;
+    execute!(terminal.backend_mut(), DisableBracketedPaste)
+        .context("Failed to disable bracketed paste")?;
+    if is_inline() {
+        // Drop the live region; printed output stays in the scrollback.
+        terminal.clear().context("Failed to clear viewport")?;
+    } else {
+        execute!(terminal.backend_mut(), LeaveAlternateScreen)
+            .context("Failed to leave alternate screen")?;
+    }
     terminal.show_cursor().context("Failed to show cursor")?;
     Ok(())
 }
 
+/// Re-enters the TUI after `restore_terminal`, e.g. when returning from an
+/// external editor, and clears the screen so the next draw repaints fully.
+pub fn resume_terminal(terminal: &mut TerminalType) -> Result<()> {
+    if is_inline() {
+        // The cursor may have moved while suspended, so place a new
+        // viewport below it.
+        *terminal = setup_inline_terminal()?;
+        return Ok(());
+    }
+    enable_raw_mode().context("Failed to enable raw mode")?;
+    execute!(terminal.backend_mut(), EnterAlternateScreen)
+        .context("Failed to enter alternate screen")?;
+    execute!(terminal.backend_mut(), EnableBracketedPaste)
+        .context("Failed to enable bracketed paste")?;
+    terminal.clear().context("Failed to clear terminal")?;
+    Ok(())
+}
+
+/// Suspends the process for shell job control (Ctrl+Z) and redraws the TUI
+/// once it is resumed with `fg`.
+pub fn suspend_terminal(terminal: &mut TerminalType) -> Result<()> {
+    restore_terminal(terminal)?;
+    let suspended = suspend_process();
+    resume_terminal(terminal)?;
+    suspended
+}
+
+/// Restores the terminal before the panic message is printed, so a crash
+/// never leaves the shell in raw mode or on the alternate screen.
+pub fn install_panic_hook() {
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        let _ = reset_terminal();
+        default_hook(info);
+    }));
+}
+
+fn reset_terminal() -> Result<()> {
+    disable_raw_mode().context("Failed to disable raw mode")?;
+    let mut stdout = io::stdout();
+    execute!(stdout, DisableBracketedPaste, Show).context("Failed to reset terminal")?;
+    if is_inline() {
+        // Start the panic message on a fresh line below the viewport.
+        execute!(stdout, Print("\r\n")).context("Failed to reset terminal")?;
+    } else {
+        execute!(stdout, LeaveAlternateScreen).context("Failed to reset terminal")?;
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -33,7 +143,12 @@ mod tests {
     fn test_terminal_functions_exist() {
         // Verify functions exist and have correct signatures
         let _setup_fn: fn() -> Result<TerminalType> = setup_terminal;
+        let _inline_fn: fn() -> Result<TerminalType> = setup_inline_terminal;
+        let _setup_for_fn: fn(ScreenMode) -> Result<TerminalType> = setup_terminal_for;
         let _restore_fn: fn(&mut TerminalType) -> Result<()> = restore_terminal;
+        let _resume_fn: fn(&mut TerminalType) -> Result<()> = resume_terminal;
+        let _suspend_fn: fn(&mut TerminalType) -> Result<()> = suspend_terminal;
+        let _panic_hook_fn: fn() = install_panic_hook;
         let _terminal_type_size = std::mem::size_of::<TerminalType>();
 
         assert!(_terminal_type_size > 0);
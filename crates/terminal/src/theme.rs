@@ -0,0 +1,288 @@
+use anyhow::{Context, Result, anyhow, bail};
+use ratatui::style::{Color, Style};
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use foundry_core::paths::config_dir;
+
+/// Built-in themes with a short description, in the order they are listed.
+pub const BUILTIN_THEMES: &[(&str, &str)] = &[
+    ("dark", "For dark terminal backgrounds"),
+    ("light", "For light terminal backgrounds"),
+    ("high-contrast", "Maximum contrast, no grays"),
+    (
+        "colorblind",
+        "Okabe-Ito palette, safe for color vision deficiency",
+    ),
+];
+
+/// Colors used by every widget. Widgets should take their colors from the
+/// active theme rather than naming colors directly.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Theme {
+    pub name: String,
+    pub text: Color,
+    pub muted: Color,
+    pub border: Color,
+    pub accent: Color,
+    pub highlight: Color,
+    pub success: Color,
+    pub warning: Color,
+    pub error: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::dark()
+    }
+}
+
+impl Theme {
+    pub fn dark() -> Self {
+        Self {
+            name: "dark".to_string(),
+            text: Color::Reset,
+            muted: Color::DarkGray,
+            border: Color::Gray,
+            accent: Color::Cyan,
+            highlight: Color::Rgb(0x3a, 0x3f, 0x4b),
+            success: Color::Green,
+            warning: Color::Yellow,
+            error: Color::Red,
+        }
+    }
+
+    pub fn light() -> Self {
+        Self {
+            name: "light".to_string(),
+            text: Color::Reset,
+            muted: Color::Rgb(0x6a, 0x73, 0x7d),
+            border: Color::Rgb(0x8c, 0x95, 0x9f),
+            accent: Color::Blue,
+            highlight: Color::Rgb(0xdd, 0xe4, 0xee),
+            success: Color::Rgb(0x1a, 0x7f, 0x37),
+            warning: Color::Rgb(0x9a, 0x67, 0x00),
+            error: Color::Rgb(0xcf, 0x22, 0x2e),
+        }
+    }
+
+    pub fn high_contrast() -> Self {
+        Self {
+            name: "high-contrast".to_string(),
+            text: Color::White,
+            muted: Color::White,
+            border: Color::White,
+            accent: Color::LightYellow,
+            highlight: Color::Blue,
+            success: Color::LightGreen,
+            warning: Color::LightYellow,
+            error: Color::LightRed,
+        }
+    }
+
+    pub fn colorblind() -> Self {
+        Self {
+            name: "colorblind".to_string(),
+            text: Color::Reset,
+            muted: Color::Rgb(0x99, 0x99, 0x99),
+            border: Color::Rgb(0x99, 0x99, 0x99),
+            accent: Color::Rgb(0x56, 0xb4, 0xe9),
+            highlight: Color::Rgb(0x00, 0x72, 0xb2),
+            success: Color::Rgb(0x00, 0x9e, 0x73),
+            warning: Color::Rgb(0xe6, 0x9f, 0x00),
+            error: Color::Rgb(0xd5, 0x5e, 0x00),
+        }
+    }
+
+    pub fn builtin(name: &str) -> Option<Self> {
+        match name {
+            "dark" => Some(Self::dark()),
+            "light" => Some(Self::light()),
+            "high-contrast" => Some(Self::high_contrast()),
+            "colorblind" => Some(Self::colorblind()),
+            _ => None,
+        }
+    }
+
+    /// Parses a user theme. Unset colors are taken from the `base` theme,
+    /// which defaults to `dark`.
+    pub fn from_toml(name: &str, contents: &str) -> Result<Self> {
+        let file: ThemeFile = toml::from_str(contents)?;
+        let base_name = file.base.as_deref().unwrap_or("dark");
+        let mut theme =
+            Self::builtin(base_name).ok_or_else(|| anyhow!("unknown base theme `{base_name}`"))?;
+        theme.name = name.to_string();
+
+        let overrides = [
+            (&mut theme.text, file.text, "text"),
+            (&mut theme.muted, file.muted, "muted"),
+            (&mut theme.border, file.border, "border"),
+            (&mut theme.accent, file.accent, "accent"),
+            (&mut theme.highlight, file.highlight, "highlight"),
+            (&mut theme.success, file.success, "success"),
+            (&mut theme.warning, file.warning, "warning"),
+            (&mut theme.error, file.error, "error"),
+        ];
+        for (slot, value, key) in overrides {
+            if let Some(value) = value {
+                *slot = Color::from_str(&value)
+                    .map_err(|_| anyhow!("invalid color `{value}` for `{key}`"))?;
+            }
+        }
+        Ok(theme)
+    }
+
+    pub fn text_style(&self) -> Style {
+        Style::default().fg(self.text)
+    }
+
+    pub fn muted_style(&self) -> Style {
+        Style::default().fg(self.muted)
+    }
+
+    pub fn border_style(&self) -> Style {
+        Style::default().fg(self.border)
+    }
+
+    pub fn accent_style(&self) -> Style {
+        Style::default().fg(self.accent)
+    }
+
+    pub fn selected_style(&self) -> Style {
+        Style::default().bg(self.highlight)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ThemeFile {
+    base: Option<String>,
+    text: Option<String>,
+    muted: Option<String>,
+    border: Option<String>,
+    accent: Option<String>,
+    highlight: Option<String>,
+    success: Option<String>,
+    warning: Option<String>,
+    error: Option<String>,
+}
+
+/// Directory holding user themes, one `<name>.toml` file per theme.
+pub fn user_themes_dir() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("themes"))
+}
+
+/// Loads a built-in theme or, failing that, a user theme file by name.
+pub fn load_theme(name: &str) -> Result<Theme> {
+    load_theme_from(name, user_themes_dir().as_deref())
+}
+
+/// Loads a built-in theme or a theme file from `themes_dir` by name.
+fn load_theme_from(name: &str, themes_dir: Option<&Path>) -> Result<Theme> {
+    if let Some(theme) = Theme::builtin(name) {
+        return Ok(theme);
+    }
+
+    let Some(path) = themes_dir.map(|dir| dir.join(format!("{name}.toml"))) else {
+        bail!("Unknown theme: {name}");
+    };
+    if !path.exists() {
+        bail!("Unknown theme: {name}");
+    }
+    let contents = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read theme file {}", path.display()))?;
+    Theme::from_toml(name, &contents)
+        .with_context(|| format!("Invalid theme file {}", path.display()))
+}
+
+/// Lists built-in themes followed by user themes found on disk.
+pub fn available_themes() -> Vec<(String, String)> {
+    let mut themes = BUILTIN_THEMES
+        .iter()
+        .map(|(name, description)| (name.to_string(), description.to_string()))
+        .collect::<Vec<_>>();
+
+    if let Some(entries) = user_themes_dir().and_then(|dir| fs::read_dir(dir).ok()) {
+        let mut user_themes = entries
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+            .filter_map(|path| Some(path.file_stem()?.to_string_lossy().into_owned()))
+            .filter(|name| Theme::builtin(name).is_none())
+            .map(|name| (name, "User theme".to_string()))
+            .collect::<Vec<_>>();
+        user_themes.sort();
+        themes.extend(user_themes);
+    }
+    themes
+}
+
+/// Picks `light` or `dark` from the terminal background advertised in
+/// `COLORFGBG` (e.g. `15;0`), defaulting to `dark` when it is not set.
+pub fn detect_theme() -> Theme {
+    match std::env::var("COLORFGBG") {
+        Ok(value) if is_light_background(&value) => Theme::light(),
+        _ => Theme::dark(),
+    }
+}
+
+fn is_light_background(colorfgbg: &str) -> bool {
+    // The background is the last field. ANSI 7 and 9-15 are light; 8 is
+    // dark gray.
+    colorfgbg
+        .rsplit(';')
+        .next()
+        .and_then(|background| background.parse::<u8>().ok())
+        .is_some_and(|background| background == 7 || (9..=15).contains(&background))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_themes_exist() {
+        // Test every advertised built-in theme can be loaded by name
+        let config_home = foundry_core::testing::temp_dir();
+        let themes_dir = config_home.path().join("foundry/themes");
+        for (name, _) in BUILTIN_THEMES {
+            assert_eq!(
+                load_theme_from(name, Some(&themes_dir)).unwrap().name,
+                *name
+            );
+        }
+        assert!(load_theme_from("no-such-theme", Some(&themes_dir)).is_err());
+
+        fs::create_dir_all(&themes_dir).unwrap();
+        fs::write(themes_dir.join("mine.toml"), "accent = \"red\"").unwrap();
+        assert_eq!(
+            load_theme_from("mine", Some(&themes_dir)).unwrap().accent,
+            Color::Red
+        );
+    }
+
+    #[test]
+    fn test_theme_from_toml() {
+        // Test user themes override colors on top of their base theme
+        let theme = Theme::from_toml("mine", "base = \"light\"\naccent = \"#ff8800\"").unwrap();
+        assert_eq!(theme.name, "mine");
+        assert_eq!(theme.accent, Color::Rgb(0xff, 0x88, 0x00));
+        assert_eq!(theme.error, Theme::light().error);
+
+        assert!(Theme::from_toml("bad", "accent = \"not-a-color\"").is_err());
+        assert!(Theme::from_toml("bad", "accnet = \"red\"").is_err());
+        assert!(Theme::from_toml("bad", "base = \"sepia\"").is_err());
+    }
+
+    #[test]
+    fn test_light_background_detection() {
+        // Test COLORFGBG values map to the expected background brightness
+        assert!(is_light_background("0;15"));
+        assert!(is_light_background("0;default;7"));
+        assert!(!is_light_background("15;0"));
+        assert!(!is_light_background("15;8"));
+        assert!(!is_light_background("garbage"));
+    }
+}
@@ -0,0 +1,136 @@
+use std::time::{Duration, Instant};
+
+use crate::status::format_elapsed;
+
+/// Output lines shown for a collapsed tool call in compact mode.
+pub const TOOL_PREVIEW_LINES: usize = 5;
+
+/// Longest argument summary shown in a tool call header.
+const MAX_ARGS_SUMMARY: usize = 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToolStatus {
+    Running,
+    Succeeded,
+    Failed,
+}
+
+/// A tool invocation shown as a block in the console. The block is
+/// rendered into its console entry whenever it changes.
+#[derive(Debug, Clone)]
+pub struct ToolCall {
+    pub name: String,
+    pub args: String,
+    pub status: ToolStatus,
+    pub started: Instant,
+    pub duration: Option<Duration>,
+    pub output: String,
+    /// Shows the full output even in compact mode.
+    pub expanded: bool,
+    /// Index of the console entry holding this block.
+    pub entry: usize,
+}
+
+impl ToolCall {
+    pub fn new(name: &str, args: &str, entry: usize) -> Self {
+        Self {
+            name: name.to_string(),
+            args: args.to_string(),
+            status: ToolStatus::Running,
+            started: Instant::now(),
+            duration: None,
+            output: String::new(),
+            expanded: false,
+            entry,
+        }
+    }
+
+    pub fn finish(&mut self, succeeded: bool, output: &str) {
+        self.status = if succeeded {
+            ToolStatus::Succeeded
+        } else {
+            ToolStatus::Failed
+        };
+        self.duration = Some(self.started.elapsed());
+        self.output = output.trim_end().to_string();
+    }
+
+    /// `⏺ name(args) · status`, with the arguments squashed onto one line.
+    pub fn header(&self) -> String {
+        let status = match (self.status, self.duration) {
+            (ToolStatus::Running, _) => "running…".to_string(),
+            (ToolStatus::Succeeded, duration) => format_elapsed(duration.unwrap_or_default()),
+            (ToolStatus::Failed, duration) => {
+                format!(
+                    "failed after {}",
+                    format_elapsed(duration.unwrap_or_default())
+                )
+            }
+        };
+        format!("⏺ {}({}) · {status}", self.name, summarize_args(&self.args))
+    }
+
+    /// The block's console text. Compact mode truncates collapsed output to
+    /// `TOOL_PREVIEW_LINES` lines.
+    pub fn render(&self, verbose: bool) -> String {
+        let mut lines = vec![self.header()];
+        let output = self.output.lines().collect::<Vec<_>>();
+        let shown = if verbose || self.expanded {
+            output.len()
+        } else {
+            output.len().min(TOOL_PREVIEW_LINES)
+        };
+        lines.extend(output[..shown].iter().map(|line| format!("  │ {line}")));
+        if shown < output.len() {
+            lines.push(format!("  └ … {} more lines", output.len() - shown));
+        }
+        lines.join("\n")
+    }
+}
+
+fn summarize_args(args: &str) -> String {
+    let squashed = args.split_whitespace().collect::<Vec<_>>().join(" ");
+    if squashed.chars().count() <= MAX_ARGS_SUMMARY {
+        squashed
+    } else {
+        let mut summary = squashed
+            .chars()
+            .take(MAX_ARGS_SUMMARY - 1)
+            .collect::<String>();
+        summary.push('…');
+        summary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_truncates_in_compact_mode() {
+        // Test long output is previewed unless expanded or verbose
+        let mut call = ToolCall::new("grep", "pattern:\n  \"foo\"", 0);
+        assert_eq!(call.render(false), "⏺ grep(pattern: \"foo\") · running…");
+
+        let output = (1..=8).map(|i| format!("match {i}\n")).collect::<String>();
+        call.finish(true, &output);
+        let compact = call.render(false);
+        assert_eq!(compact.lines().count(), 1 + TOOL_PREVIEW_LINES + 1);
+        assert!(compact.contains("3 more lines"));
+        assert!(!compact.contains("match 8"));
+
+        assert!(call.render(true).contains("match 8"));
+        call.expanded = true;
+        assert_eq!(call.render(false).lines().count(), 9);
+    }
+
+    #[test]
+    fn test_header_summarizes_args() {
+        // Test long arguments are cut short and failures are reported
+        let mut call = ToolCall::new("bash", &"x".repeat(100), 0);
+        call.finish(false, "");
+        let header = call.header();
+        assert!(header.contains(&format!("{}…", "x".repeat(MAX_ARGS_SUMMARY - 1))));
+        assert!(header.ends_with("failed after 0s"));
+    }
+}
@@ -18,12 +18,33 @@ pub fn create_main_layout(area: Rect) -> (Layout, Rect) {
     (layout, padded_area)
 }
 
+/// Layout of the live region in inline mode: status, room for popups and
+/// in-progress output, input and instructions. Finished output is printed
+/// into the terminal scrollback above it instead.
+pub fn create_inline_layout() -> Layout {
+    Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3),
+            Constraint::Min(0),
+            Constraint::Length(3),
+            Constraint::Length(1),
+        ])
+}
+
 pub fn create_console_layout(_area: Rect) -> Layout {
     Layout::default()
         .direction(Direction::Vertical)
         .constraints([Constraint::Min(0)])
 }
 
+/// Places a popup of `height` rows directly above `anchor`, clamped to the
+/// space available above it.
+pub fn create_popup_area(anchor: Rect, height: u16) -> Rect {
+    let height = height.min(anchor.y);
+    Rect::new(anchor.x, anchor.y - height, anchor.width, height)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -56,4 +77,23 @@ mod tests {
         assert_eq!(chunks.len(), 1);
         assert_eq!(chunks[0], area);
     }
+
+    #[test]
+    fn test_create_inline_layout() {
+        // Test the inline live region keeps the input and instructions at the bottom
+        let area = Rect::new(0, 30, 80, 12);
+        let chunks = create_inline_layout().split(area);
+        assert_eq!(chunks.len(), 4);
+        assert_eq!(chunks[1].height, 5);
+        assert_eq!(chunks[2], Rect::new(0, 38, 80, 3));
+        assert_eq!(chunks[3].y, 41);
+    }
+
+    #[test]
+    fn test_create_popup_area() {
+        // Test popups sit above the anchor and never extend past the top
+        let anchor = Rect::new(0, 18, 80, 3);
+        assert_eq!(create_popup_area(anchor, 5), Rect::new(0, 13, 80, 5));
+        assert_eq!(create_popup_area(anchor, 40), Rect::new(0, 0, 80, 18));
+    }
 }
@@ -1,5 +1,6 @@
 pub mod layout;
 pub mod render;
+pub mod syntax;
 pub mod widgets;
 
 pub use layout::*;
@@ -1,10 +1,17 @@
 use anyhow::Result;
-use ratatui::{Frame, Terminal, backend::Backend};
+use ratatui::{
+    Frame, Terminal,
+    backend::Backend,
+    layout::Rect,
+    widgets::{Clear, Paragraph, Widget, Wrap},
+};
 
 use crate::app::AppState;
+use crate::completion::MAX_VISIBLE_COMPLETIONS;
 use crate::ui::{
-    create_console_layout, create_console_output, create_input_widget, create_instructions,
-    create_main_layout, create_status_widget,
+    console_scroll_offset, create_completion_popup, create_console_layout, create_console_output,
+    create_diff_widget, create_inline_layout, create_input_widget, create_instructions,
+    create_main_layout, create_popup_area, create_status_widget,
 };
 
 pub fn ui(f: &mut Frame, app: &AppState) {
@@ -14,9 +21,44 @@ pub fn ui(f: &mut Frame, app: &AppState) {
     f.render_widget(create_status_widget(app), chunks[0]);
 
     let console_chunks = create_console_layout(chunks[1]).split(chunks[1]);
-    f.render_widget(create_console_output(app), console_chunks[0]);
+    if let Some(view) = &app.diff_view {
+        let area = console_chunks[0];
+        f.render_widget(create_diff_widget(view, &app.theme, area.width), area);
+    } else {
+        let console = create_console_output(app);
+        let offset = console_scroll_offset(&console, console_chunks[0], app.scroll_back);
+        f.render_widget(console.scroll((offset, 0)), console_chunks[0]);
+    }
+    f.render_widget(create_input_widget(app), chunks[2]);
+    f.render_widget(create_instructions(&app.theme, &app.keymap), chunks[3]);
+    render_completion_popup(f, app, chunks[2]);
+}
+
+/// Draws the live region of inline mode. Unlike `ui`, there is no console:
+/// output is printed into the scrollback with `print_to_scrollback`.
+pub fn inline_ui(f: &mut Frame, app: &AppState) {
+    let chunks = create_inline_layout().split(f.area());
+
+    f.render_widget(create_status_widget(app), chunks[0]);
+    if let Some(view) = &app.diff_view {
+        f.render_widget(
+            create_diff_widget(view, &app.theme, chunks[1].width),
+            chunks[1],
+        );
+    }
     f.render_widget(create_input_widget(app), chunks[2]);
-    f.render_widget(create_instructions(), chunks[3]);
+    f.render_widget(create_instructions(&app.theme, &app.keymap), chunks[3]);
+    render_completion_popup(f, app, chunks[2]);
+}
+
+fn render_completion_popup(f: &mut Frame, app: &AppState, input_area: Rect) {
+    if let Some(popup) = &app.completion {
+        let height = popup.items.len().min(MAX_VISIBLE_COMPLETIONS) as u16 + 2;
+        // The inline viewport does not start at the top of the screen.
+        let area = create_popup_area(input_area, height).intersection(f.area());
+        f.render_widget(Clear, area);
+        f.render_widget(create_completion_popup(popup, &app.theme), area);
+    }
 }
 
 pub fn render_ui<B: Backend>(terminal: &mut Terminal<B>, app: &AppState) -> Result<()> {
@@ -24,11 +66,47 @@ pub fn render_ui<B: Backend>(terminal: &mut Terminal<B>, app: &AppState) -> Resu
     Ok(())
 }
 
+pub fn render_inline_ui<B: Backend>(terminal: &mut Terminal<B>, app: &AppState) -> Result<()> {
+    terminal.draw(|f| inline_ui(f, app))?;
+    Ok(())
+}
+
+/// Prints finished output lines above the inline viewport, where they
+/// become part of the terminal's normal scrollback.
+pub fn print_to_scrollback<B: Backend>(
+    terminal: &mut Terminal<B>,
+    lines: &[String],
+    app: &AppState,
+) -> Result<()> {
+    if lines.is_empty() {
+        return Ok(());
+    }
+    let paragraph = Paragraph::new(lines.join("\n"))
+        .style(app.theme.text_style())
+        .wrap(Wrap { trim: false });
+    let width = terminal.size()?.width;
+    let height = u16::try_from(paragraph.line_count(width)).unwrap_or(u16::MAX);
+    terminal.insert_before(height, |buf| paragraph.render(buf.area, buf))?;
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use ratatui::backend::TestBackend;
 
+    fn buffer_text(terminal: &Terminal<TestBackend>) -> String {
+        let buffer = terminal.backend().buffer();
+        (0..buffer.area.height)
+            .map(|y| {
+                (0..buffer.area.width)
+                    .map(|x| buffer[(x, y)].symbol())
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
     #[test]
     fn test_ui_rendering() {
         let backend = TestBackend::new(80, 24);
@@ -49,11 +127,67 @@ mod tests {
         assert!(result.is_ok());
 
         // Test with populated app state
-        let mut test_app = AppState::default();
-        test_app.input = "test input".to_string();
-        test_app.output_history = vec!["output line".to_string()];
+        let test_app = AppState {
+            input: "test input".to_string(),
+            output_history: vec!["output line".to_string()],
+            ..AppState::default()
+        };
 
         let result = terminal.draw(|f| ui(f, &test_app));
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn test_ui_renders_completion_popup() {
+        let backend = TestBackend::new(80, 24);
+        let mut terminal = Terminal::new(backend).unwrap();
+        let mut app = AppState::default();
+        app.set_input("/lo");
+        app.refresh_completion();
+
+        terminal.draw(|f| ui(f, &app)).unwrap();
+
+        assert!(buffer_text(&terminal).contains("logout  Sign out and forget saved credentials"));
+    }
+
+    #[test]
+    fn test_inline_ui_prints_output_to_scrollback() {
+        // Test inline mode prints output above a live region that keeps the input
+        let backend = TestBackend::new(40, 20);
+        let options = ratatui::TerminalOptions {
+            viewport: ratatui::Viewport::Inline(12),
+        };
+        let mut terminal = Terminal::with_options(backend, options).unwrap();
+        let mut app = AppState::default();
+        app.set_input("draft");
+
+        let lines = vec!["first message".to_string(), "second message".to_string()];
+        print_to_scrollback(&mut terminal, &lines, &app).unwrap();
+        render_inline_ui(&mut terminal, &app).unwrap();
+
+        let rendered = buffer_text(&terminal);
+        assert!(rendered.contains("first message"));
+        assert!(rendered.contains("second message"));
+        assert!(rendered.contains("draft"));
+    }
+
+    #[test]
+    fn test_console_follows_newest_output() {
+        // Test long output scrolls to the bottom unless scrolled back
+        let backend = TestBackend::new(40, 12);
+        let mut terminal = Terminal::new(backend).unwrap();
+        let mut app = AppState {
+            output_history: (1..=30).map(|i| format!("line {i}")).collect(),
+            ..AppState::default()
+        };
+
+        terminal.draw(|f| ui(f, &app)).unwrap();
+        assert!(buffer_text(&terminal).contains("line 30"));
+
+        app.scroll_back = 20;
+        terminal.draw(|f| ui(f, &app)).unwrap();
+        let rendered = buffer_text(&terminal);
+        assert!(!rendered.contains("line 30"));
+        assert!(rendered.contains("line 10"));
+    }
 }
@@ -0,0 +1,251 @@
+use ratatui::{style::Style, text::Span};
+use std::path::Path;
+
+use crate::theme::Theme;
+
+/// Just enough about a language to color keywords, strings, numbers and
+/// line comments.
+#[derive(Debug)]
+pub struct Language {
+    pub line_comment: &'static str,
+    pub keywords: &'static [&'static str],
+    /// Whether single quotes delimit strings rather than e.g. lifetimes.
+    pub single_quoted_strings: bool,
+}
+
+const RUST: Language = Language {
+    line_comment: "//",
+    keywords: &[
+        "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum",
+        "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut",
+        "pub", "ref", "return", "self", "Self", "static", "struct", "super", "trait", "true",
+        "type", "unsafe", "use", "where", "while",
+    ],
+    single_quoted_strings: false,
+};
+
+const PYTHON: Language = Language {
+    line_comment: "#",
+    keywords: &[
+        "and", "as", "async", "await", "class", "def", "elif", "else", "except", "False",
+        "finally", "for", "from", "if", "import", "in", "is", "lambda", "None", "not", "or",
+        "pass", "raise", "return", "True", "try", "while", "with", "yield",
+    ],
+    single_quoted_strings: true,
+};
+
+const JAVASCRIPT: Language = Language {
+    line_comment: "//",
+    keywords: &[
+        "async",
+        "await",
+        "break",
+        "case",
+        "class",
+        "const",
+        "continue",
+        "default",
+        "else",
+        "export",
+        "extends",
+        "false",
+        "for",
+        "from",
+        "function",
+        "if",
+        "import",
+        "in",
+        "interface",
+        "let",
+        "new",
+        "null",
+        "return",
+        "switch",
+        "this",
+        "throw",
+        "true",
+        "try",
+        "type",
+        "typeof",
+        "undefined",
+        "var",
+        "while",
+    ],
+    single_quoted_strings: true,
+};
+
+const GO: Language = Language {
+    line_comment: "//",
+    keywords: &[
+        "break",
+        "case",
+        "chan",
+        "const",
+        "continue",
+        "default",
+        "defer",
+        "else",
+        "false",
+        "for",
+        "func",
+        "go",
+        "if",
+        "import",
+        "interface",
+        "map",
+        "nil",
+        "package",
+        "range",
+        "return",
+        "select",
+        "struct",
+        "switch",
+        "true",
+        "type",
+        "var",
+    ],
+    single_quoted_strings: false,
+};
+
+const SHELL: Language = Language {
+    line_comment: "#",
+    keywords: &[
+        "case", "do", "done", "elif", "else", "esac", "export", "fi", "for", "function", "if",
+        "in", "local", "return", "then", "while",
+    ],
+    single_quoted_strings: true,
+};
+
+const CONFIG: Language = Language {
+    line_comment: "#",
+    keywords: &["true", "false", "null"],
+    single_quoted_strings: true,
+};
+
+/// Picks a language from the file extension.
+pub fn language_for(path: &Path) -> Option<&'static Language> {
+    let extension = path.extension()?.to_str()?;
+    match extension {
+        "rs" => Some(&RUST),
+        "py" => Some(&PYTHON),
+        "js" | "jsx" | "ts" | "tsx" | "mjs" | "cjs" => Some(&JAVASCRIPT),
+        "go" => Some(&GO),
+        "sh" | "bash" | "zsh" => Some(&SHELL),
+        "toml" | "yaml" | "yml" => Some(&CONFIG),
+        _ => None,
+    }
+}
+
+/// Splits `text` into colored spans. Without a language the text is
+/// returned as a single span in `base` style.
+pub fn highlight(
+    text: &str,
+    language: Option<&Language>,
+    base: Style,
+    theme: &Theme,
+) -> Vec<Span<'static>> {
+    let Some(language) = language else {
+        return vec![Span::styled(text.to_string(), base)];
+    };
+
+    let mut spans = Vec::new();
+    let mut rest = text;
+    while !rest.is_empty() {
+        let first = rest.chars().next().unwrap_or_default();
+        let (token, style) = if rest.starts_with(language.line_comment) {
+            (rest, base.fg(theme.muted))
+        } else if first == '"' || (first == '\'' && language.single_quoted_strings) {
+            (quoted(rest, first), base.fg(theme.success))
+        } else if first.is_ascii_digit() {
+            (
+                take_while(rest, |c| c.is_ascii_alphanumeric() || c == '.' || c == '_'),
+                base.fg(theme.warning),
+            )
+        } else if first.is_alphabetic() || first == '_' {
+            let word = take_while(rest, |c| c.is_alphanumeric() || c == '_');
+            if language.keywords.contains(&word) {
+                (word, base.fg(theme.accent))
+            } else {
+                (word, base)
+            }
+        } else {
+            // Punctuation and whitespace, up to the next token of interest.
+            let end = rest
+                .char_indices()
+                .skip(1)
+                .find(|(index, c)| {
+                    c.is_alphanumeric()
+                        || matches!(c, '_' | '"' | '\'')
+                        || rest[*index..].starts_with(language.line_comment)
+                })
+                .map_or(rest.len(), |(index, _)| index);
+            (&rest[..end], base)
+        };
+        spans.push(Span::styled(token.to_string(), style));
+        rest = &rest[token.len()..];
+    }
+    spans
+}
+
+fn take_while(text: &str, predicate: impl Fn(char) -> bool) -> &str {
+    let end = text
+        .char_indices()
+        .find(|(_, c)| !predicate(*c))
+        .map_or(text.len(), |(index, _)| index);
+    &text[..end]
+}
+
+/// The string literal at the start of `text`, up to and including the
+/// closing quote, or the rest of the line if it is not closed.
+fn quoted(text: &str, quote: char) -> &str {
+    let mut escaped = false;
+    for (index, c) in text.char_indices().skip(1) {
+        match c {
+            '\\' if !escaped => escaped = true,
+            c if c == quote && !escaped => return &text[..index + c.len_utf8()],
+            _ => escaped = false,
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_highlight_rust() {
+        // Test keywords, strings, numbers and comments get their own spans
+        let theme = Theme::dark();
+        let spans = highlight(
+            "let s = \"a \\\" b\"; // 42",
+            language_for(Path::new("main.rs")),
+            Style::default(),
+            &theme,
+        );
+        let text = spans
+            .iter()
+            .map(|span| span.content.as_ref())
+            .collect::<String>();
+        assert_eq!(text, "let s = \"a \\\" b\"; // 42");
+
+        let styled = |content: &str| {
+            spans
+                .iter()
+                .find(|span| span.content == content)
+                .map(|span| span.style.fg)
+        };
+        assert_eq!(styled("let"), Some(Some(theme.accent)));
+        assert_eq!(styled("\"a \\\" b\""), Some(Some(theme.success)));
+        assert_eq!(styled("// 42"), Some(Some(theme.muted)));
+        assert_eq!(styled("s"), Some(None));
+    }
+
+    #[test]
+    fn test_highlight_unknown_language() {
+        // Test files without a known language are left uncolored
+        assert!(language_for(Path::new("README")).is_none());
+        let spans = highlight("let x", None, Style::default(), &Theme::dark());
+        assert_eq!(spans.len(), 1);
+    }
+}
@@ -1,41 +1,273 @@
+use foundry_core::paths::display_path;
 use ratatui::{
-    style::{Color, Style},
+    layout::Rect,
+    style::{Modifier, Style},
     text::{Line, Span, Text},
-    widgets::{Block, Borders, Padding, Paragraph, Wrap},
+    widgets::{Block, Borders, List, ListItem, Padding, Paragraph, Wrap},
 };
 
+use std::ops::Range;
+
 use crate::app::AppState;
+use crate::completion::{CompletionKind, CompletionPopup, MAX_VISIBLE_COMPLETIONS};
+use crate::diff::{DiffLayout, DiffLine, DiffLineKind, DiffPurpose, DiffView, HunkDecision};
+use crate::keymap::{Action, Keymap};
+use crate::search::ConsoleSearch;
+use crate::status::{Activity, ConnectionState, format_elapsed, format_tokens};
+use crate::theme::Theme;
+use crate::tool_call::ToolStatus;
+use crate::ui::syntax::{Language, highlight, language_for};
+use crate::vim::VimMode;
+
+fn themed_block(theme: &Theme) -> Block<'static> {
+    Block::default()
+        .borders(Borders::ALL)
+        .border_style(theme.border_style())
+}
+
+/// The live status bar: current activity (or the latest status message)
+/// inside, connection and model on the top border, and usage and location
+/// on the bottom border.
+pub fn create_status_widget(app: &AppState) -> Paragraph<'_> {
+    let status = &app.status_bar;
+    let content = match status.elapsed() {
+        Some(elapsed) if status.activity != Activity::Idle => Line::from(vec![
+            Span::styled(format!("{} ", status.spinner()), app.theme.accent_style()),
+            Span::styled(status.activity.label(), app.theme.text_style()),
+            Span::styled(
+                format!(" ({})", format_elapsed(elapsed)),
+                app.theme.muted_style(),
+            ),
+        ]),
+        _ => Line::styled(app.status_text.clone(), app.theme.accent_style()),
+    };
+
+    let connection_color = match status.connection {
+        ConnectionState::Connected => app.theme.success,
+        ConnectionState::Connecting => app.theme.warning,
+        ConnectionState::SignedOut | ConnectionState::Offline => app.theme.error,
+    };
+    let mut connection = vec![
+        Span::styled(" ● ", Style::default().fg(connection_color)),
+        Span::styled(status.connection.label(), app.theme.muted_style()),
+    ];
+    // Protected profiles, e.g. production, stand out.
+    if let Some(profile) = &status.profile {
+        connection.push(Span::styled(" · ", app.theme.muted_style()));
+        connection.push(if status.profile_protected {
+            Span::styled(
+                format!("{profile} (protected)"),
+                Style::default().fg(app.theme.warning),
+            )
+        } else {
+            Span::styled(profile.clone(), app.theme.accent_style())
+        });
+    }
+    connection.push(Span::styled(
+        format!(" · {} ", status.model.as_deref().unwrap_or("no model")),
+        app.theme.muted_style(),
+    ));
+    let connection = Line::from(connection).right_aligned();
+
+    let mut location = display_path(&status.cwd);
+    if let Some(branch) = &status.git_branch {
+        location.push_str(&format!(" ({branch})"));
+    }
+    let usage = Line::styled(
+        format!(
+            " {} in / {} out · ${:.4} · {location} ",
+            format_tokens(status.input_tokens),
+            format_tokens(status.output_tokens),
+            status.cost
+        ),
+        app.theme.muted_style(),
+    )
+    .right_aligned();
 
-pub fn create_status_widget(app: &AppState) -> Paragraph {
-    Paragraph::new(app.status_text.clone())
+    Paragraph::new(content)
         .block(
-            Block::default()
-                .borders(Borders::ALL)
-                .padding(Padding::new(1, 0, 0, 0)),
+            themed_block(&app.theme)
+                .padding(Padding::new(1, 0, 0, 0))
+                .title(connection)
+                .title_bottom(usage),
         )
         .wrap(Wrap { trim: true })
 }
 
-pub fn create_console_output(app: &AppState) -> Paragraph {
-    let output_text = app
-        .output_history
-        .iter()
-        .map(|line| line.to_string())
-        .collect::<Vec<_>>()
-        .join("\n");
+pub fn create_console_output(app: &AppState) -> Paragraph<'_> {
+    let search_matches = app
+        .console_search
+        .as_ref()
+        .map(ConsoleSearch::matches_by_line)
+        .unwrap_or_default();
+    let mut lines = Vec::new();
+    for (index, entry) in app.output_history.iter().enumerate() {
+        // The focused message or code block is highlighted for copying.
+        let focused = app
+            .focus
+            .filter(|focus| focus.message() == index)
+            .map(|focus| focus.line_range(&app.output_history));
+        // Tool call blocks get a colored header and a muted body.
+        let tool_status = app
+            .tool_calls
+            .iter()
+            .find(|call| call.entry == index)
+            .map(|call| call.status);
+        for (line_index, line) in entry.split('\n').enumerate() {
+            let style = match tool_status {
+                Some(ToolStatus::Running) if line_index == 0 => app.theme.accent_style(),
+                Some(ToolStatus::Succeeded) if line_index == 0 => {
+                    Style::default().fg(app.theme.success)
+                }
+                Some(ToolStatus::Failed) if line_index == 0 => Style::default().fg(app.theme.error),
+                Some(_) => app.theme.muted_style(),
+                None => Style::default(),
+            };
+            let style = if focused
+                .as_ref()
+                .is_some_and(|range| range.contains(&line_index))
+            {
+                app.theme.selected_style()
+            } else {
+                style
+            };
+            let matches = search_matches
+                .get(&(index, line_index))
+                .map_or(&[][..], Vec::as_slice);
+            lines.push(search_highlighted_line(line, style, matches, &app.theme));
+        }
+    }
 
-    Paragraph::new(output_text)
-        .block(
-            Block::default()
-                .borders(Borders::ALL)
-                .padding(Padding::new(1, 0, 0, 0)),
-        )
+    Paragraph::new(Text::from(lines))
+        .style(app.theme.text_style())
+        .block(themed_block(&app.theme).padding(Padding::new(1, 0, 0, 0)))
         .wrap(Wrap { trim: true })
-        .scroll((0, 0))
 }
 
-pub fn create_input_widget(app: &AppState) -> Paragraph {
-    let input_with_cursor = if app.cursor_position <= app.input.len() {
+/// A console line with search matches reversed; the current match also
+/// stands out in the warning color. Matches that no longer fit the line,
+/// e.g. because the output changed since the search ran, are skipped.
+fn search_highlighted_line(
+    line: &str,
+    style: Style,
+    matches: &[(Range<usize>, bool)],
+    theme: &Theme,
+) -> Line<'static> {
+    if matches.is_empty() {
+        return Line::styled(line.to_string(), style);
+    }
+
+    let mut spans = Vec::new();
+    let mut position = 0;
+    for (range, current) in matches {
+        let (Some(before), Some(found)) =
+            (line.get(position..range.start), line.get(range.clone()))
+        else {
+            continue;
+        };
+        if !before.is_empty() {
+            spans.push(Span::styled(before.to_string(), style));
+        }
+        let highlight = if *current {
+            style
+                .fg(theme.warning)
+                .add_modifier(Modifier::REVERSED | Modifier::BOLD)
+        } else {
+            style.add_modifier(Modifier::REVERSED)
+        };
+        spans.push(Span::styled(found.to_string(), highlight));
+        position = range.end;
+    }
+    if position < line.len() {
+        spans.push(Span::styled(line[position..].to_string(), style));
+    }
+    Line::from(spans)
+}
+
+/// Width left for console text in a console `width` columns wide. Borders
+/// take a column on each side, plus one column of left padding.
+pub fn console_text_width(width: u16) -> u16 {
+    width.saturating_sub(3)
+}
+
+/// Vertical scroll offset that keeps the newest console output in view,
+/// moved up by the lines the user has scrolled back.
+pub fn console_scroll_offset(console: &Paragraph, area: Rect, scroll_back: usize) -> u16 {
+    let total_lines = console.line_count(console_text_width(area.width));
+    let offset = total_lines
+        .saturating_sub(area.height as usize)
+        .saturating_sub(scroll_back);
+    u16::try_from(offset).unwrap_or(u16::MAX)
+}
+
+pub fn create_input_widget(app: &AppState) -> Paragraph<'_> {
+    if let Some(search) = &app.reverse_search {
+        let matched = search
+            .match_index
+            .and_then(|index| app.history.get(index))
+            .unwrap_or_default();
+        let prompt = format!(" (reverse-i-search)`{}': {matched}", search.query);
+        return Paragraph::new(prompt)
+            .style(app.theme.text_style())
+            .block(themed_block(&app.theme));
+    }
+
+    if let Some(search) = &app.console_search {
+        let mode = if search.regex { "regex" } else { "text" };
+        let hint = if search.editing {
+            "Enter: done · Tab: regex · ↑/↓: older/newer"
+        } else {
+            "n/N: older/newer · /: edit · Esc: close"
+        };
+        let caret = if search.editing { "_" } else { "" };
+        let mut prompt = vec![Span::raw(format!(
+            " (search {mode})`{}{caret}': {}",
+            search.query,
+            search.position()
+        ))];
+        if let Some(error) = &search.error {
+            prompt.push(Span::styled(
+                format!("  {}", error.lines().last().unwrap_or_default()),
+                Style::default().fg(app.theme.error),
+            ));
+        }
+        let title = Span::styled(format!(" {hint} "), app.theme.muted_style());
+        return Paragraph::new(Line::from(prompt))
+            .style(app.theme.text_style())
+            .block(themed_block(&app.theme).title(title));
+    }
+
+    if let Some(vim) = &app.vim {
+        let title = Line::from(vec![
+            Span::styled(format!(" {} ", vim.mode.label()), app.theme.accent_style()),
+            Span::styled(vim.pending(), app.theme.muted_style()),
+        ]);
+        let block = themed_block(&app.theme).title(title);
+
+        // Outside insert mode the cursor sits on a character, so highlight
+        // it (or the visual selection) instead of drawing a caret.
+        if vim.mode != VimMode::Insert {
+            let (start, end) = vim
+                .selection(app.cursor_position)
+                .unwrap_or((app.cursor_position, app.cursor_position));
+            return Paragraph::new(highlight_range(&app.input, start, end, &app.theme))
+                .style(app.theme.text_style())
+                .block(block);
+        }
+        return Paragraph::new(format!(" {}", input_with_caret(app)))
+            .style(app.theme.text_style())
+            .block(block);
+    }
+
+    let formatted_input = format!(" {}", input_with_caret(app));
+
+    Paragraph::new(formatted_input)
+        .style(app.theme.text_style())
+        .block(themed_block(&app.theme))
+}
+
+fn input_with_caret(app: &AppState) -> String {
+    if app.cursor_position <= app.input.len() {
         let mut chars: Vec<char> = app.input.chars().collect();
         if app.cursor_position == chars.len() {
             chars.push('_');
@@ -45,25 +277,304 @@ pub fn create_input_widget(app: &AppState) -> Paragraph {
         chars.into_iter().collect()
     } else {
         format!("{}_", app.input)
+    }
+}
+
+/// Renders `text` with the inclusive character range `start..=end` selected.
+fn highlight_range(text: &str, start: usize, end: usize, theme: &Theme) -> Text<'static> {
+    let chars = text.chars().collect::<Vec<_>>();
+    let mut lines = vec![Line::from(" ")];
+
+    // One extra cell past the end lets the cursor show on an empty input.
+    for index in 0..=chars.len() {
+        let selected = (start..=end).contains(&index);
+        let c = chars.get(index).copied();
+        if c.is_none() && !selected {
+            break;
+        }
+
+        let style = if selected {
+            theme.selected_style()
+        } else {
+            theme.text_style()
+        };
+        if let Some(line) = lines.last_mut().filter(|_| c != Some('\n') || selected) {
+            let symbol = c.filter(|&c| c != '\n').unwrap_or(' ');
+            line.push_span(Span::styled(symbol.to_string(), style));
+        }
+        if c == Some('\n') {
+            lines.push(Line::from(" "));
+        }
+    }
+    Text::from(lines)
+}
+
+pub fn create_completion_popup<'a>(popup: &'a CompletionPopup, theme: &Theme) -> List<'a> {
+    let width = popup
+        .items
+        .iter()
+        .map(|item| item.value.chars().count())
+        .max()
+        .unwrap_or(0);
+
+    let items = popup
+        .items
+        .iter()
+        .enumerate()
+        .skip(popup.scroll_offset())
+        .take(MAX_VISIBLE_COMPLETIONS)
+        .map(|(index, item)| {
+            let line = Line::from(vec![
+                Span::styled(format!("{:<width$}", item.value), theme.text_style()),
+                Span::styled(format!("  {}", item.description), theme.muted_style()),
+            ]);
+            if index == popup.selected {
+                ListItem::new(line).style(theme.selected_style())
+            } else {
+                ListItem::new(line)
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let title = match popup.kind {
+        CompletionKind::File => " Files ",
+        CompletionKind::Command | CompletionKind::Argument => "",
+    };
+
+    List::new(items).block(themed_block(theme).title(title))
+}
+
+/// Renders the diff viewer, scrolled so the selected hunk is at the top.
+/// `width` is the width of the area, used to split side-by-side columns.
+pub fn create_diff_widget(view: &DiffView, theme: &Theme, width: u16) -> Paragraph<'static> {
+    // Half of the space inside the borders, less the column separator.
+    let column_width = usize::from(width.saturating_sub(3) / 2);
+    let mut lines = Vec::new();
+    let mut selected_line = 0;
+
+    for (file_index, file) in view.files.iter().enumerate() {
+        let (added, removed) = file.stats();
+        lines.push(Line::from(vec![
+            Span::styled(format!("{} ", file.path.display()), theme.accent_style()),
+            Span::styled(format!("+{added}"), Style::default().fg(theme.success)),
+            Span::styled(format!(" -{removed}"), Style::default().fg(theme.error)),
+        ]));
+        let language = language_for(&file.path);
+
+        for (hunk_index, (hunk, decision)) in file.hunks.iter().zip(&file.decisions).enumerate() {
+            let selected = file_index == view.file && hunk_index == view.hunk;
+            if selected {
+                // Keep the file name in view when its first hunk is selected.
+                selected_line = lines.len() - usize::from(hunk_index == 0);
+            }
+            let badge = match (view.purpose, decision) {
+                (DiffPurpose::Session, _) => "",
+                (_, HunkDecision::Pending) => "  [pending]",
+                (_, HunkDecision::Accepted) => "  [accepted]",
+                (_, HunkDecision::Rejected) => "  [rejected]",
+            };
+            let header = Line::styled(format!("{}{badge}", hunk.header()), theme.muted_style());
+            lines.push(if selected {
+                header.patch_style(theme.selected_style())
+            } else {
+                header
+            });
+
+            match view.layout {
+                DiffLayout::Unified => lines.extend(
+                    hunk.lines
+                        .iter()
+                        .map(|line| unified_diff_line(line, language, theme)),
+                ),
+                DiffLayout::SideBySide => lines.extend(side_by_side_lines(
+                    &hunk.lines,
+                    language,
+                    theme,
+                    column_width,
+                )),
+            }
+        }
+    }
+
+    let title = match view.purpose {
+        DiffPurpose::Review => " Review edits ",
+        DiffPurpose::Session => " Session changes ",
+    };
+    let keys = match view.purpose {
+        DiffPurpose::Review => {
+            " n/p: hunk | a/r: accept/reject | A/R: all | s: layout | Enter: apply | Esc: discard "
+        }
+        DiffPurpose::Session => " n/p: hunk | s: layout | Esc: close ",
+    };
+    Paragraph::new(Text::from(lines))
+        .style(theme.text_style())
+        .block(
+            themed_block(theme)
+                .title(Span::styled(title, theme.accent_style()))
+                .title_bottom(Span::styled(keys, theme.muted_style())),
+        )
+        .scroll((u16::try_from(selected_line).unwrap_or(u16::MAX), 0))
+}
+
+fn diff_sign(kind: DiffLineKind, theme: &Theme) -> Span<'static> {
+    match kind {
+        DiffLineKind::Context => Span::styled("  ", theme.muted_style()),
+        DiffLineKind::Added => Span::styled("+ ", Style::default().fg(theme.success)),
+        DiffLineKind::Removed => Span::styled("- ", Style::default().fg(theme.error)),
+    }
+}
+
+/// Syntax-colored spans for a diff line, with changed words reversed in the
+/// color of the change.
+fn diff_line_spans(
+    line: &DiffLine,
+    language: Option<&Language>,
+    theme: &Theme,
+) -> Vec<Span<'static>> {
+    let change_color = match line.kind {
+        DiffLineKind::Added => theme.success,
+        DiffLineKind::Removed => theme.error,
+        DiffLineKind::Context => theme.text,
+    };
+    line.segments
+        .iter()
+        .flat_map(|(emphasized, text)| {
+            if *emphasized {
+                let style = Style::default()
+                    .fg(change_color)
+                    .add_modifier(Modifier::REVERSED);
+                vec![Span::styled(text.clone(), style)]
+            } else {
+                highlight(text, language, theme.text_style(), theme)
+            }
+        })
+        .collect()
+}
+
+fn unified_diff_line(line: &DiffLine, language: Option<&Language>, theme: &Theme) -> Line<'static> {
+    let number = |n: Option<usize>| n.map_or_else(|| "    ".to_string(), |n| format!("{n:>4}"));
+    let mut spans = vec![
+        Span::styled(
+            format!("{} {} ", number(line.old_line), number(line.new_line)),
+            theme.muted_style(),
+        ),
+        diff_sign(line.kind, theme),
+    ];
+    spans.extend(diff_line_spans(line, language, theme));
+    Line::from(spans)
+}
+
+/// Pairs removed lines with the added lines that replace them, so each row
+/// shows the old text on the left and the new text on the right.
+fn side_by_side_lines(
+    lines: &[DiffLine],
+    language: Option<&Language>,
+    theme: &Theme,
+    column_width: usize,
+) -> Vec<Line<'static>> {
+    let mut rows = Vec::new();
+    let mut index = 0;
+    while index < lines.len() {
+        if lines[index].kind == DiffLineKind::Context {
+            rows.push((Some(&lines[index]), Some(&lines[index])));
+            index += 1;
+            continue;
+        }
+        let removed = lines[index..]
+            .iter()
+            .take_while(|line| line.kind == DiffLineKind::Removed)
+            .collect::<Vec<_>>();
+        let added = lines[index + removed.len()..]
+            .iter()
+            .take_while(|line| line.kind == DiffLineKind::Added)
+            .collect::<Vec<_>>();
+        for row in 0..removed.len().max(added.len()) {
+            rows.push((removed.get(row).copied(), added.get(row).copied()));
+        }
+        index += removed.len() + added.len();
+    }
+
+    let column = |line: Option<&DiffLine>, number: fn(&DiffLine) -> Option<usize>| {
+        let mut spans = match line {
+            Some(line) => {
+                let mut spans = vec![
+                    Span::styled(
+                        number(line).map_or_else(|| "     ".to_string(), |n| format!("{n:>4} ")),
+                        theme.muted_style(),
+                    ),
+                    diff_sign(line.kind, theme),
+                ];
+                spans.extend(diff_line_spans(line, language, theme));
+                spans
+            }
+            None => Vec::new(),
+        };
+        fit_spans(&mut spans, column_width);
+        spans
     };
 
-    let formatted_input = format!(" {input_with_cursor}");
+    rows.into_iter()
+        .map(|(left, right)| {
+            let mut spans = column(left, |line| line.old_line);
+            spans.push(Span::styled("│", theme.border_style()));
+            spans.extend(column(right, |line| line.new_line));
+            Line::from(spans)
+        })
+        .collect()
+}
 
-    Paragraph::new(formatted_input).block(Block::default().borders(Borders::ALL))
+/// Truncates or pads `spans` to exactly `width` characters.
+fn fit_spans(spans: &mut Vec<Span<'static>>, width: usize) {
+    let mut remaining = width;
+    for span in spans.iter_mut() {
+        let length = span.content.chars().count();
+        if length > remaining {
+            span.content = span
+                .content
+                .chars()
+                .take(remaining)
+                .collect::<String>()
+                .into();
+        }
+        remaining -= span.content.chars().count();
+    }
+    spans.retain(|span| !span.content.is_empty());
+    if remaining > 0 {
+        spans.push(Span::raw(" ".repeat(remaining)));
+    }
 }
 
-pub fn create_instructions() -> Paragraph<'static> {
-    let instructions = Text::from(vec![Line::from(vec![
-        Span::styled(" ", Style::default().fg(Color::DarkGray)),
-        Span::styled("Enter", Style::default().fg(Color::DarkGray)),
-        Span::styled(": Execute command | ", Style::default().fg(Color::DarkGray)),
-        Span::styled("←→", Style::default().fg(Color::DarkGray)),
-        Span::styled(": Move cursor | ", Style::default().fg(Color::DarkGray)),
-        Span::styled("Esc", Style::default().fg(Color::DarkGray)),
-        Span::styled(": Exit", Style::default().fg(Color::DarkGray)),
-    ])]);
+/// Actions advertised in the instructions bar, with the label shown after
+/// their keys. Grouped actions show their primary keys side by side.
+const INSTRUCTION_ACTIONS: &[(&[Action], &str)] = &[
+    (&[Action::Submit], "Execute command"),
+    (&[Action::MoveLeft, Action::MoveRight], "Move cursor"),
+    (&[Action::MoveUp, Action::MoveDown], "History"),
+    (&[Action::ReverseSearch], "Search"),
+    (&[Action::Complete], "Complete"),
+    (&[Action::Quit], "Exit"),
+];
+
+pub fn create_instructions(theme: &Theme, keymap: &Keymap) -> Paragraph<'static> {
+    let muted = theme.muted_style();
+    let mut spans = vec![Span::styled(" ", muted)];
 
-    Paragraph::new(instructions)
+    let entries = INSTRUCTION_ACTIONS.iter().filter_map(|(actions, label)| {
+        let keys = actions
+            .iter()
+            .filter_map(|action| keymap.primary_key(*action))
+            .collect::<String>();
+        (!keys.is_empty()).then_some((keys, label))
+    });
+    for (index, (keys, label)) in entries.enumerate() {
+        if index > 0 {
+            spans.push(Span::styled(" | ", muted));
+        }
+        spans.push(Span::styled(keys, muted));
+        spans.push(Span::styled(format!(": {label}"), muted));
+    }
+
+    Paragraph::new(Text::from(vec![Line::from(spans)]))
 }
 
 #[cfg(test)]
@@ -76,8 +587,10 @@ mod tests {
         let widget = create_status_widget(&app);
 
         // Test with custom status text
-        let mut custom_app = AppState::default();
-        custom_app.status_text = "Custom status".to_string();
+        let custom_app = AppState {
+            status_text: "Custom status".to_string(),
+            ..AppState::default()
+        };
         let custom_widget = create_status_widget(&custom_app);
 
         assert!(std::mem::size_of_val(&widget) > 0);
@@ -90,8 +603,10 @@ mod tests {
         let empty_widget = create_console_output(&app);
 
         // Test with populated history
-        let mut populated_app = AppState::default();
-        populated_app.output_history = vec!["line1".to_string(), "line2".to_string()];
+        let populated_app = AppState {
+            output_history: vec!["line1".to_string(), "line2".to_string()],
+            ..AppState::default()
+        };
         let populated_widget = create_console_output(&populated_app);
 
         assert!(std::mem::size_of_val(&empty_widget) > 0);
@@ -101,15 +616,19 @@ mod tests {
     #[test]
     fn test_create_input_widget() {
         // Test with cursor in middle
-        let mut app = AppState::default();
-        app.input = "test".to_string();
-        app.cursor_position = 2;
+        let app = AppState {
+            input: "test".to_string(),
+            cursor_position: 2,
+            ..AppState::default()
+        };
         let widget_mid_cursor = create_input_widget(&app);
 
         // Test with cursor at end
-        let mut end_app = AppState::default();
-        end_app.input = "test".to_string();
-        end_app.cursor_position = end_app.input.len();
+        let end_app = AppState {
+            input: "test".to_string(),
+            cursor_position: "test".len(),
+            ..AppState::default()
+        };
         let widget_end_cursor = create_input_widget(&end_app);
 
         // Test with empty input
@@ -123,8 +642,8 @@ mod tests {
 
     #[test]
     fn test_create_instructions() {
-        let widget = create_instructions();
-        let widget2 = create_instructions();
+        let widget = create_instructions(&Theme::dark(), &Keymap::default());
+        let widget2 = create_instructions(&Theme::light(), &Keymap::default());
 
         assert!(std::mem::size_of_val(&widget) > 0);
         assert!(std::mem::size_of_val(&widget2) > 0);
@@ -134,4 +653,137 @@ mod tests {
             std::mem::size_of_val(&widget2)
         );
     }
+
+    #[test]
+    fn test_create_completion_popup() {
+        let popup = CompletionPopup::for_input("/", 1, &[]).unwrap();
+        let widget = create_completion_popup(&popup, &Theme::default());
+
+        assert_eq!(widget.len(), popup.items.len().min(MAX_VISIBLE_COMPLETIONS));
+    }
+
+    #[test]
+    fn test_create_input_widget_vim_modes() {
+        // Test vi mode renders in every mode, including an empty input
+        let mut app = AppState {
+            vim: Some(crate::vim::Vim::default()),
+            ..AppState::default()
+        };
+        assert!(std::mem::size_of_val(&create_input_widget(&app)) > 0);
+
+        app.set_input("hello\nworld");
+        app.vim.as_mut().unwrap().mode = VimMode::Normal;
+        assert!(std::mem::size_of_val(&create_input_widget(&app)) > 0);
+
+        let text = highlight_range("ab\ncd", 1, 3, &Theme::default());
+        assert_eq!(text.lines.len(), 2);
+        assert_eq!(highlight_range("", 0, 0, &Theme::default()).width(), 2);
+    }
+
+    #[test]
+    fn test_create_diff_widget_layouts() {
+        // Test both diff layouts render every row, starting at the selected hunk
+        use crate::diff::FileDiff;
+        let file = FileDiff::new("main.rs", "let a = 1;\n", "let a = 2;\nlet b = 3;\n");
+        let mut view = DiffView::new(vec![file], DiffPurpose::Review);
+
+        let unified = create_diff_widget(&view, &Theme::default(), 60);
+        // Two rows of the count are the block borders.
+        assert_eq!(unified.line_count(58), 7);
+
+        view.toggle_layout();
+        let side_by_side = create_diff_widget(&view, &Theme::default(), 60);
+        assert_eq!(side_by_side.line_count(58), 6);
+
+        let mut spans = vec![Span::raw("abc"), Span::raw("def")];
+        fit_spans(&mut spans, 4);
+        assert_eq!(
+            spans
+                .iter()
+                .map(|span| span.content.as_ref())
+                .collect::<String>(),
+            "abcd"
+        );
+        fit_spans(&mut spans, 6);
+        assert_eq!(
+            spans
+                .iter()
+                .map(|span| span.content.as_ref())
+                .collect::<String>(),
+            "abcd  "
+        );
+    }
+
+    #[test]
+    fn test_status_widget_shows_activity() {
+        // Test the status bar shows the spinner and activity while busy
+        use ratatui::{Terminal, backend::TestBackend};
+        let mut app = AppState::default();
+        app.status_bar.model = Some("gpt-4o".to_string());
+        app.status_bar.add_usage(1_500, 20, 0.01);
+        app.status_bar.start_activity(Activity::Thinking);
+
+        let mut terminal = Terminal::new(TestBackend::new(80, 3)).unwrap();
+        terminal
+            .draw(|f| f.render_widget(create_status_widget(&app), f.area()))
+            .unwrap();
+        let buffer = terminal.backend().buffer();
+        let rendered = (0..3)
+            .map(|y| (0..80).map(|x| buffer[(x, y)].symbol()).collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n");
+        assert!(rendered.contains("⠋ Thinking (0s)"));
+        assert!(rendered.contains("signed out · gpt-4o"));
+        assert!(rendered.contains("1.5k in / 20 out · $0.0100"));
+
+        app.status_bar.profile = Some("prod".to_string());
+        app.status_bar.profile_protected = true;
+        terminal
+            .draw(|f| f.render_widget(create_status_widget(&app), f.area()))
+            .unwrap();
+        let buffer = terminal.backend().buffer();
+        let top = (0..80).map(|x| buffer[(x, 0)].symbol()).collect::<String>();
+        assert!(top.contains("signed out · prod (protected) · gpt-4o"));
+    }
+
+    #[test]
+    fn test_search_highlighted_line() {
+        // Test matches are split into reversed spans with the current one in bold
+        let theme = Theme::dark();
+        let line = search_highlighted_line(
+            "an error and another error",
+            Style::default(),
+            &[(3..8, false), (21..26, true)],
+            &theme,
+        );
+        let contents = line
+            .spans
+            .iter()
+            .map(|span| span.content.as_ref())
+            .collect::<Vec<_>>();
+        assert_eq!(contents, vec!["an ", "error", " and another ", "error"]);
+        assert!(
+            line.spans[1]
+                .style
+                .add_modifier
+                .contains(Modifier::REVERSED)
+        );
+        assert!(!line.spans[1].style.add_modifier.contains(Modifier::BOLD));
+        assert!(line.spans[3].style.add_modifier.contains(Modifier::BOLD));
+        assert_eq!(line.spans[3].style.fg, Some(theme.warning));
+
+        // Ranges from an older version of the line are dropped, not sliced.
+        let line = search_highlighted_line(
+            "naïve",
+            Style::default(),
+            &[(1..3, false), (3..4, false), (4..40, true)],
+            &theme,
+        );
+        let text = line
+            .spans
+            .iter()
+            .map(|span| span.content.as_ref())
+            .collect::<String>();
+        assert_eq!(text, "naïve");
+    }
 }
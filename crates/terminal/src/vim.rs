@@ -0,0 +1,905 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::keymap::Action;
+
+/// Maximum number of undo steps kept per session.
+const MAX_UNDO_STEPS: usize = 100;
+/// Largest count accepted before a command, e.g. `9999x`.
+const MAX_COUNT: usize = 9999;
+/// Largest text a counted put may insert, in characters.
+const MAX_PUT_CHARS: usize = 1 << 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VimMode {
+    Normal,
+    Insert,
+    Visual,
+}
+
+impl VimMode {
+    pub fn label(self) -> &'static str {
+        match self {
+            VimMode::Normal => "NORMAL",
+            VimMode::Insert => "INSERT",
+            VimMode::Visual => "VISUAL",
+        }
+    }
+}
+
+/// What the caller should do after a key has been offered to vi mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VimOutcome {
+    /// The key was consumed.
+    Handled,
+    /// The key is not a vi command and should go through the keymap.
+    Unhandled,
+    /// The key maps onto an existing action, e.g. `j`/`k` to move down/up
+    /// (which falls back to history at the first and last line).
+    Action(Action),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Motion {
+    Left,
+    Right,
+    WordForward,
+    WordBackward,
+    WordEnd,
+    LineStart,
+    FirstNonBlank,
+    LineEnd,
+    FindForward(char),
+    TillForward(char),
+    FindBackward(char),
+    TillBackward(char),
+}
+
+impl Motion {
+    /// Inclusive motions include the character under the target position
+    /// when used with an operator.
+    fn is_inclusive(self) -> bool {
+        matches!(
+            self,
+            Motion::WordEnd | Motion::LineEnd | Motion::FindForward(_) | Motion::TillForward(_)
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operator {
+    Delete,
+    Change,
+    Yank,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Target {
+    Motion(Motion),
+    Line,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Command {
+    Move(Motion),
+    Operate(Operator, Target),
+    Replace(char),
+    Simple(char),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Parsed {
+    Incomplete,
+    Invalid,
+    Complete { count: usize, command: Command },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Snapshot {
+    text: String,
+    cursor: usize,
+}
+
+/// Modal vi-style editing state for the input box.
+#[derive(Debug, Clone)]
+pub struct Vim {
+    pub mode: VimMode,
+    pending: Vec<char>,
+    visual_anchor: usize,
+    register: String,
+    register_linewise: bool,
+    undo_stack: Vec<Snapshot>,
+    redo_stack: Vec<Snapshot>,
+}
+
+impl Default for Vim {
+    fn default() -> Self {
+        Self {
+            mode: VimMode::Insert,
+            pending: Vec::new(),
+            visual_anchor: 0,
+            register: String::new(),
+            register_linewise: false,
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+        }
+    }
+}
+
+impl Vim {
+    /// The partially typed command, shown next to the mode indicator.
+    pub fn pending(&self) -> String {
+        self.pending.iter().collect()
+    }
+
+    /// Inclusive character range selected in visual mode.
+    pub fn selection(&self, cursor: usize) -> Option<(usize, usize)> {
+        (self.mode == VimMode::Visual).then(|| {
+            (
+                self.visual_anchor.min(cursor),
+                self.visual_anchor.max(cursor),
+            )
+        })
+    }
+
+    /// Leaves insert mode, stepping the cursor back onto the last character
+    /// typed as vi does.
+    pub fn enter_normal(&mut self, text: &str, cursor: &mut usize) {
+        self.mode = VimMode::Normal;
+        self.pending.clear();
+        let chars = text.chars().collect::<Vec<_>>();
+        if *cursor > line_start(&chars, *cursor) {
+            *cursor -= 1;
+        }
+    }
+
+    /// Forgets undo history, e.g. after the input has been submitted.
+    pub fn reset(&mut self) {
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+        self.pending.clear();
+        self.mode = VimMode::Insert;
+    }
+
+    /// Offers a key to vi mode. Insert mode only reacts to leaving it, which
+    /// callers do through `enter_normal` on the cancel action.
+    pub fn handle_key(
+        &mut self,
+        text: &mut String,
+        cursor: &mut usize,
+        key: KeyEvent,
+    ) -> VimOutcome {
+        if self.mode == VimMode::Insert {
+            return VimOutcome::Unhandled;
+        }
+
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Esc => {
+                self.pending.clear();
+                self.mode = VimMode::Normal;
+                VimOutcome::Handled
+            }
+            KeyCode::Char('r') if ctrl && self.pending.is_empty() => {
+                self.redo(text, cursor);
+                VimOutcome::Handled
+            }
+            KeyCode::Backspace if self.pending.is_empty() => {
+                *cursor = cursor.saturating_sub(1);
+                VimOutcome::Handled
+            }
+            KeyCode::Char(c) if !ctrl && !key.modifiers.contains(KeyModifiers::ALT) => {
+                self.pending.push(c);
+                self.run_pending(text, cursor)
+            }
+            _ => VimOutcome::Unhandled,
+        }
+    }
+
+    fn run_pending(&mut self, text: &mut String, cursor: &mut usize) -> VimOutcome {
+        // Operators act on the selection straight away in visual mode.
+        let parsed = match self.pending.as_slice() {
+            [c @ ('d' | 'c' | 'y')] if self.mode == VimMode::Visual => Parsed::Complete {
+                count: 1,
+                command: Command::Simple(*c),
+            },
+            keys => parse(keys),
+        };
+        let (count, command) = match parsed {
+            Parsed::Incomplete => return VimOutcome::Handled,
+            Parsed::Invalid => {
+                self.pending.clear();
+                return VimOutcome::Handled;
+            }
+            Parsed::Complete { count, command } => (count, command),
+        };
+        self.pending.clear();
+
+        let outcome = if self.mode == VimMode::Visual {
+            self.run_visual(text, cursor, count, command)
+        } else {
+            self.run_normal(text, cursor, count, command)
+        };
+
+        if self.mode != VimMode::Insert {
+            clamp_normal_cursor(text, cursor);
+        }
+        outcome
+    }
+
+    fn run_normal(
+        &mut self,
+        text: &mut String,
+        cursor: &mut usize,
+        count: usize,
+        command: Command,
+    ) -> VimOutcome {
+        let mut chars = text.chars().collect::<Vec<_>>();
+
+        match command {
+            Command::Move(motion) => {
+                if let Some(target) = apply_motion(&chars, *cursor, motion, count) {
+                    *cursor = target;
+                }
+            }
+            Command::Operate(operator, target) => {
+                let Some((start, end)) = operator_range(&chars, *cursor, operator, target, count)
+                else {
+                    return VimOutcome::Handled;
+                };
+                self.register = chars[start..end].iter().collect();
+                self.register_linewise = target == Target::Line;
+
+                if operator == Operator::Yank {
+                    *cursor = start.min(*cursor);
+                    return VimOutcome::Handled;
+                }
+
+                self.snapshot(text, *cursor);
+                // Changing whole lines keeps the line itself for the new text.
+                let end = if operator == Operator::Change
+                    && target == Target::Line
+                    && chars[start..end].last() == Some(&'\n')
+                {
+                    end - 1
+                } else {
+                    end
+                };
+                chars.drain(start..end);
+                *cursor = start;
+                if operator == Operator::Change {
+                    self.mode = VimMode::Insert;
+                }
+            }
+            Command::Replace(c) => {
+                if cursor.saturating_add(count) > line_end(&chars, *cursor) {
+                    return VimOutcome::Handled;
+                }
+                self.snapshot(text, *cursor);
+                for offset in 0..count {
+                    chars[*cursor + offset] = c;
+                }
+                *cursor += count - 1;
+            }
+            Command::Simple(c) => return self.run_simple(text, cursor, count, c),
+        }
+
+        *text = chars.into_iter().collect();
+        VimOutcome::Handled
+    }
+
+    fn run_simple(
+        &mut self,
+        text: &mut String,
+        cursor: &mut usize,
+        count: usize,
+        command: char,
+    ) -> VimOutcome {
+        let mut chars = text.chars().collect::<Vec<_>>();
+        let start = line_start(&chars, *cursor);
+        let end = line_end(&chars, *cursor);
+
+        match command {
+            'j' => return VimOutcome::Action(Action::MoveDown),
+            'k' => return VimOutcome::Action(Action::MoveUp),
+            'u' => {
+                for _ in 0..count.min(self.undo_stack.len()) {
+                    self.undo(text, cursor);
+                }
+                return VimOutcome::Handled;
+            }
+            'v' => {
+                self.mode = VimMode::Visual;
+                self.visual_anchor = *cursor;
+                return VimOutcome::Handled;
+            }
+            'i' | 'a' | 'I' | 'A' | 'o' | 'O' => {
+                self.snapshot(text, *cursor);
+                self.mode = VimMode::Insert;
+                match command {
+                    'a' => *cursor = (*cursor + 1).min(end),
+                    'I' => *cursor = first_non_blank(&chars, start),
+                    'A' => *cursor = end,
+                    'o' => {
+                        chars.insert(end, '\n');
+                        *cursor = end + 1;
+                    }
+                    'O' => {
+                        chars.insert(start, '\n');
+                        *cursor = start;
+                    }
+                    _ => {}
+                }
+            }
+            'x' | 'X' | 'D' | 'C' | 's' | 'S' => {
+                let (from, to) = match command {
+                    'x' | 's' => (*cursor, cursor.saturating_add(count).min(end)),
+                    'X' => (cursor.saturating_sub(count).max(start), *cursor),
+                    'D' | 'C' => (*cursor, end),
+                    _ => (start, end),
+                };
+                self.snapshot(text, *cursor);
+                self.register = chars[from..to].iter().collect();
+                self.register_linewise = false;
+                chars.drain(from..to);
+                *cursor = from;
+                if matches!(command, 'C' | 's' | 'S') {
+                    self.mode = VimMode::Insert;
+                }
+            }
+            'p' | 'P' => {
+                if self.register.is_empty() {
+                    return VimOutcome::Handled;
+                }
+                self.snapshot(text, *cursor);
+                let length = self.register.chars().count();
+                let pasted = self
+                    .register
+                    .repeat(count.min(MAX_PUT_CHARS / length).max(1));
+                if self.register_linewise {
+                    let line = pasted.trim_end_matches('\n');
+                    let (at, insert) = if command == 'p' {
+                        (end, format!("\n{line}"))
+                    } else {
+                        (start, format!("{line}\n"))
+                    };
+                    chars.splice(at..at, insert.chars());
+                    *cursor = if command == 'p' { end + 1 } else { start };
+                } else {
+                    let at = if command == 'p' && !chars.is_empty() {
+                        (*cursor + 1).min(chars.len())
+                    } else {
+                        *cursor
+                    };
+                    let length = pasted.chars().count();
+                    chars.splice(at..at, pasted.chars());
+                    *cursor = at + length - 1;
+                }
+            }
+            _ => return VimOutcome::Handled,
+        }
+
+        *text = chars.into_iter().collect();
+        VimOutcome::Handled
+    }
+
+    fn run_visual(
+        &mut self,
+        text: &mut String,
+        cursor: &mut usize,
+        count: usize,
+        command: Command,
+    ) -> VimOutcome {
+        let mut chars = text.chars().collect::<Vec<_>>();
+        let (start, end) = self.selection(*cursor).unwrap_or((*cursor, *cursor));
+        let end = (end + 1).min(chars.len());
+
+        let operator = match command {
+            Command::Move(motion) => {
+                if let Some(target) = apply_motion(&chars, *cursor, motion, count) {
+                    *cursor = target;
+                }
+                return VimOutcome::Handled;
+            }
+            Command::Simple('v') => {
+                self.mode = VimMode::Normal;
+                return VimOutcome::Handled;
+            }
+            Command::Simple('j') => return VimOutcome::Action(Action::MoveDown),
+            Command::Simple('k') => return VimOutcome::Action(Action::MoveUp),
+            Command::Simple('d' | 'x') | Command::Operate(Operator::Delete, _) => Operator::Delete,
+            Command::Simple('c' | 's') | Command::Operate(Operator::Change, _) => Operator::Change,
+            Command::Simple('y') | Command::Operate(Operator::Yank, _) => Operator::Yank,
+            _ => return VimOutcome::Handled,
+        };
+
+        self.register = chars[start..end].iter().collect();
+        self.register_linewise = false;
+        self.mode = VimMode::Normal;
+        *cursor = start;
+
+        if operator != Operator::Yank {
+            self.snapshot(text, start);
+            chars.drain(start..end);
+            *text = chars.into_iter().collect();
+            if operator == Operator::Change {
+                self.mode = VimMode::Insert;
+            }
+        }
+        VimOutcome::Handled
+    }
+
+    fn snapshot(&mut self, text: &str, cursor: usize) {
+        self.undo_stack.push(Snapshot {
+            text: text.to_string(),
+            cursor,
+        });
+        if self.undo_stack.len() > MAX_UNDO_STEPS {
+            self.undo_stack.remove(0);
+        }
+        self.redo_stack.clear();
+    }
+
+    fn undo(&mut self, text: &mut String, cursor: &mut usize) {
+        if let Some(snapshot) = self.undo_stack.pop() {
+            self.redo_stack.push(Snapshot {
+                text: std::mem::replace(text, snapshot.text),
+                cursor: *cursor,
+            });
+            *cursor = snapshot.cursor;
+        }
+    }
+
+    fn redo(&mut self, text: &mut String, cursor: &mut usize) {
+        if let Some(snapshot) = self.redo_stack.pop() {
+            self.undo_stack.push(Snapshot {
+                text: std::mem::replace(text, snapshot.text),
+                cursor: *cursor,
+            });
+            *cursor = snapshot.cursor;
+            clamp_normal_cursor(text, cursor);
+        }
+    }
+}
+
+/// Parses `[count] command`, where operators take `[count] motion` or are
+/// doubled (`dd`) to act on whole lines. Counts multiply, so `2d3w` deletes
+/// six words.
+fn parse(keys: &[char]) -> Parsed {
+    let (count, rest) = parse_count(keys);
+    let Some((&first, rest)) = rest.split_first() else {
+        return Parsed::Incomplete;
+    };
+
+    let command = match first {
+        'd' | 'c' | 'y' => {
+            let operator = match first {
+                'd' => Operator::Delete,
+                'c' => Operator::Change,
+                _ => Operator::Yank,
+            };
+            let (motion_count, rest) = parse_count(rest);
+            let Some((&next, _)) = rest.split_first() else {
+                return Parsed::Incomplete;
+            };
+            let target = if next == first {
+                if rest.len() > 1 {
+                    return Parsed::Invalid;
+                }
+                Target::Line
+            } else {
+                match parse_motion(rest) {
+                    Parsed::Complete {
+                        command: Command::Move(motion),
+                        ..
+                    } => Target::Motion(motion),
+                    other => return other,
+                }
+            };
+            return Parsed::Complete {
+                count: count.saturating_mul(motion_count).min(MAX_COUNT),
+                command: Command::Operate(operator, target),
+            };
+        }
+        'r' => match rest {
+            [] => return Parsed::Incomplete,
+            [c] => Command::Replace(*c),
+            _ => return Parsed::Invalid,
+        },
+        'i' | 'a' | 'I' | 'A' | 'o' | 'O' | 'x' | 'X' | 'D' | 'C' | 's' | 'S' | 'p' | 'P' | 'u'
+        | 'v' | 'j' | 'k'
+            if rest.is_empty() =>
+        {
+            Command::Simple(first)
+        }
+        _ => {
+            return match parse_motion(&keys[keys.len() - rest.len() - 1..]) {
+                Parsed::Complete { command, .. } => Parsed::Complete { count, command },
+                other => other,
+            };
+        }
+    };
+    Parsed::Complete { count, command }
+}
+
+fn parse_count(keys: &[char]) -> (usize, &[char]) {
+    let digits = match keys.first() {
+        Some('1'..='9') => keys.iter().take_while(|c| c.is_ascii_digit()).count(),
+        _ => 0,
+    };
+    let count = keys[..digits]
+        .iter()
+        .filter_map(|c| c.to_digit(10))
+        .fold(0usize, |count, digit| {
+            count.saturating_mul(10).saturating_add(digit as usize)
+        })
+        .min(MAX_COUNT);
+    (count.max(1), &keys[digits..])
+}
+
+fn parse_motion(keys: &[char]) -> Parsed {
+    let motion = match keys {
+        [] => return Parsed::Incomplete,
+        ['f' | 't' | 'F' | 'T'] => return Parsed::Incomplete,
+        ['f', c] => Motion::FindForward(*c),
+        ['t', c] => Motion::TillForward(*c),
+        ['F', c] => Motion::FindBackward(*c),
+        ['T', c] => Motion::TillBackward(*c),
+        ['h'] => Motion::Left,
+        ['l' | ' '] => Motion::Right,
+        ['w'] => Motion::WordForward,
+        ['b'] => Motion::WordBackward,
+        ['e'] => Motion::WordEnd,
+        ['0'] => Motion::LineStart,
+        ['^'] => Motion::FirstNonBlank,
+        ['$'] => Motion::LineEnd,
+        _ => return Parsed::Invalid,
+    };
+    Parsed::Complete {
+        count: 1,
+        command: Command::Move(motion),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CharClass {
+    Space,
+    Word,
+    Punctuation,
+}
+
+fn class(c: char) -> CharClass {
+    if c.is_whitespace() {
+        CharClass::Space
+    } else if c.is_alphanumeric() || c == '_' {
+        CharClass::Word
+    } else {
+        CharClass::Punctuation
+    }
+}
+
+fn line_start(chars: &[char], cursor: usize) -> usize {
+    chars[..cursor.min(chars.len())]
+        .iter()
+        .rposition(|&c| c == '\n')
+        .map_or(0, |index| index + 1)
+}
+
+/// Index of the newline ending the cursor's line, or the text length.
+fn line_end(chars: &[char], cursor: usize) -> usize {
+    chars[cursor.min(chars.len())..]
+        .iter()
+        .position(|&c| c == '\n')
+        .map_or(chars.len(), |offset| cursor + offset)
+}
+
+fn first_non_blank(chars: &[char], start: usize) -> usize {
+    let end = line_end(chars, start);
+    chars[start..end]
+        .iter()
+        .position(|c| !c.is_whitespace())
+        .map_or(end, |offset| start + offset)
+}
+
+fn apply_motion(chars: &[char], cursor: usize, motion: Motion, count: usize) -> Option<usize> {
+    let start = line_start(chars, cursor);
+    let end = line_end(chars, cursor);
+    let last = end.saturating_sub(1).max(start);
+
+    let target = match motion {
+        Motion::Left => cursor.saturating_sub(count).max(start),
+        Motion::Right => cursor.saturating_add(count).min(end),
+        Motion::LineStart => start,
+        Motion::FirstNonBlank => first_non_blank(chars, start),
+        Motion::LineEnd => last,
+        Motion::WordForward => repeat_motion(cursor, count, |at| next_word_start(chars, at)),
+        Motion::WordBackward => repeat_motion(cursor, count, |at| previous_word_start(chars, at)),
+        Motion::WordEnd => repeat_motion(cursor, count, |at| next_word_end(chars, at)),
+        Motion::FindForward(c) | Motion::TillForward(c) => {
+            let mut at = cursor;
+            for _ in 0..count {
+                at += 1 + chars.get(at + 1..end)?.iter().position(|&x| x == c)?;
+            }
+            if matches!(motion, Motion::TillForward(_)) {
+                at - 1
+            } else {
+                at
+            }
+        }
+        Motion::FindBackward(c) | Motion::TillBackward(c) => {
+            let mut at = cursor;
+            for _ in 0..count {
+                at = start + chars.get(start..at)?.iter().rposition(|&x| x == c)?;
+            }
+            if matches!(motion, Motion::TillBackward(_)) {
+                at + 1
+            } else {
+                at
+            }
+        }
+    };
+    Some(target)
+}
+
+/// Applies `step` up to `count` times, stopping early once it no longer moves.
+fn repeat_motion(cursor: usize, count: usize, step: impl Fn(usize) -> usize) -> usize {
+    let mut at = cursor;
+    for _ in 0..count {
+        let next = step(at);
+        if next == at {
+            break;
+        }
+        at = next;
+    }
+    at
+}
+
+fn next_word_start(chars: &[char], cursor: usize) -> usize {
+    let mut at = cursor;
+    if let Some(&c) = chars.get(at) {
+        let current = class(c);
+        while at < chars.len() && class(chars[at]) == current && current != CharClass::Space {
+            at += 1;
+        }
+    }
+    while at < chars.len() && class(chars[at]) == CharClass::Space {
+        at += 1;
+    }
+    at
+}
+
+fn previous_word_start(chars: &[char], cursor: usize) -> usize {
+    let mut at = cursor;
+    while at > 0 && class(chars[at - 1]) == CharClass::Space {
+        at -= 1;
+    }
+    if at > 0 {
+        let current = class(chars[at - 1]);
+        while at > 0 && class(chars[at - 1]) == current {
+            at -= 1;
+        }
+    }
+    at
+}
+
+fn next_word_end(chars: &[char], cursor: usize) -> usize {
+    let mut at = cursor + 1;
+    while at < chars.len() && class(chars[at]) == CharClass::Space {
+        at += 1;
+    }
+    if at >= chars.len() {
+        return chars.len().saturating_sub(1).max(cursor);
+    }
+    let current = class(chars[at]);
+    while at + 1 < chars.len() && class(chars[at + 1]) == current {
+        at += 1;
+    }
+    at
+}
+
+/// Half-open character range an operator acts on, or `None` when the
+/// motion fails (e.g. `df` for a character not on the line).
+fn operator_range(
+    chars: &[char],
+    cursor: usize,
+    operator: Operator,
+    target: Target,
+    count: usize,
+) -> Option<(usize, usize)> {
+    match target {
+        Target::Line => {
+            let start = line_start(chars, cursor);
+            let mut end = line_end(chars, cursor);
+            for _ in 1..count {
+                if end >= chars.len() {
+                    break;
+                }
+                end = line_end(chars, end + 1);
+            }
+            if end < chars.len() {
+                Some((start, end + 1))
+            } else if start > 0 && operator != Operator::Change {
+                // Deleting the last line also removes the newline before it.
+                Some((start - 1, end))
+            } else {
+                Some((start, end))
+            }
+        }
+        Target::Motion(motion) => {
+            // `cw` on a word changes to the end of the word, as in vi, and
+            // only the character under the cursor when it ends a word.
+            let change_word = operator == Operator::Change
+                && motion == Motion::WordForward
+                && chars
+                    .get(cursor)
+                    .is_some_and(|&c| class(c) != CharClass::Space);
+            let motion = if change_word { Motion::WordEnd } else { motion };
+            let ends_word = chars
+                .get(cursor + 1)
+                .is_none_or(|&next| class(next) != class(chars[cursor]));
+            let destination = if change_word && count == 1 && ends_word {
+                cursor
+            } else {
+                apply_motion(chars, cursor, motion, count)?
+            };
+
+            let (start, mut end) = if destination < cursor {
+                (destination, cursor)
+            } else {
+                (cursor, destination)
+            };
+            if motion.is_inclusive() {
+                end = (end + 1).min(chars.len());
+            }
+            // Word motions stop at the end of the line rather than eating it.
+            if motion == Motion::WordForward {
+                end = end.min(line_end(chars, cursor));
+            }
+            (start < end).then_some((start, end))
+        }
+    }
+}
+
+/// Keeps the normal-mode cursor on a character rather than past the end of
+/// its line.
+fn clamp_normal_cursor(text: &str, cursor: &mut usize) {
+    let chars = text.chars().collect::<Vec<_>>();
+    let start = line_start(&chars, *cursor);
+    let end = line_end(&chars, *cursor);
+    if *cursor >= end && end > start {
+        *cursor = end - 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Runs `keys` in normal mode against `text` with the cursor at `cursor`.
+    fn run(text: &str, cursor: usize, keys: &str) -> (Vim, String, usize) {
+        let mut vim = Vim {
+            mode: VimMode::Normal,
+            ..Vim::default()
+        };
+        let mut text = text.to_string();
+        let mut cursor = cursor;
+        for c in keys.chars() {
+            let key = KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE);
+            vim.handle_key(&mut text, &mut cursor, key);
+        }
+        (vim, text, cursor)
+    }
+
+    #[test]
+    fn test_vim_motions() {
+        // Test word, line and find motions with counts
+        let text = "let value = foo(bar);";
+        assert_eq!(run(text, 0, "w").2, 4);
+        assert_eq!(run(text, 0, "3w").2, 12);
+        assert_eq!(run(text, 12, "b").2, 10);
+        assert_eq!(run(text, 0, "e").2, 2);
+        assert_eq!(run(text, 5, "0").2, 0);
+        assert_eq!(run(text, 0, "$").2, 20);
+        assert_eq!(run(text, 0, "f(").2, 15);
+        assert_eq!(run(text, 0, "t(").2, 14);
+        assert_eq!(run(text, 20, "F=").2, 10);
+        assert_eq!(run(text, 0, "fz").2, 0);
+    }
+
+    #[test]
+    fn test_vim_operators() {
+        // Test delete, change and yank with motions, counts and line targets
+        assert_eq!(run("one two three", 0, "dw").1, "two three");
+        assert_eq!(run("one two three", 0, "2dw").1, "three");
+        assert_eq!(run("one two three", 0, "d2w").1, "three");
+        assert_eq!(run("one two three", 4, "d$").1, "one ");
+        assert_eq!(run("call(a, b)", 0, "dt(").1, "(a, b)");
+        assert_eq!(run("first\nsecond\nthird", 7, "dd").1, "first\nthird");
+        assert_eq!(run("first\nsecond", 7, "dd").1, "first");
+
+        let (vim, text, cursor) = run("one two", 0, "cw");
+        assert_eq!(vim.mode, VimMode::Insert);
+        assert_eq!((text.as_str(), cursor), (" two", 0));
+
+        let (_, text, _) = run("one two", 0, "yeP");
+        assert_eq!(text, "oneone two");
+        let (_, text, _) = run("one two", 4, "xp");
+        assert_eq!(text, "one wto");
+    }
+
+    #[test]
+    fn test_vim_undo_redo() {
+        // Test undo restores earlier text and Ctrl+R reapplies it
+        let (mut vim, mut text, mut cursor) = run("one two three", 0, "dwdw");
+        assert_eq!(text, "three");
+
+        let u = KeyEvent::new(KeyCode::Char('u'), KeyModifiers::NONE);
+        vim.handle_key(&mut text, &mut cursor, u);
+        assert_eq!(text, "two three");
+        vim.handle_key(&mut text, &mut cursor, u);
+        assert_eq!(text, "one two three");
+
+        let redo = KeyEvent::new(KeyCode::Char('r'), KeyModifiers::CONTROL);
+        vim.handle_key(&mut text, &mut cursor, redo);
+        assert_eq!(text, "two three");
+    }
+
+    #[test]
+    fn test_vim_huge_counts_are_capped() {
+        // Test oversized counts neither overflow nor run unbounded
+        let digits = "9".repeat(40);
+        assert_eq!(
+            parse_count(&digits.chars().collect::<Vec<_>>()).0,
+            MAX_COUNT
+        );
+        assert_eq!(run("one two", 0, &format!("{digits}l")).2, 6);
+        assert_eq!(run("one two", 0, &format!("{digits}w")).2, 6);
+        assert_eq!(run("one two", 0, &format!("{digits}x")).1, "");
+        assert_eq!(run("one two", 0, "9999d9999w").1, "");
+        assert_eq!(run("ab", 0, &format!("{digits}ra")).1, "ab");
+
+        let (_, text, _) = run("one two", 0, &format!("dw{digits}u"));
+        assert_eq!(text, "one two");
+        let (_, text, _) = run("ab", 0, "x9999p");
+        assert_eq!(text.len(), 10_000);
+    }
+
+    #[test]
+    fn test_vim_visual_mode() {
+        // Test visual selections extend with motions and feed operators
+        let (vim, _, cursor) = run("hello world", 0, "ve");
+        assert_eq!(vim.selection(cursor), Some((0, 4)));
+
+        let (vim, text, _) = run("hello world", 0, "ved");
+        assert_eq!(text, " world");
+        assert_eq!(vim.mode, VimMode::Normal);
+
+        let (vim, text, _) = run("hello world", 6, "v$c");
+        assert_eq!(text, "hello ");
+        assert_eq!(vim.mode, VimMode::Insert);
+    }
+
+    #[test]
+    fn test_vim_insert_commands_and_history_keys() {
+        // Test insert entry points and j/k delegating to movement actions
+        assert_eq!(run("abc", 1, "a").2, 2);
+        assert_eq!(run("  abc", 4, "I").2, 2);
+        assert_eq!(run("abc", 0, "A").2, 3);
+        assert_eq!(run("abc", 0, "o").1, "abc\n");
+
+        let mut vim = Vim {
+            mode: VimMode::Normal,
+            ..Vim::default()
+        };
+        let (mut text, mut cursor) = (String::from("abc"), 0);
+        let j = KeyEvent::new(KeyCode::Char('j'), KeyModifiers::NONE);
+        assert_eq!(
+            vim.handle_key(&mut text, &mut cursor, j),
+            VimOutcome::Action(Action::MoveDown)
+        );
+        let enter = KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE);
+        assert_eq!(
+            vim.handle_key(&mut text, &mut cursor, enter),
+            VimOutcome::Unhandled
+        );
+    }
+}
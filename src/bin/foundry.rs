@@ -1,17 +1,512 @@
-use clap::Command;
-use std::env;
+use anyhow::Context;
+use clap::builder::PossibleValuesParser;
+use clap::{Args, CommandFactory, Parser, Subcommand, ValueEnum};
+use clap_complete::engine::{ArgValueCandidates, ArgValueCompleter, CompletionCandidate};
+use clap_complete::env::{CompleteEnv, Shells};
+use foundry_core::config::{ConfigFile, ConfigSource, LayeredConfig};
+use foundry_core::config_schema::json_schema;
+use foundry_core::instructions::Instructions;
+use foundry_core::permissions::PermissionPolicy;
+use foundry_core::sessions::SessionStore;
+use foundry_terminal::ScreenMode;
+use foundry_terminal::headless::{self, InputFormat, OutputFormat, PrintRequest};
+use foundry_terminal::{commands, doctor};
+use identity::LoginMethod;
+use identity::credentials::API_KEY_ENV;
+use std::ffi::OsStr;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::process::ExitCode;
+use toml::Value;
 
-fn main() -> anyhow::Result<()> {
-    let args: Vec<String> = env::args().collect();
+#[derive(Parser)]
+#[command(name = "foundry", version, about = "Azure AI Foundry Code")]
+struct Cli {
+    /// Render below the prompt and keep the conversation in the terminal scrollback
+    #[arg(long, conflicts_with = "print")]
+    inline: bool,
 
-    if args.len() > 1 {
-        let _matches = Command::new("foundry")
-            .version(env!("CARGO_PKG_VERSION"))
-            .about("Azure AI Foundry Code")
-            .get_matches();
+    /// Answer PROMPT without the TUI, with any piped stdin as extra context
+    #[arg(
+        short,
+        long,
+        value_name = "PROMPT",
+        num_args = 0..=1,
+        default_missing_value = ""
+    )]
+    print: Option<String>,
+
+    /// Tools a headless run may use without approval
+    #[arg(long, value_name = "MODE", requires = "print", value_enum)]
+    permission_mode: Option<PermissionPolicy>,
+
+    /// How a headless run reports its answer
+    #[arg(long, value_name = "FORMAT", requires = "print", value_enum)]
+    output_format: Option<OutputFormat>,
+
+    /// How a headless run receives prompts; stream-json keeps reading messages from stdin
+    #[arg(long, value_name = "FORMAT", requires = "print", value_enum)]
+    input_format: Option<InputFormat>,
+
+    /// Switch endpoint, deployment, tenant, auth method, permissions and saved
+    /// credentials to a `[profile.<name>]`
+    #[arg(long, value_name = "NAME", add = ArgValueCandidates::new(profile_candidates))]
+    profile: Option<String>,
+
+    /// Use a protected profile, including a configured `default_profile`,
+    /// without asking for confirmation
+    #[arg(long)]
+    confirm_profile: bool,
+
+    /// Override a setting for this run, e.g. `-c limits.max_turns=10`
+    #[arg(
+        short = 'c',
+        long = "config",
+        value_name = "KEY=VALUE",
+        value_parser = parse_override,
+        add = ArgValueCompleter::new(override_candidates)
+    )]
+    overrides: Vec<(String, String)>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Sign in to Azure AI Foundry
+    Login(LoginArgs),
+    /// Sign out and forget saved credentials
+    Logout,
+    /// Show the signed-in account, its tenant and when the token expires
+    Whoami,
+    /// Read and change settings in the user config file
+    #[command(subcommand)]
+    Config(ConfigCommand),
+    /// Model deployments
+    #[command(subcommand)]
+    Models(ModelsCommand),
+    /// Sessions recorded in this project
+    #[command(subcommand)]
+    Sessions(SessionsCommand),
+    /// Check the terminal, config, credentials and connection to the endpoint
+    Doctor {
+        /// Skip the DNS, TLS and clock checks that need the network
+        #[arg(long)]
+        offline: bool,
+        /// Print the report as JSON, e.g. for bug reports
+        #[arg(long)]
+        json: bool,
+    },
+    /// Print a script that enables tab completion, e.g. `source <(foundry completions bash)`
+    Completions {
+        #[arg(value_parser = PossibleValuesParser::new(SHELLS))]
+        shell: String,
+    },
+    /// Print the man page in roff format
+    Man,
+}
+
+/// Shells `foundry completions` can write a script for.
+const SHELLS: &[&str] = &["bash", "zsh", "fish", "powershell"];
+
+#[derive(Args)]
+#[group(multiple = false)]
+struct LoginArgs {
+    /// Sign in with a device code, for machines without a browser
+    #[arg(long)]
+    device: bool,
+    /// Sign in in the browser (the default)
+    #[arg(long)]
+    browser: bool,
+    /// Use an API key, read from FOUNDRY_API_KEY or stdin
+    #[arg(long)]
+    key: bool,
+}
+
+#[derive(Subcommand)]
+enum ConfigCommand {
+    /// Print the effective value of one setting
+    Get { key: String },
+    /// Change one setting in the user config file
+    Set {
+        key: String,
+        value: String,
+        /// Write to the project file, `.foundry/config.toml`, instead
+        #[arg(long, conflicts_with = "local")]
+        project: bool,
+        /// Write to the local file, `.foundry/config.local.toml`, instead
+        #[arg(long)]
+        local: bool,
+    },
+    /// Print every effective setting and where it came from
+    List,
+    /// Print a JSON Schema of the config files, for editor completion
+    Schema,
+}
+
+#[derive(Subcommand)]
+enum ModelsCommand {
+    /// List configured model deployments
+    List,
+}
+
+#[derive(Subcommand)]
+enum SessionsCommand {
+    /// List recorded sessions, newest first
+    List,
+    /// Print the transcript of a session
+    Show {
+        /// Session id, or a unique prefix of it
+        #[arg(add = ArgValueCandidates::new(session_candidates))]
+        id: String,
+    },
+    /// Delete a recorded session
+    Rm {
+        /// Session id, or a unique prefix of it
+        #[arg(add = ArgValueCandidates::new(session_candidates))]
+        id: String,
+    },
+}
+
+fn main() -> anyhow::Result<ExitCode> {
+    // Answers the shell when it asks for completions, then exits.
+    CompleteEnv::with_factory(Cli::command).complete();
+    let cli = Cli::parse();
+    let overrides = cli_overrides(&cli)?;
+
+    if let Some(command) = cli.command {
+        return run_command(
+            command,
+            overrides,
+            cli.profile.as_deref(),
+            cli.confirm_profile,
+        );
+    }
+
+    if let Some(prompt) = cli.print {
+        let config = load_config(overrides)?;
+        check_profile(&config, cli.profile.as_deref(), cli.confirm_profile)?;
+        let input_format = cli.input_format.unwrap_or_default();
+        // Streamed input is read message by message while the session runs.
+        let stdin = match input_format {
+            InputFormat::Text => headless::read_piped_stdin()?,
+            InputFormat::StreamJson => None,
+        };
+        let request = PrintRequest {
+            prompt,
+            stdin,
+            permissions: match cli.permission_mode {
+                Some(policy) => policy,
+                None => config
+                    .get_str("permission_mode")
+                    .map(|name| PermissionPolicy::from_str(name, false))
+                    .transpose()
+                    .map_err(anyhow::Error::msg)
+                    .context("Invalid permission_mode setting")?
+                    .unwrap_or_default(),
+            },
+            format: cli.output_format.unwrap_or_default(),
+            input_format,
+            model: config.get_str("deployment").map(str::to_string),
+            deployments: commands::deployment_names(&config),
+            instructions: load_instructions()?,
+        };
+        return Ok(ExitCode::from(headless::run_print(&request)?));
+    }
+
+    // The TUI shows config problems in its status bar.
+    let config = load_config_quietly(overrides)?;
+    check_profile(&config, cli.profile.as_deref(), cli.confirm_profile)?;
+    let mode = if config.get_bool("inline").unwrap_or(false) {
+        ScreenMode::Inline
+    } else {
+        ScreenMode::Fullscreen
+    };
+    foundry_terminal::run(mode, config)?;
+
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Runs a subcommand. Those that act on the active profile check it first,
+/// like a session does.
+fn run_command(
+    command: Command,
+    overrides: ConfigFile,
+    profile: Option<&str>,
+    confirm_profile: bool,
+) -> anyhow::Result<ExitCode> {
+    let load_profile_config = |overrides| {
+        let config = load_config(overrides)?;
+        check_profile(&config, profile, confirm_profile)?;
+        anyhow::Ok(config)
+    };
+    let output = match command {
+        Command::Login(args) => {
+            let config = load_profile_config(overrides)?;
+            let method = if args.device {
+                LoginMethod::Device
+            } else if args.key {
+                LoginMethod::ApiKey(read_api_key()?)
+            } else if args.browser {
+                LoginMethod::Browser
+            } else {
+                configured_login_method(&config)?
+            };
+            commands::login(
+                &commands::credential_store(&config)?,
+                method,
+                config.get_str("tenant"),
+            )
+        }
+        Command::Logout => {
+            let config = load_profile_config(overrides)?;
+            commands::logout(&commands::credential_store(&config)?)
+        }
+        Command::Whoami => {
+            let config = load_config(overrides)?;
+            commands::whoami(
+                &commands::credential_store(&config)?,
+                config.get_str("tenant"),
+            )
+        }
+        Command::Config(action) => match action {
+            ConfigCommand::Get { key } => commands::config_get(&load_config(overrides)?, &key),
+            ConfigCommand::Set {
+                key,
+                value,
+                project,
+                local,
+            } => {
+                let source = if project {
+                    ConfigSource::Project
+                } else if local {
+                    ConfigSource::Local
+                } else {
+                    ConfigSource::User
+                };
+                commands::config_set(&mut load_config(overrides)?, source, &key, &value)
+            }
+            ConfigCommand::List => Ok(commands::config_list(&load_config(overrides)?)),
+            ConfigCommand::Schema => Ok(serde_json::to_string_pretty(&json_schema())?),
+        },
+        Command::Models(ModelsCommand::List) => {
+            Ok(commands::models_list(&load_profile_config(overrides)?))
+        }
+        Command::Sessions(action) => {
+            let cwd = std::env::current_dir().context("Failed to determine current directory")?;
+            let store = SessionStore::for_project(&cwd)
+                .context("Failed to locate the state directory: no home directory")?;
+            match action {
+                SessionsCommand::List => commands::sessions_list(&store),
+                SessionsCommand::Show { id } => commands::sessions_show(&store, &id),
+                SessionsCommand::Rm { id } => commands::sessions_remove(&store, &id),
+            }
+        }
+        Command::Doctor { offline, json } => {
+            // The doctor reports a config that fails to load as a check.
+            let config = load_config_quietly(overrides);
+            if let Ok(config) = &config {
+                check_profile(config, profile, confirm_profile)?;
+            }
+            return run_doctor(&config, offline, json);
+        }
+        Command::Completions { shell } => {
+            let shells = Shells::builtins();
+            let completer = shells.completer(&shell).context("Unsupported shell")?;
+            let mut stdout = io::stdout();
+            completer
+                .write_registration("COMPLETE", "foundry", "foundry", "foundry", &mut stdout)
+                .context("Failed to write the completion script")?;
+            return Ok(ExitCode::SUCCESS);
+        }
+        Command::Man => {
+            clap_mangen::Man::new(Cli::command())
+                .render(&mut io::stdout())
+                .context("Failed to write the man page")?;
+            return Ok(ExitCode::SUCCESS);
+        }
+    }?;
+    println!("{output}");
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Prints the diagnostics, failing the process if any check failed.
+fn run_doctor(
+    config: &anyhow::Result<LayeredConfig>,
+    offline: bool,
+    json: bool,
+) -> anyhow::Result<ExitCode> {
+    let checks = doctor::run_checks(config, offline);
+    if json {
+        println!("{}", doctor::render_json(&checks)?);
+    } else {
+        println!("{}", doctor::render_report(&checks));
+    }
+    Ok(if doctor::has_failures(&checks) {
+        ExitCode::FAILURE
     } else {
-        foundry_terminal::run()?;
+        ExitCode::SUCCESS
+    })
+}
+
+/// Settings given on the command line, the highest config layer.
+fn cli_overrides(cli: &Cli) -> anyhow::Result<ConfigFile> {
+    let mut overrides = ConfigFile::default();
+    for (key, value) in &cli.overrides {
+        overrides.set(key, value)?;
+    }
+    if let Some(profile) = &cli.profile {
+        overrides.insert("default_profile", Value::String(profile.clone()))?;
     }
+    if cli.inline {
+        overrides.insert("inline", Value::Boolean(true))?;
+    }
+    Ok(overrides)
+}
 
+/// Loads the config, warning about its problems on stderr.
+fn load_config(overrides: ConfigFile) -> anyhow::Result<LayeredConfig> {
+    let config = load_config_quietly(overrides)?;
+    for issue in config.issues() {
+        eprintln!("warning: {issue}");
+    }
+    Ok(config)
+}
+
+fn load_config_quietly(overrides: ConfigFile) -> anyhow::Result<LayeredConfig> {
+    let cwd = std::env::current_dir().context("Failed to determine current directory")?;
+    LayeredConfig::load(&cwd, overrides)
+}
+
+/// Loads the `FOUNDRY.md` files, warning about includes that were skipped.
+fn load_instructions() -> anyhow::Result<Instructions> {
+    let cwd = std::env::current_dir().context("Failed to determine current directory")?;
+    let instructions = Instructions::load(&cwd);
+    for file in &instructions.files {
+        for problem in &file.problems {
+            eprintln!("warning: {}: {problem}", file.path.display());
+        }
+    }
+    Ok(instructions)
+}
+
+fn parse_override(text: &str) -> Result<(String, String), String> {
+    match text.split_once('=') {
+        Some((key, value)) if !key.trim().is_empty() => {
+            Ok((key.trim().to_string(), value.trim().to_string()))
+        }
+        _ => Err("expected KEY=VALUE".to_string()),
+    }
+}
+
+/// Fails when the profile given with `--profile` does not exist, and asks
+/// before a session uses a protected profile. Without a terminal to ask on,
+/// `--confirm-profile` is required.
+fn check_profile(
+    config: &LayeredConfig,
+    requested: Option<&str>,
+    confirmed: bool,
+) -> anyhow::Result<()> {
+    if let Some(requested) = requested
+        && config.active_profile() != Some(requested)
+    {
+        // Switching again reports why, with a suggestion for typos.
+        config.clone().use_profile(Some(requested))?;
+    }
+    let Some(name) = config.active_profile() else {
+        return Ok(());
+    };
+    if confirmed || !config.is_protected(name) {
+        return Ok(());
+    }
+    let stdin = io::stdin();
+    if !stdin.is_terminal() {
+        anyhow::bail!("Profile '{name}' is protected; pass --confirm-profile to use it");
+    }
+    eprint!("Profile '{name}' is protected. Continue? [y/N] ");
+    io::stderr().flush()?;
+    let mut answer = String::new();
+    stdin
+        .lock()
+        .read_line(&mut answer)
+        .context("Failed to read the confirmation")?;
+    if !answer.trim().eq_ignore_ascii_case("y") {
+        anyhow::bail!("Cancelled: profile '{name}' was not confirmed");
+    }
     Ok(())
 }
+
+/// The sign-in method from the `auth_method` setting.
+fn configured_login_method(config: &LayeredConfig) -> anyhow::Result<LoginMethod> {
+    Ok(match config.get_str("auth_method") {
+        None | Some("browser") => LoginMethod::Browser,
+        Some("device") => LoginMethod::Device,
+        Some("azure-cli") => LoginMethod::AzureCli,
+        Some("api-key") => LoginMethod::ApiKey(read_api_key()?),
+        Some(other) => anyhow::bail!(
+            "Unknown auth_method '{other}', expected one of: browser, device, azure-cli, api-key"
+        ),
+    })
+}
+
+/// Deployment names from the config, offered when completing
+/// `-c deployment=`.
+fn override_candidates(current: &OsStr) -> Vec<CompletionCandidate> {
+    let Some(prefix) = current.to_str() else {
+        return Vec::new();
+    };
+    if !prefix.starts_with("deployment=") {
+        return Vec::new();
+    }
+    load_config_quietly(ConfigFile::default())
+        .map(|config| commands::deployment_names(&config))
+        .unwrap_or_default()
+        .into_iter()
+        .map(|name| format!("deployment={name}"))
+        .filter(|candidate| candidate.starts_with(prefix))
+        .map(CompletionCandidate::new)
+        .collect()
+}
+
+/// Profiles defined in the config, offered when completing `--profile`.
+fn profile_candidates() -> Vec<CompletionCandidate> {
+    load_config_quietly(ConfigFile::default())
+        .map(|config| config.profiles())
+        .unwrap_or_default()
+        .into_iter()
+        .map(CompletionCandidate::new)
+        .collect()
+}
+
+/// Sessions recorded in the current project, described by their first prompt.
+fn session_candidates() -> Vec<CompletionCandidate> {
+    let store = std::env::current_dir()
+        .ok()
+        .and_then(|cwd| SessionStore::for_project(&cwd));
+    let sessions = store
+        .and_then(|store| store.list().ok())
+        .unwrap_or_default();
+    sessions
+        .sessions
+        .into_iter()
+        .map(|session| CompletionCandidate::new(session.id).help(session.title.map(Into::into)))
+        .collect()
+}
+
+/// Reads the key from `FOUNDRY_API_KEY`, or the first line of stdin so it
+/// stays out of the shell history. Typed keys are not echoed.
+fn read_api_key() -> anyhow::Result<String> {
+    if let Ok(key) = std::env::var(API_KEY_ENV) {
+        return Ok(key);
+    }
+    let stdin = io::stdin();
+    if stdin.is_terminal() {
+        return rpassword::prompt_password("API key: ").context("Failed to read the API key");
+    }
+    let mut key = String::new();
+    stdin
+        .lock()
+        .read_line(&mut key)
+        .context("Failed to read the API key")?;
+    Ok(key)
+}
@@ -1,4 +1,22 @@
-use std::process::Command;
+use foundry_core::testing::temp_dir;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+fn foundry_command(home: &Path) -> Command {
+    isolated(Command::new(env!("CARGO_BIN_EXE_foundry")), home)
+}
+
+fn isolated(mut command: Command, home: &Path) -> Command {
+    let project = home.join("project");
+    std::fs::create_dir_all(&project).expect("Failed to create the project directory");
+    command
+        .current_dir(project)
+        .env("XDG_CONFIG_HOME", home.join("config"))
+        .env("XDG_STATE_HOME", home.join("state"))
+        .env_remove("FOUNDRY_API_KEY");
+    command
+}
 
 #[test]
 fn test_binary_exists() {
@@ -13,9 +31,16 @@ fn test_binary_exists() {
 
 #[test]
 fn test_no_args_does_not_crash_immediately() {
-    let mut child = Command::new("cargo")
-        .args(["run", "--bin", "foundry"])
-        .current_dir(env!("CARGO_MANIFEST_DIR"))
+    let home = temp_dir();
+    let mut cargo = Command::new("cargo");
+    cargo.args([
+        "run",
+        "--manifest-path",
+        concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.toml"),
+        "--bin",
+        "foundry",
+    ]);
+    let mut child = isolated(cargo, home.path())
         .spawn()
         .expect("Failed to start foundry without arguments");
 
@@ -30,9 +55,9 @@ fn test_no_args_does_not_crash_immediately() {
 
 #[test]
 fn test_help_flag() {
-    let output = Command::new("cargo")
-        .args(["run", "--bin", "foundry", "--", "--help"])
-        .current_dir(env!("CARGO_MANIFEST_DIR"))
+    let home = temp_dir();
+    let output = foundry_command(home.path())
+        .arg("--help")
         .output()
         .expect("Failed to execute foundry --help");
 
@@ -43,9 +68,9 @@ fn test_help_flag() {
 
 #[test]
 fn test_version_flag() {
-    let output = Command::new("cargo")
-        .args(["run", "--bin", "foundry", "--", "--version"])
-        .current_dir(env!("CARGO_MANIFEST_DIR"))
+    let home = temp_dir();
+    let output = foundry_command(home.path())
+        .arg("--version")
         .output()
         .expect("Failed to execute foundry --version");
 
@@ -53,3 +78,149 @@ fn test_version_flag() {
     let stdout = String::from_utf8_lossy(&output.stdout);
     assert!(stdout.contains(env!("CARGO_PKG_VERSION")));
 }
+
+#[test]
+fn test_print_mode_reads_piped_stdin() {
+    let home = temp_dir();
+    let mut child = foundry_command(home.path())
+        .args(["-p", "review this"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("Failed to start foundry -p");
+    child
+        .stdin
+        .take()
+        .expect("stdin is piped")
+        .write_all(b"+ added line\n")
+        .expect("Failed to write stdin");
+    let output = child.wait_with_output().expect("Failed to run foundry -p");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("review this"));
+    assert!(stdout.contains("+ added line"));
+}
+
+#[test]
+fn test_print_mode_without_prompt_is_a_usage_error() {
+    let home = temp_dir();
+    let output = foundry_command(home.path())
+        .arg("-p")
+        .stdin(Stdio::null())
+        .output()
+        .expect("Failed to run foundry -p");
+
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("No prompt given"));
+}
+
+#[test]
+fn test_config_subcommands_round_trip() {
+    let home = temp_dir();
+    let foundry = |args: &[&str]| {
+        foundry_command(home.path())
+            .args(args)
+            .output()
+            .expect("Failed to run foundry")
+    };
+
+    let output = foundry(&["whoami"]);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Not signed in"));
+
+    assert!(
+        foundry(&["config", "set", "deployment", "gpt-4.1"])
+            .status
+            .success()
+    );
+    let output = foundry(&["config", "get", "deployment"]);
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout).trim(),
+        "\"gpt-4.1\""
+    );
+    let output = foundry(&["models", "list"]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("* gpt-4.1"));
+
+    let output = foundry(&["config", "set", "deploymnet", "o3"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("did you mean 'deployment'"));
+    let output = foundry(&["config", "schema"]);
+    let schema = serde_json::from_slice::<serde_json::Value>(&output.stdout).unwrap();
+    assert_eq!(schema["properties"]["deployment"]["type"], "string");
+}
+
+#[test]
+fn test_completions_and_man_page() {
+    let home = temp_dir();
+    let foundry = |args: &[&str], complete: Option<&str>| {
+        let mut command = foundry_command(home.path());
+        command.args(args);
+        if let Some(shell) = complete {
+            command.env("COMPLETE", shell);
+        }
+        command.output().expect("Failed to run foundry")
+    };
+
+    for shell in ["bash", "zsh", "fish", "powershell"] {
+        let output = foundry(&["completions", shell], None);
+        assert!(output.status.success());
+        assert!(String::from_utf8_lossy(&output.stdout).contains("COMPLETE"));
+    }
+    assert!(!foundry(&["completions", "tcsh"], None).status.success());
+
+    let output = foundry(&["man"], None);
+    assert!(String::from_utf8_lossy(&output.stdout).contains(".TH foundry 1"));
+
+    foundry(
+        &["config", "set", "deployments", r#"["gpt-4.1", "o3"]"#],
+        None,
+    );
+    let output = foundry(&["--", "foundry", "-c", "deployment="], Some("fish"));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "deployment=gpt-4.1\ndeployment=o3\n"
+    );
+}
+
+#[test]
+fn test_protected_profile_needs_confirmation() {
+    let home = temp_dir();
+    let foundry = |args: &[&str]| {
+        foundry_command(home.path())
+            .args(args)
+            .stdin(Stdio::null())
+            .output()
+            .expect("Failed to run foundry")
+    };
+
+    assert!(
+        foundry(&["config", "set", "profile.prod.deployment", "o3"])
+            .status
+            .success()
+    );
+    assert!(
+        foundry(&["config", "set", "profile.prod.protected", "true"])
+            .status
+            .success()
+    );
+
+    let output = foundry(&["--profile", "prod", "-p", "hi"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--confirm-profile"));
+    let output = foundry(&["--profile", "prod", "--confirm-profile", "-p", "hi"]);
+    assert!(output.status.success());
+    let output = foundry(&["--profile", "prd", "-p", "hi"]);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("did you mean 'prod'?"));
+
+    foundry(&["config", "set", "default_profile", "prod"]);
+    let output = foundry(&["models", "list"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--confirm-profile"));
+    assert!(
+        foundry(&["--confirm-profile", "models", "list"])
+            .status
+            .success()
+    );
+}